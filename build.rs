@@ -1,21 +1,145 @@
+use std::collections::BTreeMap;
 use std::env;
 use std::fs;
 use std::path::Path;
 
+/// Networks with a checked-in `metadata_<network>.rs` snapshot, each selected by the cargo feature of the same name.
+const NETWORKS: &[&str] = &["finney", "testnet", "local"];
+
+const DEFAULT_NETWORK: &str = "finney";
+
 fn main() {
     let out_dir = env::var_os("OUT_DIR").unwrap();
     let metadata_path = Path::new(&out_dir).join("metadata.rs");
 
+    let enabled: Vec<&str> = NETWORKS
+        .iter()
+        .copied()
+        .filter(|network| {
+            env::var_os(format!("CARGO_FEATURE_{}", network.to_ascii_uppercase())).is_some()
+        })
+        .collect();
+
+    // Non-default networks take precedence, so enabling one works without disabling default features
+    let selected: Vec<&str> = match enabled.as_slice() {
+        [] => vec![DEFAULT_NETWORK],
+        [network] => vec![*network],
+        _ => enabled
+            .iter()
+            .copied()
+            .filter(|network| *network != DEFAULT_NETWORK)
+            .collect(),
+    };
+
+    if let [first, second, ..] = selected.as_slice() {
+        fs::write(
+            &metadata_path,
+            format!(
+                "compile_error!(\"The {first} and {second} network features are mutually exclusive, enable only \
+                 one\");\n"
+            ),
+        )
+        .expect("Failed to write metadata");
+
+        return;
+    }
+
+    let network = selected[0];
+
     // Use pre-generated metadata instead of fetching from network
     // This avoids metadata version compatibility issues
-    let source_path = "metadata_finney.rs";
+    let source_path = format!("metadata_{network}.rs");
+
+    println!("cargo:rerun-if-changed={source_path}");
+
+    let Ok(metadata) = fs::read_to_string(&source_path) else {
+        fs::write(
+            &metadata_path,
+            format!(
+                "compile_error!(\"{source_path} is not checked in. Generate it from the {network} network's metadata \
+                 with `cargo run -p crabtensor-codegen -- <metadata.scale> {source_path}`\");\n"
+            ),
+        )
+        .expect("Failed to write metadata");
+
+        return;
+    };
+
+    // Snapshots generated by crabtensor-codegen record the spec version they were generated from on the first line
+    let spec_version = metadata
+        .lines()
+        .next()
+        .and_then(|line| line.split("spec version ").nth(1))
+        .and_then(|version| version.trim().parse::<u32>().ok());
+
+    let spec_version = match spec_version {
+        Some(version) => format!("Some({version})"),
+        None => {
+            println!(
+                "cargo:warning={source_path} does not record its spec version, so check_runtime can only compare \
+                 metadata hashes. Regenerate it with crabtensor-codegen."
+            );
 
-    fs::copy(source_path, &metadata_path)
-        .expect("Failed to copy pre-generated metadata. Make sure metadata_finney.rs exists.");
+            "None".to_string()
+        }
+    };
+
+    let calls = validation_hashes(&metadata, "tx::payload::StaticPayload::new_static(");
+    let storage = validation_hashes(&metadata, "storage::address::StaticAddress::new_static(");
+    let constants = validation_hashes(&metadata, "constants::address::StaticAddress::new_static(");
+
+    fs::write(
+        &metadata_path,
+        format!(
+            "{metadata}\npub const METADATA_NETWORK: &str = {network:?};\n\
+             pub const METADATA_SPEC_VERSION: Option<u32> = {spec_version};\n\
+             pub const METADATA_CALL_HASHES: &[(&str, &str, [u8; 32])] = &{calls};\n\
+             pub const METADATA_STORAGE_HASHES: &[(&str, &str, [u8; 32])] = &{storage};\n\
+             pub const METADATA_CONSTANT_HASHES: &[(&str, &str, [u8; 32])] = &{constants};\n"
+        ),
+    )
+    .expect("Failed to write metadata");
 
     println!(
         "cargo:warning=Using pre-generated metadata from {}",
         source_path
     );
-    println!("cargo:rerun-if-changed={}", source_path);
+}
+
+/// Collects the `(pallet, item, validation hash)` of every generated item built with `constructor`, such as
+/// `StaticPayload::new_static("SubtensorModule", "set_weights", args, [hash])`, as a Rust array expression.
+fn validation_hashes(metadata: &str, constructor: &str) -> String {
+    let mut hashes = BTreeMap::new();
+
+    for (index, _) in metadata.match_indices(constructor) {
+        let arguments = &metadata[index + constructor.len()..];
+
+        let mut strings = arguments.split('"');
+        let (Some(pallet), Some(item)) = (strings.nth(1), strings.nth(1)) else {
+            continue;
+        };
+
+        let Some(start) = arguments.find('[') else {
+            continue;
+        };
+        let Some(end) = arguments[start..].find(']') else {
+            continue;
+        };
+
+        let hash: Vec<u8> = arguments[start + 1..start + end]
+            .split(',')
+            .filter_map(|byte| byte.trim().strip_suffix("u8")?.parse().ok())
+            .collect();
+
+        if hash.len() == 32 {
+            hashes.insert((pallet, item), hash);
+        }
+    }
+
+    let entries: Vec<String> = hashes
+        .into_iter()
+        .map(|((pallet, item), hash)| format!("({pallet:?}, {item:?}, {hash:?})"))
+        .collect();
+
+    format!("[{}]", entries.join(", "))
 }
@@ -0,0 +1,73 @@
+//! Regenerates a crabtensor metadata snapshot from SCALE encoded metadata, without connecting to a node.
+//!
+//! ```sh
+//! subxt metadata --url wss://test.finney.opentensor.ai:443 > testnet.scale
+//! cargo run -p crabtensor-codegen -- testnet.scale metadata_testnet.rs
+//! ```
+
+use std::env;
+use std::fs;
+use std::process::{self, Command};
+
+use parity_scale_codec::Decode;
+use subxt_codegen::CodegenBuilder;
+use subxt_metadata::Metadata;
+
+/// The prefix of `sp_version::RuntimeVersion`, which the `System` pallet exposes as its `Version` constant.
+#[derive(Decode)]
+struct RuntimeVersion {
+    _spec_name: String,
+    _impl_name: String,
+    _authoring_version: u32,
+    spec_version: u32,
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let [input, output] = args.as_slice() else {
+        eprintln!("Usage: crabtensor-codegen <metadata.scale> <metadata_network.rs>");
+        process::exit(2);
+    };
+
+    if let Err(error) = generate(input, output) {
+        eprintln!("{error}");
+        process::exit(1);
+    }
+}
+
+fn generate(input: &str, output: &str) -> Result<(), String> {
+    let bytes = fs::read(input).map_err(|error| format!("Failed to read {input}: {error}"))?;
+
+    let metadata = Metadata::decode(&mut &bytes[..])
+        .map_err(|error| format!("Failed to decode metadata from {input}: {error}"))?;
+
+    let spec_version = metadata
+        .pallet_by_name("System")
+        .and_then(|pallet| pallet.constant_by_name("Version"))
+        .and_then(|version| RuntimeVersion::decode(&mut version.value()).ok())
+        .map(|version| version.spec_version)
+        .ok_or("Metadata has no System::Version constant to read the spec version from")?;
+
+    let code = CodegenBuilder::new()
+        .generate(metadata)
+        .map_err(|error| format!("Failed to generate code: {error}"))?;
+
+    // build.rs reads the spec version back from this line
+    let code =
+        format!("// Generated by crabtensor-codegen from spec version {spec_version}\n{code}\n");
+
+    fs::write(output, code).map_err(|error| format!("Failed to write {output}: {error}"))?;
+
+    match Command::new("rustfmt")
+        .args(["--edition", "2021", output])
+        .status()
+    {
+        Ok(status) if status.success() => {}
+        _ => eprintln!("rustfmt failed, {output} is left unformatted"),
+    }
+
+    println!("Generated {output} from spec version {spec_version}");
+
+    Ok(())
+}
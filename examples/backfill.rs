@@ -0,0 +1,61 @@
+//! Backfills a range of blocks from the archive node into a SQLite database.
+//!
+//! ```sh
+//! cargo run --example backfill --features indexer -- history.sqlite 5000000 5001000 [netuid]
+//! ```
+//!
+//! Running it again only indexes the blocks of the range that were not written yet.
+
+use std::env;
+use std::process::ExitCode;
+
+use crabtensor::indexer::{Indexer, IndexerOptions};
+use crabtensor::subtensor::SubtensorUrl;
+
+/// How many blocks are indexed between progress reports.
+const CHUNK: u32 = 100;
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let (Some(database), Some(start), Some(end)) = (
+        args.first(),
+        args.get(1).and_then(|start| start.parse::<u32>().ok()),
+        args.get(2).and_then(|end| end.parse::<u32>().ok()),
+    ) else {
+        eprintln!("Usage: backfill <database> <start block> <end block> [netuid]");
+        return ExitCode::FAILURE;
+    };
+
+    let options = IndexerOptions {
+        netuid: args.get(3).and_then(|netuid| netuid.parse().ok()),
+        ..Default::default()
+    };
+
+    let mut indexer = match Indexer::connect(SubtensorUrl::Archive, database, options).await {
+        Ok(indexer) => indexer,
+        Err(error) => {
+            eprintln!("Failed to start indexer: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut chunk_start = start;
+
+    while chunk_start <= end {
+        let chunk_end = chunk_start.saturating_add(CHUNK - 1).min(end);
+
+        match indexer.index(chunk_start..=chunk_end).await {
+            Ok(written) => println!("Indexed up to block {chunk_end} of {end}, {written} new"),
+            Err(error) => {
+                eprintln!("Failed to index blocks {chunk_start}..={chunk_end}: {error}");
+                return ExitCode::FAILURE;
+            }
+        }
+
+        chunk_start = chunk_end + 1;
+    }
+
+    ExitCode::SUCCESS
+}
@@ -2,9 +2,13 @@ use std::fmt::{Display, Formatter};
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 
 use subxt::tx::Payload;
+use thiserror::Error;
 
-use crate::api;
-use crate::api::runtime_types::pallet_subtensor::pallet::{AxonInfo, PrometheusInfo};
+use crate::api::runtime_types::pallet_subtensor::pallet::{
+    AxonInfo, NeuronCertificate, PrometheusInfo,
+};
+use crate::subtensor::Subtensor;
+use crate::{api, AccountId, BlockRef};
 
 #[derive(Clone, Copy, Debug, Default)]
 pub enum AxonProtocol {
@@ -30,11 +34,149 @@ pub trait AsAddr {
     fn as_addr(&self) -> SocketAddr;
 }
 
+#[derive(Debug, Error)]
+#[error("Unknown axon protocol {0}, expected 0 (TCP), 1 (UDP) or 4 (TCP/UDP)")]
+pub struct UnknownAxonProtocolError(pub u8);
+
+impl TryFrom<u8> for AxonProtocol {
+    type Error = UnknownAxonProtocolError;
+
+    fn try_from(protocol: u8) -> Result<Self, Self::Error> {
+        match protocol {
+            0 => Ok(AxonProtocol::Tcp),
+            1 => Ok(AxonProtocol::Udp),
+            4 => Ok(AxonProtocol::Other),
+            _ => Err(UnknownAxonProtocolError(protocol)),
+        }
+    }
+}
+
 pub trait WithAxonProtocol {
     fn axon_protocol(&self) -> Option<AxonProtocol>;
 }
 
-fn neuron_info_as_addr(ip: u128, port: u16, ip_type: u8) -> SocketAddr {
+/// Like [`WithAxonProtocol`], but reporting which protocol was not recognized.
+pub trait TryAxonProtocol {
+    fn try_axon_protocol(&self) -> Result<AxonProtocol, UnknownAxonProtocolError>;
+}
+
+/// What an endpoint published on chain points to, as `as_addr` decodes anything, including never served endpoints.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AxonEndpoint {
+    /// Never served, or served with an unspecified IP or port 0.
+    Unset,
+    /// An `ip_type` other than 4 or 6, or an IPv4 type with an IP that does not fit 32 bits.
+    Invalid {
+        ip: u128,
+        ip_type: u8,
+    },
+    /// Not routable on the internet, such as RFC 1918, link-local, shared or unique local addresses.
+    Private(SocketAddr),
+    Loopback(SocketAddr),
+    /// A public IPv6 address.
+    Ipv6(SocketAddr),
+    /// A public IPv4 address.
+    Public(SocketAddr),
+}
+
+impl AxonEndpoint {
+    pub fn classify(ip: u128, port: u16, ip_type: u8) -> Self {
+        let ip = match ip_type {
+            4 => match u32::try_from(ip) {
+                Ok(ip) => IpAddr::V4(Ipv4Addr::from(ip)),
+                Err(_) => return AxonEndpoint::Invalid { ip, ip_type },
+            },
+            6 => {
+                let ip = Ipv6Addr::from(ip);
+
+                // Mapped addresses are classified like the IPv4 address they hold
+                ip.to_ipv4_mapped().map_or(IpAddr::V6(ip), IpAddr::V4)
+            }
+            0 if ip == 0 => return AxonEndpoint::Unset,
+            _ => return AxonEndpoint::Invalid { ip, ip_type },
+        };
+
+        let address = SocketAddr::new(ip, port);
+
+        if ip.is_unspecified() || port == 0 {
+            return AxonEndpoint::Unset;
+        }
+
+        if ip.is_loopback() {
+            return AxonEndpoint::Loopback(address);
+        }
+
+        match ip {
+            IpAddr::V4(ip) => {
+                let [a, b, ..] = ip.octets();
+
+                let private = ip.is_private()
+                    || ip.is_link_local()
+                    || ip.is_broadcast()
+                    || ip.is_documentation()
+                    || ip.is_multicast()
+                    || (a == 100 && (64..128).contains(&b)) // shared address space, RFC 6598
+                    || a == 0
+                    || a >= 240;
+
+                if private {
+                    AxonEndpoint::Private(address)
+                } else {
+                    AxonEndpoint::Public(address)
+                }
+            }
+            IpAddr::V6(ip) => {
+                let [first, ..] = ip.segments();
+
+                let private = (first & 0xfe00) == 0xfc00 // unique local
+                    || (first & 0xffc0) == 0xfe80 // link-local
+                    || ip.is_multicast()
+                    || (first == 0x2001 && ip.segments()[1] == 0xdb8); // documentation
+
+                if private {
+                    AxonEndpoint::Private(address)
+                } else {
+                    AxonEndpoint::Ipv6(address)
+                }
+            }
+        }
+    }
+
+    pub fn address(&self) -> Option<SocketAddr> {
+        match *self {
+            AxonEndpoint::Private(address)
+            | AxonEndpoint::Loopback(address)
+            | AxonEndpoint::Ipv6(address)
+            | AxonEndpoint::Public(address) => Some(address),
+            AxonEndpoint::Unset | AxonEndpoint::Invalid { .. } => None,
+        }
+    }
+
+    /// Whether other neurons can reach the endpoint over the internet.
+    pub fn is_public(&self) -> bool {
+        matches!(self, AxonEndpoint::Ipv6(_) | AxonEndpoint::Public(_))
+    }
+}
+
+impl Display for AxonEndpoint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AxonEndpoint::Unset => f.write_str("unset"),
+            AxonEndpoint::Invalid { ip, ip_type } => {
+                write!(f, "invalid (ip {ip}, ip type {ip_type})")
+            }
+            AxonEndpoint::Private(address) => write!(f, "private {address}"),
+            AxonEndpoint::Loopback(address) => write!(f, "loopback {address}"),
+            AxonEndpoint::Ipv6(address) | AxonEndpoint::Public(address) => address.fmt(f),
+        }
+    }
+}
+
+pub trait AsEndpoint {
+    fn endpoint(&self) -> AxonEndpoint;
+}
+
+pub(crate) fn neuron_info_as_addr(ip: u128, port: u16, ip_type: u8) -> SocketAddr {
     let ip: IpAddr = if ip_type == 4 {
         Ipv4Addr::from(ip as u32).into()
     } else {
@@ -56,14 +198,52 @@ impl AsAddr for PrometheusInfo {
     }
 }
 
+impl AsEndpoint for AxonInfo {
+    fn endpoint(&self) -> AxonEndpoint {
+        AxonEndpoint::classify(self.ip, self.port, self.ip_type)
+    }
+}
+
+impl AsEndpoint for PrometheusInfo {
+    fn endpoint(&self) -> AxonEndpoint {
+        AxonEndpoint::classify(self.ip, self.port, self.ip_type)
+    }
+}
+
 impl WithAxonProtocol for AxonInfo {
     fn axon_protocol(&self) -> Option<AxonProtocol> {
-        match self.protocol {
-            0 => Some(AxonProtocol::Tcp),
-            1 => Some(AxonProtocol::Udp),
-            4 => Some(AxonProtocol::Other),
-            _ => None,
-        }
+        self.try_axon_protocol().ok()
+    }
+}
+
+impl TryAxonProtocol for AxonInfo {
+    fn try_axon_protocol(&self) -> Result<AxonProtocol, UnknownAxonProtocolError> {
+        AxonProtocol::try_from(self.protocol)
+    }
+}
+
+/// The inverse of [`neuron_info_as_addr`], the IP as stored on chain along with its type.
+fn ip_encoding(address: SocketAddr) -> (u128, u8) {
+    match address.ip() {
+        IpAddr::V4(addr) => (u128::from(u32::from(addr)), 4),
+        IpAddr::V6(addr) => (u128::from(addr), 6),
+    }
+}
+
+/// Whether the endpoint stored on chain already matches the desired one.
+pub trait ServedAt {
+    fn is_served_at(&self, address: SocketAddr) -> bool;
+}
+
+impl ServedAt for AxonInfo {
+    fn is_served_at(&self, address: SocketAddr) -> bool {
+        (self.ip, self.ip_type) == ip_encoding(address) && self.port == address.port()
+    }
+}
+
+impl ServedAt for PrometheusInfo {
+    fn is_served_at(&self, address: SocketAddr) -> bool {
+        (self.ip, self.ip_type) == ip_encoding(address) && self.port == address.port()
     }
 }
 
@@ -72,10 +252,7 @@ pub fn serve_axon_payload(
     address: SocketAddr,
     protocol: AxonProtocol,
 ) -> impl Payload {
-    let (ip_addr, ip_type) = match address.ip() {
-        IpAddr::V4(addr) => (u128::from(u32::from(addr)), 4u8),
-        IpAddr::V6(addr) => (u128::from(addr), 6u8),
-    };
+    let (ip_addr, ip_type) = ip_encoding(address);
 
     api::tx().subtensor_module().serve_axon(
         netuid,
@@ -88,3 +265,294 @@ pub fn serve_axon_payload(
         0, // placeholder2 unused
     )
 }
+
+/// Like [`serve_axon_payload`], also publishing `certificate`, the algorithm byte followed by the public key of the
+/// axon's TLS certificate (see `tls::NeuronIdentity::neuron_certificate` with the `tls` feature).
+pub fn serve_axon_tls_payload(
+    netuid: u16,
+    address: SocketAddr,
+    protocol: AxonProtocol,
+    certificate: Vec<u8>,
+) -> impl Payload {
+    let (ip_addr, ip_type) = ip_encoding(address);
+
+    api::tx().subtensor_module().serve_axon_tls(
+        netuid,
+        1, // version is always 1 in practice
+        ip_addr,
+        address.port(),
+        ip_type,
+        protocol as u8,
+        0,
+        0,
+        certificate,
+    )
+}
+
+pub fn serve_prometheus_payload(netuid: u16, address: SocketAddr) -> impl Payload {
+    let (ip_addr, ip_type) = ip_encoding(address);
+
+    api::tx().subtensor_module().serve_prometheus(
+        netuid,
+        1, // version is always 1 in practice
+        ip_addr,
+        address.port(),
+        ip_type,
+    )
+}
+
+/// A [`serve_axon_payload`] if the axon `hotkey` serves on `netuid` differs from `address` and `protocol`, `None`
+/// otherwise, as serving it again would only count against the serving rate limit.
+pub async fn serve_axon_if_changed(
+    client: &Subtensor,
+    at: BlockRef,
+    netuid: u16,
+    hotkey: &AccountId,
+    address: SocketAddr,
+    protocol: AxonProtocol,
+) -> Result<Option<impl Payload>, subxt::Error> {
+    let current = client
+        .storage()
+        .at(at)
+        .fetch(&api::storage().subtensor_module().axons(netuid, hotkey))
+        .await?;
+
+    let unchanged =
+        current.is_some_and(|axon| axon.is_served_at(address) && axon.protocol == protocol as u8);
+
+    Ok((!unchanged).then(|| serve_axon_payload(netuid, address, protocol)))
+}
+
+/// A [`serve_prometheus_payload`] if the Prometheus endpoint `hotkey` serves on `netuid` differs from `address`,
+/// `None` otherwise.
+pub async fn serve_prometheus_if_changed(
+    client: &Subtensor,
+    at: BlockRef,
+    netuid: u16,
+    hotkey: &AccountId,
+    address: SocketAddr,
+) -> Result<Option<impl Payload>, subxt::Error> {
+    let current = client
+        .storage()
+        .at(at)
+        .fetch(&api::storage().subtensor_module().prometheus(netuid, hotkey))
+        .await?;
+
+    let unchanged = current.is_some_and(|prometheus| prometheus.is_served_at(address));
+
+    Ok((!unchanged).then(|| serve_prometheus_payload(netuid, address)))
+}
+
+/// The certificate `hotkey` published with [`serve_axon_tls_payload`] on `netuid`, if any.
+pub async fn neuron_certificate(
+    client: &Subtensor,
+    at: BlockRef,
+    netuid: u16,
+    hotkey: &AccountId,
+) -> Result<Option<NeuronCertificate>, subxt::Error> {
+    client
+        .storage()
+        .at(at)
+        .fetch(
+            &api::storage()
+                .subtensor_module()
+                .neuron_certificates(netuid, hotkey),
+        )
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ipv4(address: &str) -> u128 {
+        u128::from(u32::from(address.parse::<Ipv4Addr>().unwrap()))
+    }
+
+    fn ipv6(address: &str) -> u128 {
+        u128::from(address.parse::<Ipv6Addr>().unwrap())
+    }
+
+    #[test]
+    fn classifies_endpoints() {
+        let address = |address: &str| SocketAddr::new(address.parse().unwrap(), 8091);
+
+        let cases = [
+            (0, 0, 0, AxonEndpoint::Unset),
+            (0, 8091, 4, AxonEndpoint::Unset),
+            (ipv4("1.2.3.4"), 0, 4, AxonEndpoint::Unset),
+            (
+                ipv4("10.0.0.1"),
+                8091,
+                4,
+                AxonEndpoint::Private(address("10.0.0.1")),
+            ),
+            (
+                ipv4("172.16.5.4"),
+                8091,
+                4,
+                AxonEndpoint::Private(address("172.16.5.4")),
+            ),
+            (
+                ipv4("192.168.1.1"),
+                8091,
+                4,
+                AxonEndpoint::Private(address("192.168.1.1")),
+            ),
+            (
+                ipv4("100.64.0.1"),
+                8091,
+                4,
+                AxonEndpoint::Private(address("100.64.0.1")),
+            ),
+            (
+                ipv4("100.127.255.254"),
+                8091,
+                4,
+                AxonEndpoint::Private(address("100.127.255.254")),
+            ),
+            (
+                ipv4("100.128.0.1"),
+                8091,
+                4,
+                AxonEndpoint::Public(address("100.128.0.1")),
+            ),
+            (
+                ipv4("127.0.0.1"),
+                8091,
+                4,
+                AxonEndpoint::Loopback(address("127.0.0.1")),
+            ),
+            (ipv6("::1"), 8091, 6, AxonEndpoint::Loopback(address("::1"))),
+            (
+                ipv4("1.2.3.4"),
+                8091,
+                4,
+                AxonEndpoint::Public(address("1.2.3.4")),
+            ),
+            (
+                ipv6("::ffff:1.2.3.4"),
+                8091,
+                6,
+                AxonEndpoint::Public(address("1.2.3.4")),
+            ),
+            (
+                ipv6("::ffff:192.168.1.1"),
+                8091,
+                6,
+                AxonEndpoint::Private(address("192.168.1.1")),
+            ),
+            (
+                ipv6("fd00::1"),
+                8091,
+                6,
+                AxonEndpoint::Private(address("fd00::1")),
+            ),
+            (
+                ipv6("fe80::1"),
+                8091,
+                6,
+                AxonEndpoint::Private(address("fe80::1")),
+            ),
+            (
+                ipv6("2606:4700::1"),
+                8091,
+                6,
+                AxonEndpoint::Ipv6(address("2606:4700::1")),
+            ),
+            (
+                ipv6("2606:4700::1"),
+                8091,
+                4,
+                AxonEndpoint::Invalid {
+                    ip: ipv6("2606:4700::1"),
+                    ip_type: 4,
+                },
+            ),
+            (
+                ipv4("1.2.3.4"),
+                8091,
+                5,
+                AxonEndpoint::Invalid {
+                    ip: ipv4("1.2.3.4"),
+                    ip_type: 5,
+                },
+            ),
+            (
+                ipv4("1.2.3.4"),
+                8091,
+                0,
+                AxonEndpoint::Invalid {
+                    ip: ipv4("1.2.3.4"),
+                    ip_type: 0,
+                },
+            ),
+        ];
+
+        for (ip, port, ip_type, expected) in cases {
+            assert_eq!(
+                AxonEndpoint::classify(ip, port, ip_type),
+                expected,
+                "ip {ip:#x}, port {port}, ip type {ip_type}",
+            );
+        }
+    }
+
+    #[test]
+    fn reports_unknown_protocols() {
+        let axon = AxonInfo {
+            block: 0,
+            version: 0,
+            ip: ipv4("1.2.3.4"),
+            port: 8091,
+            ip_type: 4,
+            protocol: 3,
+            placeholder1: 0,
+            placeholder2: 0,
+        };
+
+        assert!(axon.axon_protocol().is_none());
+        assert!(matches!(
+            axon.try_axon_protocol(),
+            Err(UnknownAxonProtocolError(3))
+        ));
+    }
+
+    #[test]
+    fn compares_served_endpoints_by_their_encoding() {
+        let axon = AxonInfo {
+            block: 0,
+            version: 1,
+            ip: ipv4("1.2.3.4"),
+            port: 8091,
+            ip_type: 4,
+            protocol: 4,
+            placeholder1: 0,
+            placeholder2: 0,
+        };
+        let address = |address: &str| address.parse::<SocketAddr>().unwrap();
+
+        assert!(axon.is_served_at(address("1.2.3.4:8091")));
+        assert!(!axon.is_served_at(address("1.2.3.4:8092")));
+        assert!(!axon.is_served_at(address("1.2.3.5:8091")));
+        // The same IPv4 address mapped into IPv6 is stored with another IP and type
+        assert!(!axon.is_served_at(address("[::ffff:1.2.3.4]:8091")));
+        assert!(!AxonInfo { ip_type: 6, ..axon }.is_served_at(address("1.2.3.4:8091")));
+
+        let prometheus = PrometheusInfo {
+            block: 0,
+            version: 1,
+            ip: ipv6("2606:4700::1"),
+            port: 9100,
+            ip_type: 6,
+        };
+
+        assert!(prometheus.is_served_at(address("[2606:4700::1]:9100")));
+        assert!(!prometheus.is_served_at(address("[2606:4700::2]:9100")));
+        assert!(!PrometheusInfo {
+            ip_type: 4,
+            ..prometheus
+        }
+        .is_served_at(address("[2606:4700::1]:9100")));
+    }
+}
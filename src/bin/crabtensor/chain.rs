@@ -0,0 +1,512 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::Path;
+
+use crabtensor::axon::{serve_axon_if_changed, AsAddr, AxonProtocol};
+use crabtensor::probe::{probe_metagraph, ProbeOptions, ProbeOutcome};
+use crabtensor::registration::{self, CancelHandle, SolverOptions};
+use crabtensor::subtensor::Subtensor;
+use crabtensor::wallet::Signer;
+use crabtensor::weights::{set_weights_payload, NormalizedWeight};
+use crabtensor::{api, dynamic, hyperparameters, AccountId, BlockRef};
+use serde_json::{json, Value};
+use subxt::events::Phase;
+use subxt::tx::Payload;
+
+use crate::output::{fraction, tao, Output};
+use crate::CliError;
+
+pub async fn metagraph(client: &Subtensor, at: BlockRef, netuid: u16) -> Result<Output, CliError> {
+    let metagraph = dynamic::metagraph(client, at, netuid)
+        .await?
+        .ok_or(CliError::SubnetNotFound(netuid))?;
+
+    let mut rows = Vec::new();
+    let mut neurons = Vec::new();
+
+    for (uid, hotkey) in metagraph.hotkeys.iter().enumerate() {
+        let value =
+            |values: &[subxt::ext::codec::Compact<u16>]| values.get(uid).map_or(0, |value| value.0);
+
+        let stake = metagraph.total_stake.get(uid).map_or(0, |stake| stake.0);
+        let emission = metagraph.emission.get(uid).map_or(0, |emission| emission.0);
+        let validator_permit = metagraph
+            .validator_permit
+            .get(uid)
+            .copied()
+            .unwrap_or(false);
+        let active = metagraph.active.get(uid).copied().unwrap_or(false);
+        let axon = metagraph
+            .axons
+            .get(uid)
+            .map(|axon| axon.as_addr().to_string());
+
+        rows.push(vec![
+            uid.to_string(),
+            hotkey.to_string(),
+            tao(stake),
+            fraction(value(&metagraph.trust)),
+            fraction(value(&metagraph.consensus)),
+            fraction(value(&metagraph.incentives)),
+            fraction(value(&metagraph.dividends)),
+            tao(emission),
+            validator_permit.to_string(),
+            active.to_string(),
+            axon.clone().unwrap_or_default(),
+        ]);
+
+        neurons.push(json!({
+            "uid": uid,
+            "hotkey": hotkey.to_string(),
+            "coldkey": metagraph.coldkeys.get(uid).map(AccountId::to_string),
+            "stake": stake,
+            "trust": value(&metagraph.trust),
+            "consensus": value(&metagraph.consensus),
+            "incentive": value(&metagraph.incentives),
+            "dividends": value(&metagraph.dividends),
+            "emission": emission,
+            "validator_permit": validator_permit,
+            "active": active,
+            "axon": axon,
+        }));
+    }
+
+    Ok(Output::table(
+        &[
+            "UID",
+            "HOTKEY",
+            "STAKE",
+            "TRUST",
+            "CONSENSUS",
+            "INCENTIVE",
+            "DIVIDENDS",
+            "EMISSION",
+            "VPERMIT",
+            "ACTIVE",
+            "AXON",
+        ],
+        rows,
+        json!({
+            "netuid": netuid,
+            "block": metagraph.block,
+            "neurons": neurons,
+        }),
+    ))
+}
+
+pub async fn probe(
+    client: &Subtensor,
+    at: BlockRef,
+    netuid: u16,
+    options: ProbeOptions,
+) -> Result<Output, CliError> {
+    let probes = probe_metagraph(client, at, netuid, &options)
+        .await?
+        .ok_or(CliError::SubnetNotFound(netuid))?;
+
+    let mut rows = Vec::new();
+    let mut axons = Vec::new();
+
+    for probe in &probes {
+        let (status, latency) = match &probe.outcome {
+            ProbeOutcome::Reachable { latency } => {
+                ("reachable".to_string(), Some(latency.as_millis()))
+            }
+            ProbeOutcome::Failed(kind) => (format!("failed: {kind}"), None),
+            ProbeOutcome::TimedOut => ("timed out".to_string(), None),
+            ProbeOutcome::Skipped => ("skipped".to_string(), None),
+        };
+
+        rows.push(vec![
+            probe.uid.to_string(),
+            probe.hotkey.to_string(),
+            probe.endpoint.to_string(),
+            status.clone(),
+            latency.map_or_else(|| "-".to_string(), |latency| format!("{latency}ms")),
+        ]);
+
+        axons.push(json!({
+            "uid": probe.uid,
+            "hotkey": probe.hotkey.to_string(),
+            "endpoint": probe.endpoint.to_string(),
+            "address": probe.endpoint.address(),
+            "reachable": probe.is_reachable(),
+            "status": status,
+            "latency_ms": latency,
+        }));
+    }
+
+    Ok(Output::table(
+        &["UID", "HOTKEY", "ENDPOINT", "STATUS", "LATENCY"],
+        rows,
+        json!({
+            "netuid": netuid,
+            "reachable": probes.iter().filter(|probe| probe.is_reachable()).count(),
+            "axons": axons,
+        }),
+    ))
+}
+
+pub async fn hyperparameters(
+    client: &Subtensor,
+    at: BlockRef,
+    netuid: u16,
+) -> Result<Output, CliError> {
+    let hyperparameters = hyperparameters::hyperparameters(client, at, netuid)
+        .await?
+        .ok_or(CliError::SubnetNotFound(netuid))?;
+
+    let values = hyperparameters.values();
+
+    let json: serde_json::Map<String, Value> = values
+        .iter()
+        .map(|(name, value)| {
+            let value = match *value {
+                hyperparameters::HyperparameterValue::Bool(value) => json!(value),
+                hyperparameters::HyperparameterValue::Integer(value) => json!(value),
+                hyperparameters::HyperparameterValue::Float(value) => json!(value),
+            };
+
+            (name.to_string(), value)
+        })
+        .collect();
+
+    Ok(Output::table(
+        &["HYPERPARAMETER", "VALUE"],
+        values
+            .iter()
+            .map(|(name, value)| vec![name.to_string(), value.to_string()])
+            .collect(),
+        Value::Object(json),
+    ))
+}
+
+pub async fn stake(
+    client: &Subtensor,
+    at: BlockRef,
+    coldkey: AccountId,
+) -> Result<Output, CliError> {
+    let account = client
+        .storage()
+        .at(at.clone())
+        .fetch_or_default(&api::storage().system().account(coldkey.clone()))
+        .await?;
+
+    let stakes = dynamic::stake_info(client, at, coldkey.clone()).await?;
+
+    let mut rows: Vec<Vec<String>> = stakes
+        .iter()
+        .map(|stake| {
+            vec![
+                stake.netuid.to_string(),
+                stake.hotkey.to_string(),
+                tao(stake.stake),
+                tao(stake.emission),
+                stake.is_registered.to_string(),
+            ]
+        })
+        .collect();
+
+    rows.push(vec![
+        "-".to_string(),
+        "free balance".to_string(),
+        tao(account.data.free),
+        "-".to_string(),
+        "-".to_string(),
+    ]);
+
+    Ok(Output::table(
+        &["NETUID", "HOTKEY", "STAKE", "EMISSION", "REGISTERED"],
+        rows,
+        json!({
+            "coldkey": coldkey.to_string(),
+            "free": account.data.free,
+            "stakes": stakes.iter().map(|stake| json!({
+                "netuid": stake.netuid,
+                "hotkey": stake.hotkey.to_string(),
+                "stake": stake.stake,
+                "emission": stake.emission,
+                "is_registered": stake.is_registered,
+            })).collect::<Vec<_>>(),
+        }),
+    ))
+}
+
+/// Reads weights as an object of uid to weight, or as a list of `[uid, weight]` pairs.
+fn read_weights(path: &Path) -> Result<Vec<(u16, f64)>, CliError> {
+    let json: Value = serde_json::from_slice(&fs::read(path)?)?;
+
+    parse_weights(&json).ok_or_else(|| {
+        CliError::Other(format!(
+            "{} must hold an object of uid to weight or a list of [uid, weight] pairs",
+            path.display()
+        ))
+    })
+}
+
+/// The weights in `json` ordered by uid, `None` if it is not in either form of [`read_weights`] or holds a negative
+/// or non-finite weight.
+fn parse_weights(json: &Value) -> Option<Vec<(u16, f64)>> {
+    let entries: Vec<(u16, f64)> = match json {
+        Value::Object(weights) => weights
+            .iter()
+            .map(|(uid, weight)| Some((uid.parse().ok()?, weight.as_f64()?)))
+            .collect::<Option<_>>()?,
+        Value::Array(weights) => weights
+            .iter()
+            .map(|pair| {
+                let [uid, weight] = pair.as_array()?.as_slice() else {
+                    return None;
+                };
+
+                Some((u16::try_from(uid.as_u64()?).ok()?, weight.as_f64()?))
+            })
+            .collect::<Option<_>>()?,
+        _ => return None,
+    };
+
+    if entries
+        .iter()
+        .any(|&(_, weight)| weight < 0.0 || !weight.is_finite())
+    {
+        return None;
+    }
+
+    // Duplicates would be rejected by the pallet, keep the last one like a JSON object would
+    let weights: BTreeMap<u16, f64> = entries.into_iter().collect();
+
+    Some(weights.into_iter().collect())
+}
+
+async fn submit(
+    client: &Subtensor,
+    payload: &impl Payload,
+    signer: &Signer,
+) -> Result<Output, CliError> {
+    let in_block = client
+        .tx()
+        .sign_and_submit_then_watch_default(payload, signer)
+        .await?
+        .wait_for_finalized()
+        .await?;
+
+    in_block.wait_for_success().await?;
+
+    let block_hash = format!("{:?}", in_block.block_hash());
+    let extrinsic_hash = format!("{:?}", in_block.extrinsic_hash());
+
+    Ok(Output::new(
+        format!("Extrinsic {extrinsic_hash} finalized in block {block_hash}"),
+        json!({
+            "block_hash": block_hash,
+            "extrinsic_hash": extrinsic_hash,
+        }),
+    ))
+}
+
+pub async fn set_weights(
+    client: &Subtensor,
+    signer: &Signer,
+    netuid: u16,
+    path: &Path,
+    version_key: Option<u64>,
+) -> Result<Output, CliError> {
+    let weights = read_weights(path)?;
+
+    // Scaled so the largest weight is u16::MAX, like `normalize_weights`
+    let max = weights
+        .iter()
+        .map(|&(_, weight)| weight)
+        .fold(0.0, f64::max);
+
+    if max == 0.0 {
+        return Err(CliError::Other(format!(
+            "{} holds no positive weight",
+            path.display()
+        )));
+    }
+
+    let weights = weights
+        .into_iter()
+        .map(|(uid, weight)| NormalizedWeight {
+            uid,
+            weight: (weight / max * u16::MAX as f64).round() as u16,
+        })
+        .collect();
+
+    let version_key = match version_key {
+        Some(version_key) => version_key,
+        None => {
+            client
+                .storage()
+                .at_latest()
+                .await?
+                .fetch_or_default(
+                    &api::storage()
+                        .subtensor_module()
+                        .weights_version_key(netuid),
+                )
+                .await?
+        }
+    };
+
+    submit(
+        client,
+        &set_weights_payload(netuid, weights, version_key),
+        signer,
+    )
+    .await
+}
+
+pub async fn serve_axon(
+    client: &Subtensor,
+    signer: &Signer,
+    netuid: u16,
+    address: SocketAddr,
+    protocol: AxonProtocol,
+) -> Result<Output, CliError> {
+    let at = client.blocks().at_latest().await?.reference();
+    let hotkey = signer.account_id();
+
+    match serve_axon_if_changed(client, at, netuid, hotkey, address, protocol).await? {
+        Some(payload) => submit(client, &payload, signer).await,
+        None => Ok(Output::new(
+            format!("Axon of {hotkey} on subnet {netuid} is already served at {address}"),
+            json!({ "unchanged": true }),
+        )),
+    }
+}
+
+pub async fn register(
+    client: &Subtensor,
+    signer: &Signer,
+    coldkey: AccountId,
+    netuid: u16,
+    options: SolverOptions,
+) -> Result<Output, CliError> {
+    let hotkey = signer.account_id().clone();
+
+    let solution = registration::solve_pow(
+        client,
+        netuid,
+        &hotkey,
+        options,
+        CancelHandle::default(),
+        |progress| {
+            eprintln!(
+                "Block {}, difficulty {}, {:.0} hashes/s",
+                progress.block_number,
+                progress.difficulty,
+                progress.hash_rate()
+            )
+        },
+    )
+    .await?
+    .ok_or_else(|| {
+        CliError::Other("Block subscription ended before a solution was found".to_string())
+    })?;
+
+    submit(
+        client,
+        &registration::register_payload(netuid, &solution, hotkey, coldkey),
+        signer,
+    )
+    .await
+}
+
+pub async fn events(
+    client: &Subtensor,
+    at: BlockRef,
+    pallet: Option<&str>,
+) -> Result<Output, CliError> {
+    let block = client.blocks().at(at).await?;
+
+    let mut rows = Vec::new();
+    let mut events = Vec::new();
+
+    for event in block.events().await?.iter() {
+        let event = event?;
+
+        if pallet.is_some_and(|pallet| !event.pallet_name().eq_ignore_ascii_case(pallet)) {
+            continue;
+        }
+
+        let extrinsic_index = match event.phase() {
+            Phase::ApplyExtrinsic(index) => Some(index),
+            _ => None,
+        };
+
+        let fields = event.field_values()?.to_string();
+
+        rows.push(vec![
+            event.index().to_string(),
+            extrinsic_index.map_or_else(|| "-".to_string(), |index| index.to_string()),
+            format!("{}::{}", event.pallet_name(), event.variant_name()),
+            fields.clone(),
+        ]);
+
+        events.push(json!({
+            "index": event.index(),
+            "extrinsic_index": extrinsic_index,
+            "pallet": event.pallet_name(),
+            "variant": event.variant_name(),
+            "fields": fields,
+        }));
+    }
+
+    Ok(Output::table(
+        &["INDEX", "EXTRINSIC", "EVENT", "FIELDS"],
+        rows,
+        json!({
+            "block_number": block.number(),
+            "block_hash": format!("{:?}", block.hash()),
+            "events": events,
+        }),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_both_weight_forms() {
+        let expected = Some(vec![(1, 0.5), (3, 2.0)]);
+
+        assert_eq!(parse_weights(&json!({ "3": 2, "1": 0.5 })), expected);
+        assert_eq!(parse_weights(&json!([[3, 2], [1, 0.5]])), expected);
+        assert_eq!(parse_weights(&json!({})), Some(Vec::new()));
+        assert_eq!(parse_weights(&json!([])), Some(Vec::new()));
+    }
+
+    #[test]
+    fn keeps_the_last_weight_of_a_duplicate_uid() {
+        assert_eq!(
+            parse_weights(&json!([[1, 0.5], [2, 1], [1, 0.25]])),
+            Some(vec![(1, 0.25), (2, 1.0)])
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_weights() {
+        for json in [
+            json!({ "1": -0.5 }),
+            json!([[1, -1]]),
+            // JSON holds no infinite or NaN numbers, these become null
+            json!({ "1": f64::INFINITY }),
+            json!([[1, f64::NAN]]),
+            json!({ "uid": 1 }),
+            json!({ "65536": 1 }),
+            json!({ "1": "1" }),
+            json!([[65536, 1]]),
+            json!([[-1, 1]]),
+            json!([[1, 1, 1]]),
+            json!([1, 1]),
+            json!(1),
+            json!(null),
+        ] {
+            assert_eq!(parse_weights(&json), None, "{json}");
+        }
+    }
+}
@@ -0,0 +1,517 @@
+//! `crabtensor`, a command line for common operator tasks, built with the `cli` feature.
+//!
+//! ```sh
+//! cargo run --features cli -- --network finney metagraph --netuid 1 --json
+//! ```
+//!
+//! The network is resolved like any other binary through [`NetworkConfig::load`], with `--config` as the file and
+//! `--network` overriding it and the environment.
+
+mod chain;
+mod output;
+mod wallet;
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::str::FromStr;
+use std::time::Duration;
+
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
+use crabtensor::axon::AxonProtocol;
+use crabtensor::network::{NetworkConfig, NetworkConfigError};
+use crabtensor::probe::ProbeOptions;
+use crabtensor::registration::{RegistrationError, SolverOptions};
+use crabtensor::subtensor::{self, Subtensor, UnknownNetworkError};
+use crabtensor::wallet::{
+    coldkeypub_location, home_wallets_location, hotkey_location, load_key_account_id,
+    load_key_seed, signer_from_seed, AccountLoadingError, Signer,
+};
+use crabtensor::{AccountId, BlockRef, Hash, SubtensorConfig};
+use subxt::backend::legacy::LegacyRpcMethods;
+use thiserror::Error;
+
+use crate::output::Output;
+
+#[derive(Debug, Error)]
+pub enum CliError {
+    // Boxed, as both errors are large and most commands return neither
+    #[error(transparent)]
+    Subxt(Box<subxt::Error>),
+
+    #[error(transparent)]
+    Wallet(#[from] AccountLoadingError),
+
+    #[error(transparent)]
+    Registration(Box<RegistrationError>),
+
+    #[error(transparent)]
+    Network(#[from] UnknownNetworkError),
+
+    #[error(transparent)]
+    NetworkConfig(#[from] NetworkConfigError),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    #[error("Subnet {0} does not exist")]
+    SubnetNotFound(u16),
+
+    #[error("{0}")]
+    Other(String),
+}
+
+impl From<subxt::Error> for CliError {
+    fn from(error: subxt::Error) -> Self {
+        Self::Subxt(Box::new(error))
+    }
+}
+
+impl From<subxt::ext::subxt_core::Error> for CliError {
+    fn from(error: subxt::ext::subxt_core::Error) -> Self {
+        Self::Subxt(Box::new(error.into()))
+    }
+}
+
+impl From<RegistrationError> for CliError {
+    fn from(error: RegistrationError) -> Self {
+        Self::Registration(Box::new(error))
+    }
+}
+
+fn netuid_arg() -> Arg {
+    Arg::new("netuid")
+        .long("netuid")
+        .required(true)
+        .value_parser(value_parser!(u16))
+}
+
+fn block_arg() -> Arg {
+    Arg::new("block")
+        .long("block")
+        .help("Block number or hash to query at, the latest finalized block by default")
+}
+
+fn wallet_args() -> [Arg; 2] {
+    [
+        Arg::new("wallet")
+            .long("wallet")
+            .default_value("default")
+            .help("Wallet name"),
+        Arg::new("hotkey")
+            .long("hotkey")
+            .default_value("default")
+            .help("Hotkey name"),
+    ]
+}
+
+fn cli() -> Command {
+    Command::new("crabtensor")
+        .about("Common Bittensor operator tasks")
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .arg(
+            Arg::new("network")
+                .long("network")
+                .global(true)
+                .help("finney, test, archive, local or a ws:// or wss:// URL, overriding the config and environment"),
+        )
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .global(true)
+                .value_parser(value_parser!(PathBuf))
+                .help("Network configuration TOML file"),
+        )
+        .arg(
+            Arg::new("wallet-path")
+                .long("wallet-path")
+                .global(true)
+                .value_parser(value_parser!(PathBuf))
+                .help("Wallets directory, ~/.bittensor/wallets by default"),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Print JSON instead of tables"),
+        )
+        .subcommand(
+            Command::new("wallet")
+                .about("Manage local wallets")
+                .subcommand_required(true)
+                .subcommand(Command::new("list").about("List wallets and their keys"))
+                .subcommand(
+                    Command::new("create")
+                        .about("Create a new unencrypted hotkey")
+                        .args(wallet_args()),
+                )
+                .subcommand(
+                    Command::new("inspect")
+                        .about("Show the addresses and public keys of a wallet")
+                        .arg(wallet_args()[0].clone()),
+                ),
+        )
+        .subcommand(
+            Command::new("metagraph")
+                .about("Show the neurons of a subnet")
+                .arg(netuid_arg())
+                .arg(block_arg()),
+        )
+        .subcommand(
+            Command::new("probe")
+                .about("Check which axons of a subnet accept TCP connections")
+                .arg(netuid_arg())
+                .arg(block_arg())
+                .arg(
+                    Arg::new("timeout")
+                        .long("timeout")
+                        .value_parser(value_parser!(u64))
+                        .default_value("3000")
+                        .help("Connection timeout in milliseconds"),
+                )
+                .arg(
+                    Arg::new("concurrency")
+                        .long("concurrency")
+                        .value_parser(value_parser!(usize))
+                        .default_value("64"),
+                )
+                .arg(
+                    Arg::new("private")
+                        .long("private")
+                        .action(ArgAction::SetTrue)
+                        .help("Also probe private and loopback addresses"),
+                ),
+        )
+        .subcommand(
+            Command::new("hyperparameters")
+                .about("Show the hyperparameters of a subnet")
+                .arg(netuid_arg())
+                .arg(block_arg()),
+        )
+        .subcommand(
+            Command::new("stake")
+                .about("Show the free balance and stakes of a coldkey")
+                .arg(
+                    Arg::new("coldkey")
+                        .long("coldkey")
+                        .help("SS58 address, the wallet's coldkey by default"),
+                )
+                .arg(wallet_args()[0].clone())
+                .arg(block_arg()),
+        )
+        .subcommand(
+            Command::new("set-weights")
+                .about("Set weights from a JSON file of uid to weight, signed by the hotkey")
+                .arg(netuid_arg())
+                .arg(
+                    Arg::new("weights")
+                        .long("weights")
+                        .required(true)
+                        .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("version-key")
+                        .long("version-key")
+                        .value_parser(value_parser!(u64))
+                        .help("The subnet's weights version key by default"),
+                )
+                .args(wallet_args()),
+        )
+        .subcommand(
+            Command::new("serve-axon")
+                .about("Publish the hotkey's axon address")
+                .arg(netuid_arg())
+                .arg(
+                    Arg::new("address")
+                        .long("address")
+                        .required(true)
+                        .value_parser(value_parser!(SocketAddr)),
+                )
+                .arg(
+                    Arg::new("protocol")
+                        .long("protocol")
+                        .value_parser(["tcp", "udp", "other"])
+                        .default_value("other"),
+                )
+                .args(wallet_args()),
+        )
+        .subcommand(
+            Command::new("register")
+                .about("Register the hotkey with proof of work")
+                .arg(netuid_arg())
+                .arg(
+                    Arg::new("threads")
+                        .long("threads")
+                        .value_parser(value_parser!(usize)),
+                )
+                .args(wallet_args()),
+        )
+        .subcommand(
+            Command::new("events")
+                .about("Show the events of a block")
+                .arg(block_arg().required(true))
+                .arg(
+                    Arg::new("pallet")
+                        .long("pallet")
+                        .help("Only show events of this pallet"),
+                ),
+        )
+}
+
+struct Context<'a> {
+    matches: &'a ArgMatches,
+    network: Option<String>,
+    config: Option<PathBuf>,
+    wallets: Option<PathBuf>,
+}
+
+impl Context<'_> {
+    fn string(&self, id: &str) -> Option<&str> {
+        self.matches.get_one::<String>(id).map(String::as_str)
+    }
+
+    fn netuid(&self) -> u16 {
+        *self.matches.get_one("netuid").expect("netuid is required")
+    }
+
+    fn wallets(&self) -> Result<PathBuf, CliError> {
+        self.wallets
+            .clone()
+            .or_else(home_wallets_location)
+            .ok_or_else(|| CliError::Other("No home directory, pass --wallet-path".to_string()))
+    }
+
+    fn wallet(&self) -> &str {
+        self.string("wallet").expect("wallet has a default")
+    }
+
+    fn hotkey(&self) -> &str {
+        self.string("hotkey").expect("hotkey has a default")
+    }
+
+    fn signer(&self) -> Result<Signer, CliError> {
+        let seed = load_key_seed(hotkey_location(
+            self.wallets()?,
+            self.wallet(),
+            self.hotkey(),
+        ))?;
+
+        signer_from_seed(&seed)
+            .map_err(|error| CliError::Other(format!("Invalid hotkey seed: {error:?}")))
+    }
+
+    fn coldkey(&self) -> Result<AccountId, CliError> {
+        Ok(load_key_account_id(coldkeypub_location(
+            self.wallets()?,
+            self.wallet(),
+        ))?)
+    }
+
+    fn network_config(&self) -> Result<NetworkConfig, CliError> {
+        let mut config = NetworkConfig::load(self.config.as_ref())?;
+
+        // Like SUBTENSOR_NETWORK, selecting a network drops any endpoint override
+        if let Some(network) = &self.network {
+            config.network = network.clone();
+            config.chain_endpoint = None;
+        }
+
+        Ok(config)
+    }
+
+    /// Connects to the first reachable endpoint of the network configuration, trying the fallbacks in order.
+    async fn connect(&self) -> Result<(Subtensor, LegacyRpcMethods<SubtensorConfig>), CliError> {
+        let config = self.network_config()?;
+        let options = config.options();
+
+        let mut last_error = None;
+
+        for endpoint in config.endpoints()? {
+            let connection = async {
+                let rpc = subtensor::rpc_client_with_options(&endpoint, &options).await?;
+                let client = Subtensor::from_rpc_client(rpc.clone()).await?;
+
+                Ok::<_, subxt::Error>((client, LegacyRpcMethods::new(rpc)))
+            };
+
+            match connection.await {
+                Ok(connection) => return Ok(connection),
+                Err(error) => last_error = Some(error),
+            }
+        }
+
+        Err(last_error
+            .expect("endpoints always include the main endpoint")
+            .into())
+    }
+
+    /// The block given with `--block` as a number or hash, or the latest finalized block.
+    async fn at(
+        &self,
+        client: &Subtensor,
+        rpc: &LegacyRpcMethods<SubtensorConfig>,
+    ) -> Result<BlockRef, CliError> {
+        let Some(block) = self.string("block") else {
+            return Ok(client.blocks().at_latest().await?.reference());
+        };
+
+        if let Some(hex) = block.strip_prefix("0x") {
+            let mut hash = [0; 32];
+            hex::decode_to_slice(hex, &mut hash)
+                .map_err(|error| CliError::Other(format!("Invalid block hash {block}: {error}")))?;
+
+            return Ok(BlockRef::from_hash(Hash::from(hash)));
+        }
+
+        let number: u32 = block.parse().map_err(|_| {
+            CliError::Other(format!(
+                "Invalid block {block}, expected a number or 0x hash"
+            ))
+        })?;
+
+        let hash = rpc
+            .chain_get_block_hash(Some(number.into()))
+            .await?
+            .ok_or_else(|| CliError::Other(format!("Block {number} not found")))?;
+
+        Ok(BlockRef::from_hash(hash))
+    }
+}
+
+async fn run(matches: &ArgMatches) -> Result<Output, CliError> {
+    let (command, matches) = matches.subcommand().expect("subcommand is required");
+
+    let context = Context {
+        matches,
+        network: matches.get_one::<String>("network").cloned(),
+        config: matches.get_one::<PathBuf>("config").cloned(),
+        wallets: matches.get_one::<PathBuf>("wallet-path").cloned(),
+    };
+
+    if command == "wallet" {
+        let (command, matches) = matches.subcommand().expect("subcommand is required");
+        let context = Context { matches, ..context };
+
+        return match command {
+            "list" => wallet::list(&context.wallets()?),
+            "create" => wallet::create(context.wallets()?, context.wallet(), context.hotkey()),
+            "inspect" => wallet::inspect(&context.wallets()?, context.wallet()),
+            _ => unreachable!("unknown wallet subcommand {command}"),
+        };
+    }
+
+    let (client, rpc) = context.connect().await?;
+
+    match command {
+        "metagraph" => {
+            let at = context.at(&client, &rpc).await?;
+            chain::metagraph(&client, at, context.netuid()).await
+        }
+        "probe" => {
+            let options = ProbeOptions {
+                concurrency: *matches
+                    .get_one("concurrency")
+                    .expect("concurrency has a default"),
+                timeout: Duration::from_millis(
+                    *matches.get_one("timeout").expect("timeout has a default"),
+                ),
+                probe_private: matches.get_flag("private"),
+            };
+
+            let at = context.at(&client, &rpc).await?;
+            chain::probe(&client, at, context.netuid(), options).await
+        }
+        "hyperparameters" => {
+            let at = context.at(&client, &rpc).await?;
+            chain::hyperparameters(&client, at, context.netuid()).await
+        }
+        "stake" => {
+            let coldkey = match context.string("coldkey") {
+                Some(coldkey) => AccountId::from_str(coldkey).map_err(|error| {
+                    CliError::Other(format!("Invalid coldkey {coldkey}: {error:?}"))
+                })?,
+                None => context.coldkey()?,
+            };
+
+            let at = context.at(&client, &rpc).await?;
+            chain::stake(&client, at, coldkey).await
+        }
+        "set-weights" => {
+            let path = matches
+                .get_one::<PathBuf>("weights")
+                .expect("weights is required");
+            let version_key = matches.get_one::<u64>("version-key").copied();
+
+            chain::set_weights(
+                &client,
+                &context.signer()?,
+                context.netuid(),
+                path,
+                version_key,
+            )
+            .await
+        }
+        "serve-axon" => {
+            let address = *matches
+                .get_one::<SocketAddr>("address")
+                .expect("address is required");
+
+            let protocol = match context.string("protocol") {
+                Some("tcp") => AxonProtocol::Tcp,
+                Some("udp") => AxonProtocol::Udp,
+                _ => AxonProtocol::Other,
+            };
+
+            chain::serve_axon(
+                &client,
+                &context.signer()?,
+                context.netuid(),
+                address,
+                protocol,
+            )
+            .await
+        }
+        "register" => {
+            let mut options = SolverOptions::default();
+
+            if let Some(&threads) = matches.get_one::<usize>("threads") {
+                options.threads = threads;
+            }
+
+            chain::register(
+                &client,
+                &context.signer()?,
+                context.coldkey()?,
+                context.netuid(),
+                options,
+            )
+            .await
+        }
+        "events" => {
+            let at = context.at(&client, &rpc).await?;
+            chain::events(&client, at, context.string("pallet")).await
+        }
+        _ => unreachable!("unknown subcommand {command}"),
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    let matches = cli().get_matches();
+
+    match run(&matches).await {
+        Ok(output) => {
+            output.print(matches.get_flag("json"));
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("{error}");
+            ExitCode::FAILURE
+        }
+    }
+}
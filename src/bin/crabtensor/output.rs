@@ -0,0 +1,106 @@
+use serde_json::Value;
+
+/// What a command prints, as a human readable text and as JSON for scripts.
+pub struct Output {
+    pub text: String,
+    pub json: Value,
+}
+
+impl Output {
+    pub fn new(text: impl Into<String>, json: Value) -> Self {
+        Self {
+            text: text.into(),
+            json,
+        }
+    }
+
+    pub fn table(headers: &[&str], rows: Vec<Vec<String>>, json: Value) -> Self {
+        Self::new(table(headers, &rows), json)
+    }
+
+    pub fn print(&self, json: bool) {
+        if json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&self.json).expect("JSON values serialize")
+            );
+        } else {
+            println!("{}", self.text.trim_end());
+        }
+    }
+}
+
+/// Aligns `rows` under `headers`, padding every column to its widest cell.
+fn table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|header| header.len()).collect();
+
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let line = |cells: Vec<&str>| {
+        cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    let mut lines = vec![line(headers.to_vec())];
+    lines.extend(
+        rows.iter()
+            .map(|row| line(row.iter().map(String::as_str).collect())),
+    );
+
+    lines.join("\n")
+}
+
+/// Formats an amount in rao as TAO, or as alpha for subnet stake.
+pub fn tao(rao: u64) -> String {
+    format!("{}.{:09}", rao / 1_000_000_000, rao % 1_000_000_000)
+}
+
+/// Formats a `u16` fixed-point fraction of `u16::MAX`.
+pub fn fraction(value: u16) -> String {
+    format!("{:.5}", value as f64 / u16::MAX as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_rao_as_tao() {
+        assert_eq!(tao(0), "0.000000000");
+        assert_eq!(tao(1), "0.000000001");
+        assert_eq!(tao(1_000_000_000), "1.000000000");
+        assert_eq!(tao(21_000_000_123_456_789), "21000000.123456789");
+        assert_eq!(tao(u64::MAX), "18446744073.709551615");
+    }
+
+    #[test]
+    fn formats_fractions_of_u16_max() {
+        assert_eq!(fraction(0), "0.00000");
+        assert_eq!(fraction(u16::MAX), "1.00000");
+        assert_eq!(fraction(32_768), "0.50001");
+    }
+
+    #[test]
+    fn aligns_columns_to_the_widest_cell() {
+        let rows = vec![
+            vec!["1".to_string(), "alice".to_string(), "0.5".to_string()],
+            vec!["10".to_string(), "bob".to_string(), "".to_string()],
+        ];
+
+        assert_eq!(
+            table(&["UID", "NAME", "STAKE"], &rows),
+            "UID  NAME   STAKE\n1    alice  0.5\n10   bob"
+        );
+        assert_eq!(table(&["UID", "NAME"], &[]), "UID  NAME");
+    }
+}
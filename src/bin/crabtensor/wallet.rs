@@ -0,0 +1,166 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crabtensor::wallet::{
+    coldkeypub_location, generate_key_file, hotkey_location, load_key_account_id,
+};
+use crabtensor::AccountId;
+use serde_json::json;
+
+use crate::output::Output;
+use crate::CliError;
+
+struct Key {
+    name: String,
+    account_id: Option<AccountId>,
+}
+
+struct Wallet {
+    name: String,
+    coldkey: Option<AccountId>,
+    hotkeys: Vec<Key>,
+}
+
+fn entries(path: &Path) -> Result<Vec<String>, CliError> {
+    if !path.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut names = fs::read_dir(path)?
+        .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+        .collect::<Result<Vec<_>, std::io::Error>>()?;
+
+    names.sort();
+
+    Ok(names)
+}
+
+fn read_wallet(wallets: &Path, name: &str) -> Result<Wallet, CliError> {
+    let mut hotkeys_path = wallets.join(name);
+    hotkeys_path.push("hotkeys");
+
+    let hotkeys = entries(&hotkeys_path)?
+        .into_iter()
+        .map(|hotkey| Key {
+            account_id: load_key_account_id(hotkey_location(wallets.to_path_buf(), name, &hotkey))
+                .ok(),
+            name: hotkey,
+        })
+        .collect();
+
+    Ok(Wallet {
+        name: name.to_string(),
+        coldkey: load_key_account_id(coldkeypub_location(wallets.to_path_buf(), name)).ok(),
+        hotkeys,
+    })
+}
+
+fn address(account_id: &Option<AccountId>) -> String {
+    account_id
+        .as_ref()
+        .map_or_else(|| "-".to_string(), AccountId::to_string)
+}
+
+fn wallet_json(wallet: &Wallet) -> serde_json::Value {
+    json!({
+        "name": wallet.name,
+        "coldkey": wallet.coldkey.as_ref().map(AccountId::to_string),
+        "hotkeys": wallet.hotkeys.iter().map(|hotkey| json!({
+            "name": hotkey.name,
+            "ss58_address": hotkey.account_id.as_ref().map(AccountId::to_string),
+        })).collect::<Vec<_>>(),
+    })
+}
+
+fn wallet_rows(wallet: &Wallet) -> Vec<Vec<String>> {
+    let mut rows = vec![vec![
+        wallet.name.clone(),
+        "coldkey".to_string(),
+        address(&wallet.coldkey),
+    ]];
+
+    rows.extend(wallet.hotkeys.iter().map(|hotkey| {
+        vec![
+            wallet.name.clone(),
+            format!("hotkey {}", hotkey.name),
+            address(&hotkey.account_id),
+        ]
+    }));
+
+    rows
+}
+
+pub fn list(wallets: &Path) -> Result<Output, CliError> {
+    let wallets = entries(wallets)?
+        .iter()
+        .map(|name| read_wallet(wallets, name))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Output::table(
+        &["WALLET", "KEY", "SS58 ADDRESS"],
+        wallets.iter().flat_map(wallet_rows).collect(),
+        wallets.iter().map(wallet_json).collect(),
+    ))
+}
+
+pub fn inspect(wallets: &Path, name: &str) -> Result<Output, CliError> {
+    let wallet = read_wallet(wallets, name)?;
+
+    if wallet.coldkey.is_none() && wallet.hotkeys.is_empty() {
+        return Err(CliError::Other(format!(
+            "No wallet named {name} in {}",
+            wallets.display()
+        )));
+    }
+
+    let public_key = |account_id: &Option<AccountId>| {
+        account_id.as_ref().map_or_else(
+            || "-".to_string(),
+            |account_id| format!("0x{}", hex::encode(account_id.0)),
+        )
+    };
+
+    let mut rows = wallet_rows(&wallet);
+    let keys = std::iter::once(&wallet.coldkey)
+        .chain(wallet.hotkeys.iter().map(|hotkey| &hotkey.account_id));
+
+    for (row, account_id) in rows.iter_mut().zip(keys) {
+        row.push(public_key(account_id));
+    }
+
+    Ok(Output::table(
+        &["WALLET", "KEY", "SS58 ADDRESS", "PUBLIC KEY"],
+        rows,
+        wallet_json(&wallet),
+    ))
+}
+
+/// Creates a hotkey, coldkeys are encrypted and are created with btcli.
+pub fn create(wallets: PathBuf, name: &str, hotkey: &str) -> Result<Output, CliError> {
+    let path = hotkey_location(wallets, name, hotkey);
+
+    if path.exists() {
+        return Err(CliError::Other(format!(
+            "{} already exists, pick another hotkey name",
+            path.display()
+        )));
+    }
+
+    let (signer, phrase) = generate_key_file(&path)?;
+    let address = signer.account_id().to_string();
+
+    Ok(Output::new(
+        format!(
+            "Created hotkey {hotkey} of wallet {name} at {}\nSS58 address: {address}\nRecovery phrase: {phrase}\n\
+             Store the recovery phrase safely, it is the only way to restore the hotkey.",
+            path.display()
+        ),
+        json!({
+            "wallet": name,
+            "hotkey": hotkey,
+            "path": path,
+            "ss58_address": address,
+            "secret_phrase": phrase,
+        }),
+    ))
+}
@@ -0,0 +1,291 @@
+//! Memoizes storage and runtime API results per block hash.
+//!
+//! The state at a block hash never changes, so results are reused until they are evicted, least recently used
+//! first. Identical requests made while one is in flight wait for it instead of reaching the node. Caching the best
+//! block is safe as well, as a reorg changes the hash rather than the state behind it.
+
+use std::any::{Any, TypeId};
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use subxt::runtime_api::Payload;
+use subxt::storage::Address;
+use subxt::utils::Yes;
+use tokio::sync::OnceCell;
+
+use crate::subtensor::Subtensor;
+use crate::{BlockRef, Hash};
+
+#[derive(Clone, Copy, Debug)]
+pub struct CacheOptions {
+    /// Maximum number of results kept across all blocks.
+    pub capacity: usize,
+}
+
+impl Default for CacheOptions {
+    fn default() -> Self {
+        Self { capacity: 4096 }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    /// Requests that reached the node, including those that failed.
+    pub misses: u64,
+    /// Requests that waited for an identical request in flight instead of reaching the node.
+    pub coalesced: u64,
+    pub evictions: u64,
+    pub entries: usize,
+}
+
+impl CacheStats {
+    /// The fraction of requests that did not reach the node.
+    pub fn hit_ratio(&self) -> f64 {
+        let total = self.hits + self.misses + self.coalesced;
+
+        if total == 0 {
+            0.0
+        } else {
+            (self.hits + self.coalesced) as f64 / total as f64
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Kind {
+    Storage,
+    RuntimeApi,
+}
+
+/// Results of different types are kept apart, as static and dynamic addresses of the same entry share their bytes.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Key {
+    block: Hash,
+    kind: Kind,
+    target: TypeId,
+    bytes: Vec<u8>,
+}
+
+type Cell = Arc<OnceCell<Arc<dyn Any + Send + Sync>>>;
+
+struct Entry {
+    cell: Cell,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct Lru {
+    entries: HashMap<Key, Entry>,
+    /// Keys ordered by when they were last used.
+    order: BTreeMap<u64, Key>,
+    clock: u64,
+}
+
+impl Lru {
+    fn cell(&mut self, key: Key, capacity: usize, evictions: &AtomicU64) -> Cell {
+        self.clock += 1;
+        let clock = self.clock;
+
+        if let Some(entry) = self.entries.get_mut(&key) {
+            self.order.remove(&entry.last_used);
+            self.order.insert(clock, key);
+            entry.last_used = clock;
+
+            return entry.cell.clone();
+        }
+
+        while self.entries.len() >= capacity.max(1) {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+
+            self.entries.remove(&oldest);
+            evictions.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let cell = Cell::default();
+
+        self.order.insert(clock, key.clone());
+        self.entries.insert(
+            key,
+            Entry {
+                cell: cell.clone(),
+                last_used: clock,
+            },
+        );
+
+        cell
+    }
+
+    fn remove_block(&mut self, block: Hash) {
+        self.entries.retain(|key, _| key.block != block);
+        self.order.retain(|_, key| key.block != block);
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    coalesced: AtomicU64,
+    evictions: AtomicU64,
+}
+
+/// A [`Subtensor`] client whose storage and runtime API results are memoized per block hash, cheap to clone and
+/// sharing its cache between clones.
+#[derive(Clone)]
+pub struct CachedSubtensor {
+    client: Subtensor,
+    options: CacheOptions,
+    lru: Arc<Mutex<Lru>>,
+    counters: Arc<Counters>,
+}
+
+impl CachedSubtensor {
+    pub fn new(client: Subtensor) -> Self {
+        Self::with_options(client, CacheOptions::default())
+    }
+
+    pub fn with_options(client: Subtensor, options: CacheOptions) -> Self {
+        Self {
+            client,
+            options,
+            lru: Arc::default(),
+            counters: Arc::default(),
+        }
+    }
+
+    /// The wrapped client, for uncached calls.
+    pub fn client(&self) -> &Subtensor {
+        &self.client
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.counters.hits.load(Ordering::Relaxed),
+            misses: self.counters.misses.load(Ordering::Relaxed),
+            coalesced: self.counters.coalesced.load(Ordering::Relaxed),
+            evictions: self.counters.evictions.load(Ordering::Relaxed),
+            entries: self.lru.lock().unwrap().entries.len(),
+        }
+    }
+
+    /// Drops the results cached at `block`, such as when it is no longer needed.
+    pub fn invalidate(&self, block: Hash) {
+        self.lru.lock().unwrap().remove_block(block);
+    }
+
+    pub fn clear(&self) {
+        *self.lru.lock().unwrap() = Lru::default();
+    }
+
+    async fn cached<T, F>(&self, key: Key, fetch: F) -> Result<T, subxt::Error>
+    where
+        T: Clone + Send + Sync + 'static,
+        F: Future<Output = Result<T, subxt::Error>>,
+    {
+        let cell =
+            self.lru
+                .lock()
+                .unwrap()
+                .cell(key, self.options.capacity, &self.counters.evictions);
+
+        let was_ready = cell.initialized();
+        let fetched = AtomicBool::new(false);
+
+        let value = cell
+            .get_or_try_init(|| async {
+                fetched.store(true, Ordering::Relaxed);
+
+                let value: Arc<dyn Any + Send + Sync> = Arc::new(fetch.await?);
+
+                Ok::<_, subxt::Error>(value)
+            })
+            .await;
+
+        // A failed fetch reached the node too, and leaves the cell empty for the next request to retry
+        let counter = if fetched.load(Ordering::Relaxed) {
+            &self.counters.misses
+        } else if was_ready {
+            &self.counters.hits
+        } else {
+            &self.counters.coalesced
+        };
+
+        counter.fetch_add(1, Ordering::Relaxed);
+
+        Ok(value?
+            .downcast_ref::<T>()
+            .expect("cache keys include the result type")
+            .clone())
+    }
+
+    /// Cached [`subxt::storage::Storage::fetch`].
+    pub async fn fetch<Addr>(
+        &self,
+        at: BlockRef,
+        address: &Addr,
+    ) -> Result<Option<Addr::Target>, subxt::Error>
+    where
+        Addr: Address<IsFetchable = Yes>,
+        Addr::Target: Clone + Send + Sync + 'static,
+    {
+        let key = Key {
+            block: at.hash(),
+            kind: Kind::Storage,
+            target: TypeId::of::<Option<Addr::Target>>(),
+            bytes: self.client.storage().address_bytes(address)?,
+        };
+
+        self.cached(key, self.client.storage().at(at).fetch(address))
+            .await
+    }
+
+    /// Cached [`subxt::storage::Storage::fetch_or_default`].
+    pub async fn fetch_or_default<Addr>(
+        &self,
+        at: BlockRef,
+        address: &Addr,
+    ) -> Result<Addr::Target, subxt::Error>
+    where
+        Addr: Address<IsFetchable = Yes, IsDefaultable = Yes>,
+        Addr::Target: Clone + Send + Sync + 'static,
+    {
+        let key = Key {
+            block: at.hash(),
+            kind: Kind::Storage,
+            target: TypeId::of::<Addr::Target>(),
+            bytes: self.client.storage().address_bytes(address)?,
+        };
+
+        self.cached(key, self.client.storage().at(at).fetch_or_default(address))
+            .await
+    }
+
+    /// Cached [`subxt::runtime_api::RuntimeApi::call`].
+    pub async fn runtime_api_call<Call>(
+        &self,
+        at: BlockRef,
+        payload: Call,
+    ) -> Result<Call::ReturnType, subxt::Error>
+    where
+        Call: Payload,
+        Call::ReturnType: Clone + Send + Sync + 'static,
+    {
+        let mut bytes = format!("{}_{}", payload.trait_name(), payload.method_name()).into_bytes();
+        payload.encode_args_to(&self.client.metadata(), &mut bytes)?;
+
+        let key = Key {
+            block: at.hash(),
+            kind: Kind::RuntimeApi,
+            target: TypeId::of::<Call::ReturnType>(),
+            bytes,
+        };
+
+        self.cached(key, self.client.runtime_api().at(at).call(payload))
+            .await
+    }
+}
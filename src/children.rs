@@ -0,0 +1,413 @@
+//! Child hotkeys, which let a parent hotkey lend a proportion of its stake on a subnet to other hotkeys.
+
+use subxt::ext::futures::future::try_join_all;
+use subxt::ext::futures::try_join;
+use subxt::tx::Payload;
+use thiserror::Error;
+
+use crate::subtensor::Subtensor;
+use crate::{api, AccountId, BlockRef};
+
+#[derive(Debug, Error)]
+pub enum ChildrenError {
+    #[error("Child proportion {0} is not between 0 and 1")]
+    InvalidProportion(f64),
+
+    #[error("Child proportions sum to {0}, more than 1")]
+    ProportionsExceedOne(f64),
+
+    #[error("Childkey take {0} is not between 0 and 1")]
+    InvalidTake(f64),
+
+    #[error("A hotkey can not be its own child")]
+    OwnChild,
+}
+
+/// A child or parent hotkey, along with the proportion of the parent's stake it receives as a fraction of `u64::MAX`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Relationship {
+    pub proportion: u64,
+    pub hotkey: AccountId,
+}
+
+impl Relationship {
+    pub fn fraction(&self) -> f64 {
+        self.proportion as f64 / u64::MAX as f64
+    }
+}
+
+impl From<(u64, AccountId)> for Relationship {
+    fn from((proportion, hotkey): (u64, AccountId)) -> Self {
+        Self { proportion, hotkey }
+    }
+}
+
+/// Children set with [`set_children_payload`] that only take effect once the cooldown block is reached.
+#[derive(Clone, Debug)]
+pub struct PendingChildren {
+    pub children: Vec<Relationship>,
+    pub cooldown_block: u64,
+}
+
+/// Converts each child's fraction of the parent's stake to a proportion of `u64::MAX`, checking they sum to at most 1.
+pub fn child_proportions(
+    children: impl IntoIterator<Item = (AccountId, f64)>,
+) -> Result<Vec<Relationship>, ChildrenError> {
+    let children: Vec<(AccountId, f64)> = children.into_iter().collect();
+
+    if let Some(&(_, fraction)) = children
+        .iter()
+        .find(|(_, fraction)| !(0.0..=1.0).contains(fraction))
+    {
+        return Err(ChildrenError::InvalidProportion(fraction));
+    }
+
+    let sum: f64 = children.iter().map(|(_, fraction)| fraction).sum();
+
+    // Leeway for float sums such as 0.1 + 0.2 + 0.7
+    if sum > 1.0 + 1e-9 {
+        return Err(ChildrenError::ProportionsExceedOne(sum));
+    }
+
+    let mut children: Vec<Relationship> = children
+        .into_iter()
+        .map(|(hotkey, fraction)| Relationship {
+            proportion: (fraction * u64::MAX as f64) as u64,
+            hotkey,
+        })
+        .collect();
+
+    // Rounding can push a sum of 1 past u64::MAX, which the pallet rejects as an overflow
+    let total: u128 = children.iter().map(|child| child.proportion as u128).sum();
+
+    if let Some(excess) = total
+        .checked_sub(u64::MAX as u128)
+        .filter(|&excess| excess > 0)
+    {
+        if let Some(largest) = children.iter_mut().max_by_key(|child| child.proportion) {
+            largest.proportion -= excess as u64;
+        }
+    }
+
+    Ok(children)
+}
+
+/// Sets the children of `hotkey` on `netuid`, replacing the current ones after a cooldown. Signed by its coldkey, an
+/// empty `children` revokes all children.
+pub fn set_children_payload(
+    hotkey: AccountId,
+    netuid: u16,
+    children: impl IntoIterator<Item = (AccountId, f64)>,
+) -> Result<impl Payload, ChildrenError> {
+    let children = child_proportions(children)?;
+
+    if children.iter().any(|child| child.hotkey == hotkey) {
+        return Err(ChildrenError::OwnChild);
+    }
+
+    Ok(api::tx().subtensor_module().set_children(
+        hotkey,
+        netuid,
+        children
+            .into_iter()
+            .map(|child| (child.proportion, child.hotkey))
+            .collect(),
+    ))
+}
+
+/// Sets the share of the emissions `hotkey` keeps from the stake its parents lend it, as a fraction. The pallet caps
+/// it at `MaxChildkeyTake`.
+pub fn set_childkey_take_payload(
+    hotkey: AccountId,
+    netuid: u16,
+    take: f64,
+) -> Result<impl Payload, ChildrenError> {
+    if !(0.0..=1.0).contains(&take) {
+        return Err(ChildrenError::InvalidTake(take));
+    }
+
+    Ok(api::tx().subtensor_module().set_childkey_take(
+        hotkey,
+        netuid,
+        (take * u16::MAX as f64).round() as u16,
+    ))
+}
+
+pub async fn children(
+    client: &Subtensor,
+    at: BlockRef,
+    netuid: u16,
+    hotkey: &AccountId,
+) -> Result<Vec<Relationship>, subxt::Error> {
+    let children = client
+        .storage()
+        .at(at)
+        .fetch_or_default(&api::storage().subtensor_module().child_keys(hotkey, netuid))
+        .await?;
+
+    Ok(children.into_iter().map(Relationship::from).collect())
+}
+
+pub async fn parents(
+    client: &Subtensor,
+    at: BlockRef,
+    netuid: u16,
+    hotkey: &AccountId,
+) -> Result<Vec<Relationship>, subxt::Error> {
+    let parents = client
+        .storage()
+        .at(at)
+        .fetch_or_default(
+            &api::storage()
+                .subtensor_module()
+                .parent_keys(hotkey, netuid),
+        )
+        .await?;
+
+    Ok(parents.into_iter().map(Relationship::from).collect())
+}
+
+/// The children `hotkey` is waiting to set on `netuid`, if any.
+pub async fn pending_children(
+    client: &Subtensor,
+    at: BlockRef,
+    netuid: u16,
+    hotkey: &AccountId,
+) -> Result<Option<PendingChildren>, subxt::Error> {
+    let pending = client
+        .storage()
+        .at(at)
+        .fetch(
+            &api::storage()
+                .subtensor_module()
+                .pending_child_keys(netuid, hotkey),
+        )
+        .await?;
+
+    Ok(pending.map(|(children, cooldown_block)| PendingChildren {
+        children: children.into_iter().map(Relationship::from).collect(),
+        cooldown_block,
+    }))
+}
+
+/// The take of `hotkey` as a fraction of `u16::MAX`.
+pub async fn childkey_take(
+    client: &Subtensor,
+    at: BlockRef,
+    netuid: u16,
+    hotkey: &AccountId,
+) -> Result<u16, subxt::Error> {
+    client
+        .storage()
+        .at(at)
+        .fetch_or_default(
+            &api::storage()
+                .subtensor_module()
+                .childkey_take(hotkey, netuid),
+        )
+        .await
+}
+
+/// The alpha stake of a hotkey on a subnet, before and after childkey delegation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ChildkeyStake {
+    /// Staked to the hotkey itself.
+    pub own: u64,
+    /// Lent to its children.
+    pub to_children: u64,
+    /// Lent to it by its parents.
+    pub from_parents: u64,
+}
+
+impl ChildkeyStake {
+    /// The stake the hotkey validates with, mirroring `Pallet::get_inherited_for_hotkey_on_subnet`.
+    pub fn effective(&self) -> u64 {
+        self.own
+            .saturating_sub(self.to_children)
+            .saturating_add(self.from_parents)
+    }
+}
+
+fn share(stake: u64, proportion: u64) -> u64 {
+    (stake as u128 * proportion as u128 / u64::MAX as u128) as u64
+}
+
+/// Computes the stake a hotkey lends and inherits on `netuid` from its own stake, its children and each parent with its
+/// own stake. Stake is not delegated on the root subnet, so there the hotkey keeps its own stake.
+pub fn inherited_stake(
+    netuid: u16,
+    own: u64,
+    children: &[Relationship],
+    parents: impl IntoIterator<Item = (Relationship, u64)>,
+) -> ChildkeyStake {
+    if netuid == 0 {
+        return ChildkeyStake {
+            own,
+            ..ChildkeyStake::default()
+        };
+    }
+
+    let to_children = children
+        .iter()
+        .map(|child| share(own, child.proportion))
+        .fold(0, u64::saturating_add);
+
+    let from_parents = parents
+        .into_iter()
+        .map(|(parent, parent_stake)| share(parent_stake, parent.proportion))
+        .fold(0, u64::saturating_add);
+
+    ChildkeyStake {
+        own,
+        to_children: to_children.min(own),
+        from_parents,
+    }
+}
+
+pub async fn childkey_stake(
+    client: &Subtensor,
+    at: BlockRef,
+    netuid: u16,
+    hotkey: &AccountId,
+) -> Result<ChildkeyStake, subxt::Error> {
+    let storage = client.storage().at(at.clone());
+    let own = api::storage()
+        .subtensor_module()
+        .total_hotkey_alpha(hotkey, netuid);
+
+    if netuid == 0 {
+        let own = storage.fetch_or_default(&own).await?;
+
+        return Ok(inherited_stake(netuid, own, &[], []));
+    }
+
+    let (own, children, parents) = try_join!(
+        storage.fetch_or_default(&own),
+        children(client, at.clone(), netuid, hotkey),
+        parents(client, at, netuid, hotkey),
+    )?;
+
+    let parent_stakes: Vec<_> = parents
+        .iter()
+        .map(|parent| {
+            api::storage()
+                .subtensor_module()
+                .total_hotkey_alpha(&parent.hotkey, netuid)
+        })
+        .collect();
+
+    let parent_stakes = try_join_all(
+        parent_stakes
+            .iter()
+            .map(|address| storage.fetch_or_default(address)),
+    )
+    .await?;
+
+    Ok(inherited_stake(
+        netuid,
+        own,
+        &children,
+        parents.into_iter().zip(parent_stakes),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hotkey(byte: u8) -> AccountId {
+        AccountId::from([byte; 32])
+    }
+
+    fn total(children: &[Relationship]) -> u128 {
+        children.iter().map(|child| child.proportion as u128).sum()
+    }
+
+    #[test]
+    fn accepts_float_error_in_a_sum_of_one() {
+        let children =
+            child_proportions([(hotkey(1), 0.1), (hotkey(2), 0.2), (hotkey(3), 0.7)]).unwrap();
+
+        assert_eq!(children.len(), 3);
+        assert!(total(&children) <= u64::MAX as u128);
+    }
+
+    #[test]
+    fn rejects_invalid_proportions() {
+        assert!(matches!(
+            child_proportions([(hotkey(1), f64::NAN)]),
+            Err(ChildrenError::InvalidProportion(fraction)) if fraction.is_nan()
+        ));
+        assert!(matches!(
+            child_proportions([(hotkey(1), 1.5)]),
+            Err(ChildrenError::InvalidProportion(fraction)) if fraction == 1.5
+        ));
+        assert!(matches!(
+            child_proportions([(hotkey(1), -0.1)]),
+            Err(ChildrenError::InvalidProportion(_))
+        ));
+        assert!(matches!(
+            child_proportions([(hotkey(1), 0.6), (hotkey(2), 0.5)]),
+            Err(ChildrenError::ProportionsExceedOne(_))
+        ));
+    }
+
+    #[test]
+    fn corrects_rounding_past_u64_max() {
+        // Each half rounds to 2^63, summing to one past u64::MAX
+        let children = child_proportions([(hotkey(1), 0.5), (hotkey(2), 0.5)]).unwrap();
+
+        assert_eq!(total(&children), u64::MAX as u128);
+
+        let children = child_proportions([(hotkey(1), 1.0)]).unwrap();
+
+        assert_eq!(children[0].proportion, u64::MAX);
+    }
+
+    #[test]
+    fn lends_and_inherits_stake() {
+        let children = [
+            Relationship::from((u64::MAX / 4, hotkey(1))),
+            Relationship::from((u64::MAX / 2, hotkey(2))),
+        ];
+        let parents = [
+            (Relationship::from((u64::MAX / 2, hotkey(3))), 2_000),
+            (Relationship::from((u64::MAX, hotkey(4))), 500),
+        ];
+
+        let stake = inherited_stake(1, 1_000, &children, parents.clone());
+
+        assert_eq!(
+            stake,
+            ChildkeyStake {
+                own: 1_000,
+                // Each share rounds down
+                to_children: 249 + 499,
+                from_parents: 999 + 500,
+            }
+        );
+        assert_eq!(stake.effective(), 1_000 - 748 + 1_499);
+
+        assert_eq!(
+            inherited_stake(0, 1_000, &children, parents),
+            ChildkeyStake {
+                own: 1_000,
+                to_children: 0,
+                from_parents: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn lends_at_most_its_own_stake() {
+        let children = [
+            Relationship::from((u64::MAX, hotkey(1))),
+            Relationship::from((u64::MAX, hotkey(2))),
+        ];
+
+        let stake = inherited_stake(1, 1_000, &children, []);
+
+        assert_eq!(stake.to_children, 1_000);
+        assert_eq!(stake.effective(), 0);
+    }
+}
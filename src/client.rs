@@ -0,0 +1,420 @@
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use thiserror::Error;
+use tokio::sync::{broadcast, mpsc, Mutex};
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout};
+
+use crate::subtensor::{self, ConnectionOptions, Subtensor};
+use crate::{api, Block, BlockNumber};
+
+/// How many missed blocks are backfilled after a reconnect when the runtime's `BlockHashCount` can not be read.
+const DEFAULT_MAX_BACKFILL: u32 = 4096;
+
+#[derive(Debug, Error)]
+pub enum ConnectionError {
+    #[error("No endpoints were provided")]
+    NoEndpoints,
+
+    #[error("Could not connect to any endpoint after {0} attempts")]
+    Exhausted(u32),
+}
+
+#[derive(Clone, Debug)]
+pub enum ConnectionEvent {
+    Connected {
+        endpoint: String,
+    },
+    Disconnected {
+        endpoint: String,
+        reason: String,
+    },
+    Reconnecting {
+        attempt: u32,
+        delay: Duration,
+    },
+    Resubscribed {
+        endpoint: String,
+    },
+    /// Blocks finalized while disconnected that were too many to backfill, and were not delivered.
+    Skipped {
+        first: BlockNumber,
+        last: BlockNumber,
+    },
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ReconnectPolicy {
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    /// Rounds over every endpoint before giving up, or `None` to retry forever.
+    pub max_attempts: Option<u32>,
+    /// Bounds a whole connection attempt to one endpoint, including the initial metadata fetch. This wins over the
+    /// [`ConnectionOptions`] timeouts, so raise it along with them for slow nodes.
+    pub connect_timeout: Duration,
+    pub health_check_interval: Duration,
+    pub health_check_timeout: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            max_attempts: None,
+            connect_timeout: Duration::from_secs(10),
+            health_check_interval: Duration::from_secs(12),
+            health_check_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum BlockStreamKind {
+    Best,
+    Finalized,
+}
+
+struct Connection {
+    endpoint: usize,
+    generation: u64,
+    client: Subtensor,
+}
+
+struct Inner {
+    endpoints: Vec<String>,
+    policy: ReconnectPolicy,
+    options: ConnectionOptions,
+    connection: RwLock<Connection>,
+    reconnecting: Mutex<()>,
+    events: broadcast::Sender<ConnectionEvent>,
+}
+
+/// A [`Subtensor`] connection that fails over between several endpoints and reconnects when the current one drops.
+///
+/// Cloning is cheap and clones share the same connection.
+#[derive(Clone)]
+pub struct SubtensorClient {
+    inner: Arc<Inner>,
+}
+
+impl SubtensorClient {
+    /// Connects to the first healthy endpoint, trying them in order.
+    pub async fn connect(
+        endpoints: impl IntoIterator<Item = impl AsRef<str>>,
+        policy: ReconnectPolicy,
+    ) -> Result<Self, ConnectionError> {
+        Self::connect_with_options(endpoints, policy, ConnectionOptions::default()).await
+    }
+
+    pub async fn connect_with_options(
+        endpoints: impl IntoIterator<Item = impl AsRef<str>>,
+        policy: ReconnectPolicy,
+        options: ConnectionOptions,
+    ) -> Result<Self, ConnectionError> {
+        let endpoints: Vec<String> = endpoints
+            .into_iter()
+            .map(|endpoint| endpoint.as_ref().to_string())
+            .collect();
+
+        if endpoints.is_empty() {
+            return Err(ConnectionError::NoEndpoints);
+        }
+
+        let (events, _) = broadcast::channel(64);
+        let (endpoint, client) = establish(&endpoints, 0, &policy, &options, &events).await?;
+
+        Ok(Self {
+            inner: Arc::new(Inner {
+                endpoints,
+                policy,
+                options,
+                connection: RwLock::new(Connection {
+                    endpoint,
+                    generation: 0,
+                    client,
+                }),
+                reconnecting: Mutex::new(()),
+                events,
+            }),
+        })
+    }
+
+    /// The client for the current connection, which stops working if the connection drops.
+    pub fn client(&self) -> Subtensor {
+        self.inner.connection.read().unwrap().client.clone()
+    }
+
+    pub fn endpoint(&self) -> String {
+        let connection = self.inner.connection.read().unwrap();
+
+        self.inner.endpoints[connection.endpoint].clone()
+    }
+
+    pub fn endpoints(&self) -> &[String] {
+        &self.inner.endpoints
+    }
+
+    pub fn events(&self) -> broadcast::Receiver<ConnectionEvent> {
+        self.inner.events.subscribe()
+    }
+
+    /// Checks that the current connection responds within the health check timeout.
+    pub async fn health_check(&self) -> Result<(), subxt::Error> {
+        check(&self.client(), self.inner.policy.health_check_timeout).await
+    }
+
+    /// Replaces the current connection with one to the next healthy endpoint.
+    pub async fn reconnect(&self) -> Result<Subtensor, ConnectionError> {
+        let generation = self.inner.connection.read().unwrap().generation;
+
+        self.reconnect_from(generation, "reconnect requested".to_string())
+            .await
+    }
+
+    /// Reconnects unless another caller already replaced the connection of `generation`.
+    async fn reconnect_from(
+        &self,
+        generation: u64,
+        reason: String,
+    ) -> Result<Subtensor, ConnectionError> {
+        let _guard = self.inner.reconnecting.lock().await;
+
+        let endpoint = {
+            let connection = self.inner.connection.read().unwrap();
+
+            if connection.generation != generation {
+                return Ok(connection.client.clone());
+            }
+
+            connection.endpoint
+        };
+
+        let _ = self.inner.events.send(ConnectionEvent::Disconnected {
+            endpoint: self.inner.endpoints[endpoint].clone(),
+            reason,
+        });
+
+        let (endpoint, client) = establish(
+            &self.inner.endpoints,
+            (endpoint + 1) % self.inner.endpoints.len(),
+            &self.inner.policy,
+            &self.inner.options,
+            &self.inner.events,
+        )
+        .await?;
+
+        *self.inner.connection.write().unwrap() = Connection {
+            endpoint,
+            generation: generation + 1,
+            client: client.clone(),
+        };
+
+        Ok(client)
+    }
+
+    /// Periodically health checks the current connection in the background, reconnecting when it fails.
+    pub fn spawn_health_monitor(&self) -> JoinHandle<()> {
+        let client = self.clone();
+
+        tokio::spawn(async move {
+            loop {
+                sleep(client.inner.policy.health_check_interval).await;
+
+                let generation = client.inner.connection.read().unwrap().generation;
+
+                if let Err(error) = client.health_check().await {
+                    if client
+                        .reconnect_from(generation, error.to_string())
+                        .await
+                        .is_err()
+                    {
+                        return;
+                    }
+                }
+            }
+        })
+    }
+
+    /// Subscribes to blocks, transparently re-subscribing after a reconnect.
+    ///
+    /// In [`BlockStreamKind::Finalized`] mode a consumer sees every height exactly once: blocks at or below the last
+    /// delivered number are skipped after re-subscribing, and blocks finalized while disconnected are fetched before
+    /// the new head. At most the runtime's `BlockHashCount` missed blocks are fetched, the newest ones, and older ones
+    /// are reported as [`ConnectionEvent::Skipped`]. The subscription ends when reconnecting fails.
+    pub fn subscribe_blocks(&self, kind: BlockStreamKind) -> BlockSubscription {
+        let (sender, receiver) = mpsc::channel(16);
+        let client = self.clone();
+
+        tokio::spawn(async move {
+            let mut last_number: Option<BlockNumber> = None;
+            let mut resubscribed = false;
+
+            loop {
+                let (generation, subtensor) = {
+                    let connection = client.inner.connection.read().unwrap();
+
+                    (connection.generation, connection.client.clone())
+                };
+
+                let subscription = match kind {
+                    BlockStreamKind::Best => subtensor.blocks().subscribe_best().await,
+                    BlockStreamKind::Finalized => subtensor.blocks().subscribe_finalized().await,
+                };
+
+                let max_backfill = subtensor
+                    .constants()
+                    .at(&api::constants().system().block_hash_count())
+                    .unwrap_or(DEFAULT_MAX_BACKFILL);
+
+                let reason = match subscription {
+                    Ok(mut blocks) => {
+                        if resubscribed {
+                            let _ = client.inner.events.send(ConnectionEvent::Resubscribed {
+                                endpoint: client.endpoint(),
+                            });
+                        }
+
+                        'blocks: loop {
+                            let block = match blocks.next().await {
+                                Some(Ok(block)) => block,
+                                Some(Err(error)) => break error.to_string(),
+                                None => break "block subscription ended".to_string(),
+                            };
+
+                            let mut pending = vec![block];
+
+                            if let (BlockStreamKind::Finalized, Some(last)) = (kind, last_number) {
+                                let head = pending[0].number();
+
+                                if head <= last {
+                                    continue;
+                                }
+
+                                // The walk is bounded so a long outage is not buffered in memory
+                                let floor = last.max(head.saturating_sub(max_backfill + 1));
+
+                                if floor > last {
+                                    let _ = client.inner.events.send(ConnectionEvent::Skipped {
+                                        first: last + 1,
+                                        last: floor,
+                                    });
+                                }
+
+                                // Blocks finalized while disconnected are walked back to through their parents
+                                while let Some(oldest) =
+                                    pending.last().filter(|block| block.number() > floor + 1)
+                                {
+                                    match subtensor.blocks().at(oldest.header().parent_hash).await {
+                                        Ok(parent) => pending.push(parent),
+                                        Err(error) => break 'blocks error.to_string(),
+                                    }
+                                }
+                            }
+
+                            for block in pending.into_iter().rev() {
+                                last_number = Some(block.number());
+
+                                if sender.send(block).await.is_err() {
+                                    return;
+                                }
+                            }
+                        }
+                    }
+                    Err(error) => error.to_string(),
+                };
+
+                if sender.is_closed() || client.reconnect_from(generation, reason).await.is_err() {
+                    return;
+                }
+
+                resubscribed = true;
+            }
+        });
+
+        BlockSubscription(receiver)
+    }
+}
+
+pub struct BlockSubscription(mpsc::Receiver<Block>);
+
+impl BlockSubscription {
+    pub async fn next(&mut self) -> Option<Block> {
+        self.0.recv().await
+    }
+}
+
+async fn check(client: &Subtensor, limit: Duration) -> Result<(), subxt::Error> {
+    match timeout(limit, client.backend().latest_finalized_block_ref()).await {
+        Ok(result) => result.map(|_| ()),
+        Err(_) => Err(subxt::Error::Other("Health check timed out".to_string())),
+    }
+}
+
+async fn try_endpoint(
+    endpoint: &str,
+    policy: &ReconnectPolicy,
+    options: &ConnectionOptions,
+) -> Result<Subtensor, String> {
+    let connect = subtensor::from_url_with_options(endpoint, options);
+
+    // The options' timeouts only cover the websocket handshake and each request, the policy's bounds the whole attempt,
+    // and so caps them
+    let client = match timeout(policy.connect_timeout, connect).await {
+        Ok(Ok(client)) => client,
+        Ok(Err(error)) => return Err(error.to_string()),
+        Err(_) => return Err("connection timed out".to_string()),
+    };
+
+    check(&client, policy.health_check_timeout)
+        .await
+        .map_err(|error| error.to_string())?;
+
+    Ok(client)
+}
+
+/// Tries every endpoint starting at `start`, backing off between full rounds.
+async fn establish(
+    endpoints: &[String],
+    start: usize,
+    policy: &ReconnectPolicy,
+    options: &ConnectionOptions,
+    events: &broadcast::Sender<ConnectionEvent>,
+) -> Result<(usize, Subtensor), ConnectionError> {
+    let mut delay = policy.initial_delay;
+    let mut attempt = 0;
+
+    loop {
+        for offset in 0..endpoints.len() {
+            let index = (start + offset) % endpoints.len();
+
+            match try_endpoint(&endpoints[index], policy, options).await {
+                Ok(client) => {
+                    let _ = events.send(ConnectionEvent::Connected {
+                        endpoint: endpoints[index].clone(),
+                    });
+
+                    return Ok((index, client));
+                }
+                Err(reason) => {
+                    let _ = events.send(ConnectionEvent::Disconnected {
+                        endpoint: endpoints[index].clone(),
+                        reason,
+                    });
+                }
+            }
+        }
+
+        attempt += 1;
+
+        if policy.max_attempts.is_some_and(|max| attempt >= max) {
+            return Err(ConnectionError::Exhausted(attempt));
+        }
+
+        let _ = events.send(ConnectionEvent::Reconnecting { attempt, delay });
+
+        sleep(delay).await;
+        delay = (delay * 2).min(policy.max_delay);
+    }
+}
@@ -0,0 +1,392 @@
+use sp_core::hashing::{blake2_256, keccak_256, sha2_256};
+use subxt::ext::codec::{Decode, Encode};
+use subxt::tx::Payload;
+use thiserror::Error;
+
+use crate::api;
+use crate::api::runtime_types::bounded_collections::bounded_vec::BoundedVec;
+use crate::subtensor::{trailing_account_id, Subtensor};
+use crate::{AccountId, BlockRef};
+
+pub use crate::api::runtime_types::pallet_commitments::types::{CommitmentInfo, Data};
+
+pub type Registration = api::commitments::storage::types::commitment_of::CommitmentOf;
+pub type RevealedCommitments =
+    api::commitments::storage::types::revealed_commitments::RevealedCommitments;
+
+/// The largest payload representable by the `Raw0..Raw128` variants.
+pub const MAX_RAW_DATA_LENGTH: usize = 128;
+
+/// Bound of the `BigRaw` variant in the pallet.
+pub const MAX_BIG_RAW_DATA_LENGTH: usize = 512;
+
+/// Bound of the encrypted payload in the `TimelockEncrypted` variant.
+pub const MAX_TIMELOCK_DATA_LENGTH: usize = 1024;
+
+#[derive(Debug, Error)]
+#[error("Commitment data is {length} bytes, the maximum for this field is {max}")]
+pub struct DataTooLongError {
+    pub length: usize,
+    pub max: usize,
+}
+
+#[derive(Debug, Error)]
+pub enum CommitmentError {
+    #[error(transparent)]
+    DataTooLong(#[from] DataTooLongError),
+
+    #[error("Commitment has {fields} fields, the maximum is {max}")]
+    TooManyFields { fields: usize, max: u32 },
+
+    #[error("Commitment needs {required} bytes but only {available} remain in the current epoch")]
+    SpaceLimitExceeded { required: u64, available: u64 },
+
+    #[error("Hotkey is not registered on netuid {0}")]
+    NotRegistered(u16),
+
+    #[error(transparent)]
+    Subxt(#[from] subxt::Error),
+}
+
+/// Picks the `RawN` variant matching the length of `bytes`.
+pub fn raw_data(bytes: impl AsRef<[u8]>) -> Result<Data, DataTooLongError> {
+    let bytes = bytes.as_ref();
+
+    if bytes.len() > MAX_RAW_DATA_LENGTH {
+        return Err(DataTooLongError {
+            length: bytes.len(),
+            max: MAX_RAW_DATA_LENGTH,
+        });
+    }
+
+    // `None` is variant 0 and `RawN` is variant N + 1, with the fixed size array following without a length prefix
+    let mut encoded = Vec::with_capacity(bytes.len() + 1);
+    encoded.push(bytes.len() as u8 + 1);
+    encoded.extend_from_slice(bytes);
+
+    Ok(Data::decode(&mut encoded.as_slice()).expect("RawN variants cover every length up to 128"))
+}
+
+pub fn big_raw_data(bytes: impl Into<Vec<u8>>) -> Result<Data, DataTooLongError> {
+    let bytes = bytes.into();
+
+    if bytes.len() > MAX_BIG_RAW_DATA_LENGTH {
+        return Err(DataTooLongError {
+            length: bytes.len(),
+            max: MAX_BIG_RAW_DATA_LENGTH,
+        });
+    }
+
+    Ok(Data::BigRaw(BoundedVec(bytes)))
+}
+
+/// Uses a `RawN` variant when the string fits, otherwise `BigRaw`.
+pub fn text_data(text: impl AsRef<str>) -> Result<Data, DataTooLongError> {
+    let bytes = text.as_ref().as_bytes();
+
+    if bytes.len() <= MAX_RAW_DATA_LENGTH {
+        raw_data(bytes)
+    } else {
+        big_raw_data(bytes)
+    }
+}
+
+pub fn sha256_data(content: impl AsRef<[u8]>) -> Data {
+    Data::Sha256(sha2_256(content.as_ref()))
+}
+
+pub fn keccak256_data(content: impl AsRef<[u8]>) -> Data {
+    Data::Keccak256(keccak_256(content.as_ref()))
+}
+
+pub fn blake2_256_data(content: impl AsRef<[u8]>) -> Data {
+    Data::BlakeTwo256(blake2_256(content.as_ref()))
+}
+
+/// The bytes held by a field, for raw data that's the payload and for hashes that's the digest.
+pub fn data_bytes(data: &Data) -> Option<Vec<u8>> {
+    match data {
+        Data::None | Data::ResetBondsFlag | Data::TimelockEncrypted { .. } => None,
+        Data::BigRaw(bytes) => Some(bytes.0.clone()),
+        Data::BlakeTwo256(hash)
+        | Data::Sha256(hash)
+        | Data::Keccak256(hash)
+        | Data::ShaThree256(hash) => Some(hash.to_vec()),
+        // Every remaining variant is `RawN`, which encodes as its index followed by the bytes
+        raw => Some(raw.encode()[1..].to_vec()),
+    }
+}
+
+pub fn data_text(data: &Data) -> Option<String> {
+    match data {
+        Data::BlakeTwo256(_) | Data::Sha256(_) | Data::Keccak256(_) | Data::ShaThree256(_) => None,
+        data => String::from_utf8(data_bytes(data)?).ok(),
+    }
+}
+
+/// Mirrors `Data::len_for_rate_limit`, the number of bytes a field counts against the per-epoch space limit.
+pub fn data_space(data: &Data) -> u64 {
+    match data {
+        Data::None | Data::ResetBondsFlag => 0,
+        Data::TimelockEncrypted { encrypted, .. } => encrypted.0.len() as u64,
+        data => data_bytes(data).map_or(0, |bytes| bytes.len() as u64),
+    }
+}
+
+pub fn commitment_info(fields: impl IntoIterator<Item = Data>) -> CommitmentInfo {
+    CommitmentInfo {
+        fields: BoundedVec(fields.into_iter().collect()),
+    }
+}
+
+pub fn set_commitment_payload(netuid: u16, info: CommitmentInfo) -> impl Payload {
+    api::tx().commitments().set_commitment(netuid, info)
+}
+
+/// Mirrors `Pallet::get_epoch_index`, used by the pallet to reset the space used by each hotkey.
+fn epoch_index(netuid: u16, tempo: u16, block_number: u64) -> u64 {
+    block_number.saturating_add(netuid as u64 + 1) / (tempo as u64 + 1)
+}
+
+/// Checks that `hotkey` may commit `info` on `netuid` at the given block, so that a rejected extrinsic doesn't
+/// need to be paid for.
+pub async fn check_commitment(
+    client: &Subtensor,
+    at: BlockRef,
+    netuid: u16,
+    hotkey: &AccountId,
+    info: &CommitmentInfo,
+) -> Result<(), CommitmentError> {
+    let max_fields = client
+        .constants()
+        .at(&api::constants().commitments().max_fields())?;
+
+    if info.fields.0.len() > max_fields as usize {
+        return Err(CommitmentError::TooManyFields {
+            fields: info.fields.0.len(),
+            max: max_fields,
+        });
+    }
+
+    for field in &info.fields.0 {
+        if let Data::TimelockEncrypted { encrypted, .. } = field {
+            if encrypted.0.len() > MAX_TIMELOCK_DATA_LENGTH {
+                return Err(DataTooLongError {
+                    length: encrypted.0.len(),
+                    max: MAX_TIMELOCK_DATA_LENGTH,
+                }
+                .into());
+            }
+        }
+    }
+
+    let block = client.blocks().at(at.clone()).await?;
+    let storage = client.storage().at(at);
+
+    let registered = storage
+        .fetch(&api::storage().subtensor_module().uids(netuid, hotkey))
+        .await?;
+
+    if registered.is_none() {
+        return Err(CommitmentError::NotRegistered(netuid));
+    }
+
+    let tempo = storage
+        .fetch_or_default(&api::storage().subtensor_module().tempo(netuid))
+        .await?;
+
+    let max_space = storage
+        .fetch_or_default(&api::storage().commitments().max_space())
+        .await?;
+
+    let usage = storage
+        .fetch(&api::storage().commitments().used_space_of(netuid, hotkey))
+        .await?;
+
+    // The commitment lands in a later block at the earliest, which is the one the pallet computes the epoch from
+    let epoch = epoch_index(netuid, tempo, u64::from(block.number()) + 1);
+
+    let used = usage
+        .filter(|usage| usage.last_epoch == epoch)
+        .map_or(0, |usage| usage.used_space);
+
+    let required = info.fields.0.iter().map(data_space).sum();
+    let available = (max_space as u64).saturating_sub(used);
+
+    if required > available {
+        return Err(CommitmentError::SpaceLimitExceeded {
+            required,
+            available,
+        });
+    }
+
+    Ok(())
+}
+
+pub async fn commitment_of(
+    client: &Subtensor,
+    at: BlockRef,
+    netuid: u16,
+    hotkey: &AccountId,
+) -> Result<Option<Registration>, subxt::Error> {
+    client
+        .storage()
+        .at(at)
+        .fetch(&api::storage().commitments().commitment_of(netuid, hotkey))
+        .await
+}
+
+pub async fn commitments(
+    client: &Subtensor,
+    at: BlockRef,
+    netuid: u16,
+) -> Result<Vec<(AccountId, Registration)>, subxt::Error> {
+    let mut entries = client
+        .storage()
+        .at(at)
+        .iter(api::storage().commitments().commitment_of_iter1(netuid))
+        .await?;
+
+    let mut commitments = Vec::new();
+
+    while let Some(entry) = entries.next().await {
+        let entry = entry?;
+
+        if let Some(account_id) = trailing_account_id(&entry.key_bytes) {
+            commitments.push((account_id, entry.value));
+        }
+    }
+
+    Ok(commitments)
+}
+
+/// Timelocked commitments the pallet has already decrypted, each as the plaintext and the block it was revealed at.
+pub async fn revealed_commitment_of(
+    client: &Subtensor,
+    at: BlockRef,
+    netuid: u16,
+    hotkey: &AccountId,
+) -> Result<Option<RevealedCommitments>, subxt::Error> {
+    client
+        .storage()
+        .at(at)
+        .fetch(
+            &api::storage()
+                .commitments()
+                .revealed_commitments(netuid, hotkey),
+        )
+        .await
+}
+
+pub async fn revealed_commitments(
+    client: &Subtensor,
+    at: BlockRef,
+    netuid: u16,
+) -> Result<Vec<(AccountId, RevealedCommitments)>, subxt::Error> {
+    let mut entries = client
+        .storage()
+        .at(at)
+        .iter(
+            api::storage()
+                .commitments()
+                .revealed_commitments_iter1(netuid),
+        )
+        .await?;
+
+    let mut commitments = Vec::new();
+
+    while let Some(entry) = entries.next().await {
+        let entry = entry?;
+
+        if let Some(account_id) = trailing_account_id(&entry.key_bytes) {
+            commitments.push((account_id, entry.value));
+        }
+    }
+
+    Ok(commitments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_data_round_trips_every_length_boundary() {
+        for length in [0, 1, 128] {
+            let bytes: Vec<u8> = (0..length).map(|byte| byte as u8).collect();
+            let data = raw_data(&bytes).unwrap();
+
+            // The variant index is the length plus one, followed by the bytes without a length prefix
+            let mut encoded = vec![length as u8 + 1];
+            encoded.extend(&bytes);
+
+            assert_eq!(data.encode(), encoded, "length {length}");
+            assert_eq!(data_bytes(&data), Some(bytes), "length {length}");
+            assert_eq!(data_space(&data), length as u64, "length {length}");
+        }
+
+        assert!(matches!(
+            raw_data([0; 129]),
+            Err(DataTooLongError {
+                length: 129,
+                max: 128,
+            })
+        ));
+    }
+
+    #[test]
+    fn text_data_falls_back_to_big_raw() {
+        assert!(matches!(
+            text_data("a".repeat(128)).unwrap(),
+            Data::Raw128(_)
+        ));
+        assert!(matches!(
+            text_data("a".repeat(129)).unwrap(),
+            Data::BigRaw(_)
+        ));
+        assert!(text_data("a".repeat(513)).is_err());
+
+        assert_eq!(
+            data_text(&text_data("a".repeat(129)).unwrap()),
+            Some("a".repeat(129))
+        );
+        assert_eq!(data_text(&sha256_data("a")), None);
+    }
+
+    #[test]
+    fn counts_space_like_the_pallet() {
+        let cases = [
+            (Data::None, 0),
+            (Data::ResetBondsFlag, 0),
+            (raw_data([1; 10]).unwrap(), 10),
+            (big_raw_data(vec![1; 300]).unwrap(), 300),
+            (sha256_data("a"), 32),
+            (keccak256_data("a"), 32),
+            (blake2_256_data("a"), 32),
+            (Data::ShaThree256([0; 32]), 32),
+            (
+                Data::TimelockEncrypted {
+                    encrypted: BoundedVec(vec![1; 700]),
+                    reveal_round: 5,
+                },
+                700,
+            ),
+        ];
+
+        for (data, space) in cases {
+            assert_eq!(data_space(&data), space, "{data:?}");
+        }
+    }
+
+    #[test]
+    fn epoch_index_matches_the_pallet() {
+        // (block_number + netuid + 1) / (tempo + 1), as in `Pallet::get_epoch_index`
+        assert_eq!(epoch_index(1, 360, 0), 0);
+        assert_eq!(epoch_index(1, 360, 358), 0);
+        assert_eq!(epoch_index(1, 360, 359), 1);
+        assert_eq!(epoch_index(0, 99, 99), 1);
+        assert_eq!(epoch_index(5, 0, 10), 16);
+        assert_eq!(epoch_index(u16::MAX, u16::MAX, 0), 1);
+        assert_eq!(epoch_index(1, 360, u64::MAX), u64::MAX / 361);
+    }
+}
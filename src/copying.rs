@@ -0,0 +1,479 @@
+//! Detection of validators copying the weights of other validators or the published consensus.
+//!
+//! The analysis works on [`WeightSnapshot`]s taken once per epoch, right after it ran, so that the consensus in each
+//! snapshot is computed from the weights in that same snapshot.
+
+use std::collections::BTreeMap;
+
+use subxt::ext::futures::try_join;
+
+use crate::matrix::{self, SparseMatrix};
+use crate::subtensor::Subtensor;
+use crate::{dynamic, BlockRef};
+
+#[derive(Clone, Debug)]
+pub struct WeightSnapshot {
+    pub block: u64,
+    pub weights: SparseMatrix,
+    pub last_update: Vec<u64>,
+    pub consensus: Vec<u16>,
+    pub validator_permit: Vec<bool>,
+}
+
+/// Reads the weights, consensus and last updates of `netuid` at `at`.
+pub async fn snapshot(
+    client: &Subtensor,
+    at: BlockRef,
+    netuid: u16,
+) -> Result<Option<WeightSnapshot>, subxt::Error> {
+    let (metagraph, weights) = try_join!(
+        dynamic::metagraph(client, at.clone(), netuid),
+        matrix::weights(client, at, netuid),
+    )?;
+
+    Ok(metagraph.map(|metagraph| WeightSnapshot {
+        block: metagraph.block,
+        weights,
+        last_update: metagraph.last_update.iter().map(|block| block.0).collect(),
+        consensus: metagraph.consensus.iter().map(|value| value.0).collect(),
+        validator_permit: metagraph.validator_permit,
+    }))
+}
+
+#[derive(Clone, Debug)]
+pub struct DetectionOptions {
+    /// Cosine similarity at or above which two weight vectors count as copies.
+    pub similarity_threshold: f64,
+    /// How many epochs back to compare weights against the consensus.
+    pub max_lag: usize,
+    /// How much closer to a past consensus than to the current one weights need to be to count as a lagged copy.
+    pub lag_margin: f64,
+    /// Fraction of a validator's weight updates that must be copies to flag it.
+    pub min_copy_ratio: f64,
+}
+
+impl Default for DetectionOptions {
+    fn default() -> Self {
+        Self {
+            similarity_threshold: 0.99,
+            max_lag: 3,
+            lag_margin: 0.01,
+            min_copy_ratio: 0.5,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PairSimilarity {
+    pub a: u16,
+    pub b: u16,
+    pub similarity: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Evidence {
+    /// The weights matched those `leader` set earlier.
+    Duplicate {
+        block: u64,
+        leader: u16,
+        similarity: f64,
+    },
+    /// The weights matched the consensus from `lag` epochs earlier better than the current one.
+    LaggedConsensus {
+        block: u64,
+        lag: usize,
+        similarity: f64,
+        current_similarity: f64,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct Suspect {
+    pub uid: u16,
+    /// How many snapshots the validator set new weights in.
+    pub updates: usize,
+    /// How many of those updates were copies.
+    pub copies: usize,
+    /// The highest similarity among the copies.
+    pub max_similarity: f64,
+    pub evidence: Vec<Evidence>,
+}
+
+impl Suspect {
+    pub fn copy_ratio(&self) -> f64 {
+        if self.updates == 0 {
+            0.0
+        } else {
+            self.copies as f64 / self.updates as f64
+        }
+    }
+}
+
+/// Cosine similarity between two sparse vectors, 0 if either is empty.
+pub fn cosine_similarity<A: Copy + Into<f64>, B: Copy + Into<f64>>(
+    a: &[(u16, A)],
+    b: &[(u16, B)],
+) -> f64 {
+    let b_values: BTreeMap<u16, f64> = b.iter().map(|&(uid, value)| (uid, value.into())).collect();
+
+    let dot: f64 = a
+        .iter()
+        .filter_map(|&(uid, value)| Some(value.into() * b_values.get(&uid)?))
+        .sum();
+
+    let a_norm = a
+        .iter()
+        .map(|&(_, value)| value.into().powi(2))
+        .sum::<f64>()
+        .sqrt();
+    let b_norm = b_values
+        .values()
+        .map(|value| value.powi(2))
+        .sum::<f64>()
+        .sqrt();
+
+    if a_norm == 0.0 || b_norm == 0.0 {
+        0.0
+    } else {
+        dot / (a_norm * b_norm)
+    }
+}
+
+fn dense_to_sparse(values: &[u16]) -> Vec<(u16, u16)> {
+    values
+        .iter()
+        .enumerate()
+        .filter(|&(_, &value)| value != 0)
+        .map(|(uid, &value)| (uid as u16, value))
+        .collect()
+}
+
+/// Uids with a validator permit that set weights.
+fn validators(snapshot: &WeightSnapshot) -> Vec<u16> {
+    (0..snapshot.weights.len() as u16)
+        .filter(|&uid| {
+            snapshot
+                .validator_permit
+                .get(uid as usize)
+                .copied()
+                .unwrap_or(false)
+                && !snapshot.weights.row(uid).is_empty()
+        })
+        .collect()
+}
+
+/// The block `uid` last set weights in, `None` if the snapshot has no last update for it.
+fn last_update(snapshot: &WeightSnapshot, uid: u16) -> Option<u64> {
+    snapshot.last_update.get(uid as usize).copied()
+}
+
+/// Whether `uid` set weights between `previous` and `snapshot`, always true without a previous snapshot.
+fn updated(snapshot: &WeightSnapshot, previous: Option<&WeightSnapshot>, uid: u16) -> bool {
+    previous.is_none_or(|previous| {
+        last_update(snapshot, uid).is_some_and(|block| block > previous.block)
+    })
+}
+
+/// Similarity between the weights of every pair of validators in `snapshot`.
+pub fn pairwise_similarity(snapshot: &WeightSnapshot) -> Vec<PairSimilarity> {
+    let validators = validators(snapshot);
+    let mut pairs = Vec::new();
+
+    for (index, &a) in validators.iter().enumerate() {
+        for &b in &validators[index + 1..] {
+            pairs.push(PairSimilarity {
+                a,
+                b,
+                similarity: cosine_similarity(snapshot.weights.row(a), snapshot.weights.row(b)),
+            });
+        }
+    }
+
+    pairs
+}
+
+/// The mean similarity of each pair of validators over the snapshots in which both set weights.
+pub fn mean_pairwise_similarity(snapshots: &[WeightSnapshot]) -> Vec<PairSimilarity> {
+    let mut sums: BTreeMap<(u16, u16), (f64, usize)> = BTreeMap::new();
+
+    for snapshot in snapshots {
+        for pair in pairwise_similarity(snapshot) {
+            let (sum, count) = sums.entry((pair.a, pair.b)).or_default();
+
+            *sum += pair.similarity;
+            *count += 1;
+        }
+    }
+
+    sums.into_iter()
+        .map(|((a, b), (sum, count))| PairSimilarity {
+            a,
+            b,
+            similarity: sum / count as f64,
+        })
+        .collect()
+}
+
+/// Flags validators whose weight updates mostly copy another validator's earlier weights or a past consensus.
+///
+/// `snapshots` must be ordered by block. Only weights set since the previous snapshot are considered, as unchanged
+/// weights naturally match the consensus they contributed to.
+pub fn detect(snapshots: &[WeightSnapshot], options: &DetectionOptions) -> Vec<Suspect> {
+    let mut suspects: BTreeMap<u16, Suspect> = BTreeMap::new();
+
+    for (index, snapshot) in snapshots.iter().enumerate() {
+        let previous = index.checked_sub(1).map(|index| &snapshots[index]);
+        let validators = validators(snapshot);
+        let current_consensus = dense_to_sparse(&snapshot.consensus);
+
+        for &uid in &validators {
+            if !updated(snapshot, previous, uid) {
+                continue;
+            }
+
+            let weights = snapshot.weights.row(uid);
+            let updated_at = last_update(snapshot, uid);
+
+            let suspect = suspects.entry(uid).or_insert_with(|| Suspect {
+                uid,
+                updates: 0,
+                copies: 0,
+                max_similarity: 0.0,
+                evidence: Vec::new(),
+            });
+
+            suspect.updates += 1;
+
+            let mut evidence = Vec::new();
+
+            // Validators that set the same weights earlier, which is unknown for those without a last update
+            let leader = validators
+                .iter()
+                .filter(|&&other| {
+                    other != uid
+                        && updated_at.zip(last_update(snapshot, other)).is_some_and(
+                            |(updated_at, other_updated_at)| other_updated_at < updated_at,
+                        )
+                })
+                .map(|&other| {
+                    (
+                        other,
+                        cosine_similarity(weights, snapshot.weights.row(other)),
+                    )
+                })
+                .filter(|&(_, similarity)| similarity >= options.similarity_threshold)
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+
+            if let Some((leader, similarity)) = leader {
+                evidence.push(Evidence::Duplicate {
+                    block: snapshot.block,
+                    leader,
+                    similarity,
+                });
+            }
+
+            let current_similarity = cosine_similarity(weights, &current_consensus);
+
+            let lagged = (1..=options.max_lag.min(index))
+                .map(|lag| {
+                    let consensus = dense_to_sparse(&snapshots[index - lag].consensus);
+
+                    (lag, cosine_similarity(weights, &consensus))
+                })
+                .filter(|&(_, similarity)| {
+                    similarity >= options.similarity_threshold
+                        && similarity > current_similarity + options.lag_margin
+                })
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+
+            if let Some((lag, similarity)) = lagged {
+                evidence.push(Evidence::LaggedConsensus {
+                    block: snapshot.block,
+                    lag,
+                    similarity,
+                    current_similarity,
+                });
+            }
+
+            if !evidence.is_empty() {
+                suspect.copies += 1;
+            }
+
+            for evidence in &evidence {
+                let similarity = match evidence {
+                    Evidence::Duplicate { similarity, .. }
+                    | Evidence::LaggedConsensus { similarity, .. } => *similarity,
+                };
+
+                suspect.max_similarity = suspect.max_similarity.max(similarity);
+            }
+
+            suspect.evidence.extend(evidence);
+        }
+    }
+
+    suspects
+        .into_values()
+        .filter(|suspect| suspect.copies > 0 && suspect.copy_ratio() >= options.min_copy_ratio)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UIDS: usize = 9;
+
+    /// The consensus of `epoch`, all on one of miners 3 to 7 so that consensuses less than 5 epochs apart differ.
+    fn consensus(epoch: usize) -> Vec<u16> {
+        let mut consensus = vec![0; UIDS];
+        consensus[3 + epoch % 5] = 60_000;
+        consensus
+    }
+
+    /// Snapshots in which uid 0 tracks the current consensus, with some weight on miner 8 so that it is not a copy
+    /// of it, while uid 1 sets the weights `copier` returns for each epoch and block, with the block it set them in.
+    fn epochs(
+        epochs: usize,
+        copier: impl Fn(usize, u64) -> (Vec<(u16, u16)>, u64),
+    ) -> Vec<WeightSnapshot> {
+        (0..epochs)
+            .map(|epoch| {
+                let block = 100 * (epoch as u64 + 1);
+                let mut tracking = dense_to_sparse(&consensus(epoch));
+                tracking.push((8, 10_000));
+                let (copied, copied_at) = copier(epoch, block);
+
+                let mut rows = vec![Vec::new(); UIDS];
+                rows[0] = tracking;
+                rows[1] = copied;
+
+                WeightSnapshot {
+                    block,
+                    weights: SparseMatrix::new(rows),
+                    last_update: vec![block - 10, copied_at],
+                    consensus: consensus(epoch),
+                    validator_permit: vec![true, true],
+                }
+            })
+            .collect()
+    }
+
+    /// Sets the consensus from `lag` epochs earlier once there is one, and the current one before that.
+    fn lagging(lag: usize) -> impl Fn(usize, u64) -> (Vec<(u16, u16)>, u64) {
+        move |epoch, block| {
+            let source = if epoch >= lag { epoch - lag } else { epoch };
+
+            (dense_to_sparse(&consensus(source)), block - 5)
+        }
+    }
+
+    #[test]
+    fn flags_copies_of_a_past_consensus_up_to_max_lag() {
+        let options = DetectionOptions::default();
+
+        for lag in 1..=options.max_lag {
+            let suspects = detect(&epochs(lag + 3, lagging(lag)), &options);
+
+            // Only the copier is flagged, and only for the epochs with a consensus `lag` epochs earlier
+            assert_eq!(suspects.len(), 1, "lag {lag}");
+            assert_eq!(suspects[0].uid, 1);
+            assert_eq!(suspects[0].updates, lag + 3);
+            assert_eq!(suspects[0].copies, 3);
+            assert_eq!(suspects[0].max_similarity, 1.0);
+            assert!(suspects[0].evidence.iter().all(|evidence| matches!(
+                evidence,
+                Evidence::LaggedConsensus { lag: found, current_similarity, .. }
+                    if *found == lag && *current_similarity == 0.0
+            )));
+        }
+    }
+
+    #[test]
+    fn ignores_validators_tracking_the_current_consensus() {
+        let options = DetectionOptions::default();
+
+        assert!(detect(&epochs(6, lagging(0)), &options).is_empty());
+
+        // A consensus further back than `max_lag` is not compared against
+        assert!(detect(&epochs(8, lagging(options.max_lag + 1)), &options).is_empty());
+    }
+
+    #[test]
+    fn flags_only_validators_copying_often_enough() {
+        let copier = |epoch: usize, block: u64| {
+            let source = if epoch == 3 { 2 } else { epoch };
+
+            (dense_to_sparse(&consensus(source)), block - 5)
+        };
+        let snapshots = epochs(4, copier);
+
+        assert!(detect(&snapshots, &DetectionOptions::default()).is_empty());
+
+        let suspects = detect(
+            &snapshots,
+            &DetectionOptions {
+                min_copy_ratio: 0.25,
+                ..DetectionOptions::default()
+            },
+        );
+
+        assert_eq!(suspects.len(), 1);
+        assert_eq!((suspects[0].updates, suspects[0].copies), (4, 1));
+        assert_eq!(suspects[0].copy_ratio(), 0.25);
+    }
+
+    #[test]
+    fn counts_only_weights_set_since_the_previous_snapshot() {
+        // The copier sets the first consensus in the first two epochs and then leaves its weights as they are
+        let copier = |epoch: usize, block: u64| {
+            let set_at = if epoch == 0 { block - 5 } else { 195 };
+
+            (dense_to_sparse(&consensus(0)), set_at)
+        };
+
+        let suspects = detect(&epochs(4, copier), &DetectionOptions::default());
+
+        // Unchanged weights matching the consensus 2 and 3 epochs back are not counted again
+        assert_eq!(suspects.len(), 1);
+        assert_eq!((suspects[0].updates, suspects[0].copies), (2, 1));
+        assert!(matches!(
+            suspects[0].evidence[..],
+            [Evidence::LaggedConsensus {
+                block: 200,
+                lag: 1,
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn excludes_validators_without_last_updates_from_leaders() {
+        let mut snapshot = WeightSnapshot {
+            block: 100,
+            weights: SparseMatrix::new(vec![
+                vec![(2, 100), (3, 200)],
+                vec![(2, 100), (3, 200)],
+                Vec::new(),
+                Vec::new(),
+            ]),
+            // uid 1 has no entry, so whether it set its weights before uid 0 is unknown
+            last_update: vec![90],
+            consensus: vec![0; 4],
+            validator_permit: vec![true, true, false, false],
+        };
+
+        assert!(detect(&[snapshot.clone()], &DetectionOptions::default()).is_empty());
+
+        snapshot.last_update.push(80);
+
+        let suspects = detect(&[snapshot], &DetectionOptions::default());
+
+        assert_eq!(suspects.len(), 1);
+        assert_eq!(suspects[0].uid, 0);
+        assert!(matches!(
+            suspects[0].evidence[..],
+            [Evidence::Duplicate { leader: 1, .. }]
+        ));
+    }
+}
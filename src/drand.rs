@@ -0,0 +1,188 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "drand")]
+use ark_serialize::CanonicalDeserialize;
+#[cfg(feature = "drand")]
+use sp_core::hashing::sha2_256;
+#[cfg(feature = "drand")]
+use thiserror::Error;
+#[cfg(feature = "drand")]
+use timelock::curves::drand::TinyBLS381;
+#[cfg(feature = "drand")]
+use timelock::tlock::EngineBLS;
+
+use crate::api;
+use crate::subtensor::Subtensor;
+use crate::BlockRef;
+
+pub use crate::api::runtime_types::pallet_drand::types::{BeaconConfiguration, Pulse};
+
+/// The drand quicknet beacon public key, a compressed G2 point, which the `drand` pallet stores pulses from.
+pub const QUICKNET_PUBLIC_KEY: &str = "83cf0f2896adee7eb8b5f01fcad3912212c437e0073e911fb90022d3e760183c8c4b450b6a0a6c3ac6a5776a2d1064510d1fec758c921cc22b0e17e63aaf4bcb5ed66304de9cf809bd274ca73bab4af5a6e9c76a4bc09e76eae8991ef5ece45a";
+
+/// Unix time of quicknet round 1.
+pub const QUICKNET_GENESIS_TIME: u64 = 1692803367;
+
+/// Seconds between quicknet rounds.
+pub const QUICKNET_PERIOD: u64 = 3;
+
+#[cfg(feature = "drand")]
+#[derive(Debug, Error)]
+pub enum PulseVerificationError {
+    #[error("Invalid public key or signature encoding")]
+    InvalidPoint,
+
+    #[error("Pulse signature does not verify against the beacon public key")]
+    InvalidSignature,
+
+    #[error("Pulse randomness is not the hash of its signature")]
+    RandomnessMismatch,
+}
+
+/// The latest quicknet round published at `time`, or 0 if `time` is before genesis.
+pub fn round_at(time: SystemTime) -> u64 {
+    let Ok(since_epoch) = time.duration_since(UNIX_EPOCH) else {
+        return 0;
+    };
+
+    match since_epoch.as_secs().checked_sub(QUICKNET_GENESIS_TIME) {
+        Some(since_genesis) => since_genesis / QUICKNET_PERIOD + 1,
+        None => 0,
+    }
+}
+
+/// The time at which quicknet publishes `round`.
+pub fn round_time(round: u64) -> SystemTime {
+    let since_genesis = round.saturating_sub(1) * QUICKNET_PERIOD;
+
+    UNIX_EPOCH + Duration::from_secs(QUICKNET_GENESIS_TIME + since_genesis)
+}
+
+pub async fn pulse(
+    client: &Subtensor,
+    at: BlockRef,
+    round: u64,
+) -> Result<Option<Pulse>, subxt::Error> {
+    client
+        .storage()
+        .at(at)
+        .fetch(&api::storage().drand().pulses(round))
+        .await
+}
+
+pub async fn last_stored_round(client: &Subtensor, at: BlockRef) -> Result<u64, subxt::Error> {
+    client
+        .storage()
+        .at(at)
+        .fetch_or_default(&api::storage().drand().last_stored_round())
+        .await
+}
+
+pub async fn latest_pulse(client: &Subtensor, at: BlockRef) -> Result<Option<Pulse>, subxt::Error> {
+    let round = last_stored_round(client, at.clone()).await?;
+
+    pulse(client, at, round).await
+}
+
+pub async fn beacon_config(
+    client: &Subtensor,
+    at: BlockRef,
+) -> Result<BeaconConfiguration, subxt::Error> {
+    client
+        .storage()
+        .at(at)
+        .fetch_or_default(&api::storage().drand().beacon_config())
+        .await
+}
+
+/// Verifies a pulse against the quicknet public key, without trusting the chain that stored it.
+#[cfg(feature = "drand")]
+pub fn verify_pulse(pulse: &Pulse) -> Result<(), PulseVerificationError> {
+    let public_key = hex::decode(QUICKNET_PUBLIC_KEY).expect("Quicknet public key is valid hex");
+
+    verify_pulse_with_key(pulse, &public_key)
+}
+
+/// Verifies that a pulse is the unchained BLS signature of its round under `public_key`, a compressed G2 point,
+/// and that its randomness is derived from that signature.
+#[cfg(feature = "drand")]
+pub fn verify_pulse_with_key(
+    pulse: &Pulse,
+    public_key: &[u8],
+) -> Result<(), PulseVerificationError> {
+    if pulse.randomness.0 != sha2_256(&pulse.signature.0) {
+        return Err(PulseVerificationError::RandomnessMismatch);
+    }
+
+    let public_key = <TinyBLS381 as EngineBLS>::PublicKeyGroup::deserialize_compressed(public_key)
+        .map_err(|_| PulseVerificationError::InvalidPoint)?;
+
+    let signature =
+        <TinyBLS381 as EngineBLS>::SignatureGroup::deserialize_compressed(&*pulse.signature.0)
+            .map_err(|_| PulseVerificationError::InvalidPoint)?;
+
+    let message = TinyBLS381::hash_to_signature_curve(&sha2_256(&pulse.round.to_be_bytes())[..]);
+
+    let verified = TinyBLS381::verify_prepared(
+        TinyBLS381::prepare_signature(signature),
+        &[(
+            TinyBLS381::prepare_public_key(public_key),
+            TinyBLS381::prepare_signature(message),
+        )],
+    );
+
+    if verified {
+        Ok(())
+    } else {
+        Err(PulseVerificationError::InvalidSignature)
+    }
+}
+
+#[cfg(all(test, feature = "drand"))]
+mod tests {
+    use super::*;
+    use crate::api::runtime_types::bounded_collections::bounded_vec::BoundedVec;
+
+    /// Quicknet round 1000, from https://api.drand.sh/52db9ba70e0cc0f6eaf7803dd07447a1f5477735fd3f661792ba94600c84e971/public/1000
+    const ROUND: u64 = 1000;
+    const SIGNATURE: &str =
+        "b44679b9a59af2ec876b1a6b1ad52ea9b1615fc3982b19576350f93447cb1125e342b73a8dd2bacbe47e4b6b63ed5e39";
+
+    fn pulse(round: u64, signature: Vec<u8>) -> Pulse {
+        Pulse {
+            round,
+            randomness: BoundedVec(sha2_256(&signature).to_vec()),
+            signature: BoundedVec(signature),
+        }
+    }
+
+    #[test]
+    fn verifies_a_quicknet_pulse() {
+        let signature = hex::decode(SIGNATURE).unwrap();
+
+        assert!(verify_pulse(&pulse(ROUND, signature)).is_ok());
+    }
+
+    #[test]
+    fn rejects_tampered_pulses() {
+        let signature = hex::decode(SIGNATURE).unwrap();
+
+        assert!(matches!(
+            verify_pulse(&pulse(ROUND + 1, signature.clone())),
+            Err(PulseVerificationError::InvalidSignature)
+        ));
+
+        let mut tampered = signature.clone();
+        tampered[20] ^= 1;
+
+        assert!(verify_pulse(&pulse(ROUND, tampered)).is_err());
+
+        let mut wrong_randomness = pulse(ROUND, signature);
+        wrong_randomness.randomness.0[0] ^= 1;
+
+        assert!(matches!(
+            verify_pulse(&wrong_randomness),
+            Err(PulseVerificationError::RandomnessMismatch)
+        ));
+    }
+}
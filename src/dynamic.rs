@@ -0,0 +1,136 @@
+use subxt::dynamic::DecodedValueThunk;
+use subxt::ext::scale_encode::EncodeAsFields;
+use subxt::ext::scale_value::{ValueDef, Variant};
+use subxt::ext::subxt_core;
+use subxt::metadata::DecodeWithMetadata;
+use subxt::runtime_api::StaticPayload;
+
+use crate::api::runtime_types::pallet_subtensor::rpc_info::metagraph::Metagraph;
+use crate::api::runtime_types::pallet_subtensor::rpc_info::stake_info::StakeInfo;
+use crate::api::runtime_types::pallet_subtensor::rpc_info::subnet_info::SubnetHyperparamsV2;
+use crate::subtensor::Subtensor;
+use crate::{api, AccountId, BlockRef};
+
+pub use subxt::dynamic::{At, Value};
+
+/// Calls a generated runtime API through its static types, which are validated against the node's metadata when it
+/// matches the compiled-in metadata, and otherwise decoded by field name against the node's metadata.
+///
+/// Fields a runtime upgrade adds are ignored by the fallback, while fields it removes still fail to decode. Use
+/// [`call_value`] to read those.
+pub async fn call<ArgsData, ReturnTy>(
+    client: &Subtensor,
+    at: BlockRef,
+    payload: StaticPayload<ArgsData, ReturnTy>,
+) -> Result<ReturnTy, subxt::Error>
+where
+    ArgsData: EncodeAsFields,
+    ReturnTy: DecodeWithMetadata,
+{
+    let payload = match subxt_core::runtime_api::validate(&payload, &client.metadata()) {
+        Ok(()) => payload,
+        Err(_) => payload.unvalidated(),
+    };
+
+    client.runtime_api().at(at).call(payload).await
+}
+
+/// Calls a generated runtime API, decoding its result into a [`Value`] against the node's metadata rather than into
+/// the generated type, so that a missing field reads as `None` through [`At`].
+pub async fn call_value<ArgsData, ReturnTy>(
+    client: &Subtensor,
+    at: BlockRef,
+    payload: StaticPayload<ArgsData, ReturnTy>,
+) -> Result<Value, subxt::Error>
+where
+    ArgsData: EncodeAsFields + Clone,
+{
+    let payload = StaticPayload::<ArgsData, DecodedValueThunk>::new(
+        payload.trait_name(),
+        payload.method_name(),
+        payload.args_data().clone(),
+    );
+
+    let value = client.runtime_api().at(at).call(payload).await?;
+
+    Ok(value.to_value()?.remove_context())
+}
+
+/// The value inside a decoded `Option`, or `None` if it is `None` or not an `Option`.
+pub fn option_value(value: Value) -> Option<Value> {
+    match value.value {
+        ValueDef::Variant(Variant { name, values }) if name == "Some" => {
+            values.into_values().next()
+        }
+        _ => None,
+    }
+}
+
+pub async fn metagraph(
+    client: &Subtensor,
+    at: BlockRef,
+    netuid: u16,
+) -> Result<Option<Metagraph<AccountId>>, subxt::Error> {
+    let payload = api::apis().subnet_info_runtime_api().get_metagraph(netuid);
+
+    call(client, at, payload).await
+}
+
+pub async fn metagraph_value(
+    client: &Subtensor,
+    at: BlockRef,
+    netuid: u16,
+) -> Result<Option<Value>, subxt::Error> {
+    let payload = api::apis().subnet_info_runtime_api().get_metagraph(netuid);
+
+    Ok(option_value(call_value(client, at, payload).await?))
+}
+
+pub async fn hyperparameters(
+    client: &Subtensor,
+    at: BlockRef,
+    netuid: u16,
+) -> Result<Option<SubnetHyperparamsV2>, subxt::Error> {
+    let payload = api::apis()
+        .subnet_info_runtime_api()
+        .get_subnet_hyperparams_v2(netuid);
+
+    call(client, at, payload).await
+}
+
+pub async fn hyperparameters_value(
+    client: &Subtensor,
+    at: BlockRef,
+    netuid: u16,
+) -> Result<Option<Value>, subxt::Error> {
+    let payload = api::apis()
+        .subnet_info_runtime_api()
+        .get_subnet_hyperparams_v2(netuid);
+
+    Ok(option_value(call_value(client, at, payload).await?))
+}
+
+pub async fn stake_info(
+    client: &Subtensor,
+    at: BlockRef,
+    coldkey: AccountId,
+) -> Result<Vec<StakeInfo<AccountId>>, subxt::Error> {
+    let payload = api::apis()
+        .stake_info_runtime_api()
+        .get_stake_info_for_coldkey(coldkey);
+
+    call(client, at, payload).await
+}
+
+/// The stake info of `coldkey` as a sequence of values, one per hotkey and subnet.
+pub async fn stake_info_value(
+    client: &Subtensor,
+    at: BlockRef,
+    coldkey: AccountId,
+) -> Result<Value, subxt::Error> {
+    let payload = api::apis()
+        .stake_info_runtime_api()
+        .get_stake_info_for_coldkey(coldkey);
+
+    call_value(client, at, payload).await
+}
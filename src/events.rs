@@ -0,0 +1,823 @@
+use std::collections::BTreeMap;
+
+use subxt::events::Phase;
+use tokio::sync::mpsc;
+
+use crate::api::runtime_types::node_subtensor_runtime::RuntimeEvent;
+use crate::client::BlockStreamKind;
+use crate::subtensor::Subtensor;
+use crate::{AccountId, Block, BlockNumber, Hash};
+
+pub use crate::api::runtime_types::pallet_subtensor::pallet::Event as PalletEvent;
+
+/// How many delivered best blocks are remembered to find where a reorg forked off.
+const REORG_WINDOW: usize = 256;
+
+/// `SubtensorModule` events, with named fields for the common ones.
+#[derive(Clone, Debug)]
+pub enum SubtensorEvent {
+    NetworkAdded {
+        netuid: u16,
+        modality: u16,
+    },
+    NetworkRemoved {
+        netuid: u16,
+    },
+    NeuronRegistered {
+        netuid: u16,
+        uid: u16,
+        hotkey: AccountId,
+    },
+    WeightsSet {
+        netuid: u16,
+        uid: u16,
+    },
+    WeightsCommitted {
+        hotkey: AccountId,
+        netuid: u16,
+        commit_hash: Hash,
+    },
+    WeightsRevealed {
+        hotkey: AccountId,
+        netuid: u16,
+        commit_hash: Hash,
+    },
+    AxonServed {
+        netuid: u16,
+        hotkey: AccountId,
+    },
+    PrometheusServed {
+        netuid: u16,
+        hotkey: AccountId,
+    },
+    StakeAdded {
+        coldkey: AccountId,
+        hotkey: AccountId,
+        tao: u64,
+        alpha: u64,
+        netuid: u16,
+        fee: u64,
+    },
+    StakeRemoved {
+        coldkey: AccountId,
+        hotkey: AccountId,
+        tao: u64,
+        alpha: u64,
+        netuid: u16,
+        fee: u64,
+    },
+    StakeMoved {
+        coldkey: AccountId,
+        origin_hotkey: AccountId,
+        origin_netuid: u16,
+        destination_hotkey: AccountId,
+        destination_netuid: u16,
+        amount: u64,
+    },
+    StakeTransferred {
+        origin_coldkey: AccountId,
+        destination_coldkey: AccountId,
+        hotkey: AccountId,
+        origin_netuid: u16,
+        destination_netuid: u16,
+        amount: u64,
+    },
+    StakeSwapped {
+        coldkey: AccountId,
+        hotkey: AccountId,
+        origin_netuid: u16,
+        destination_netuid: u16,
+        amount: u64,
+    },
+    HotkeySwapped {
+        coldkey: AccountId,
+        old_hotkey: AccountId,
+        new_hotkey: AccountId,
+        /// Set when the hotkey was only swapped on one subnet.
+        netuid: Option<u16>,
+    },
+    ColdkeySwapped {
+        old_coldkey: AccountId,
+        new_coldkey: AccountId,
+        swap_cost: u64,
+    },
+    Other(PalletEvent),
+}
+
+impl From<PalletEvent> for SubtensorEvent {
+    fn from(event: PalletEvent) -> Self {
+        match event {
+            PalletEvent::NetworkAdded(netuid, modality) => Self::NetworkAdded { netuid, modality },
+            PalletEvent::NetworkRemoved(netuid) => Self::NetworkRemoved { netuid },
+            PalletEvent::NeuronRegistered(netuid, uid, hotkey) => Self::NeuronRegistered {
+                netuid,
+                uid,
+                hotkey,
+            },
+            PalletEvent::WeightsSet(netuid, uid) => Self::WeightsSet { netuid, uid },
+            PalletEvent::WeightsCommitted(hotkey, netuid, commit_hash)
+            | PalletEvent::CRV3WeightsCommitted(hotkey, netuid, commit_hash) => {
+                Self::WeightsCommitted {
+                    hotkey,
+                    netuid,
+                    commit_hash,
+                }
+            }
+            PalletEvent::WeightsRevealed(hotkey, netuid, commit_hash) => Self::WeightsRevealed {
+                hotkey,
+                netuid,
+                commit_hash,
+            },
+            PalletEvent::AxonServed(netuid, hotkey) => Self::AxonServed { netuid, hotkey },
+            PalletEvent::PrometheusServed(netuid, hotkey) => {
+                Self::PrometheusServed { netuid, hotkey }
+            }
+            PalletEvent::StakeAdded(coldkey, hotkey, tao, alpha, netuid, fee) => Self::StakeAdded {
+                coldkey,
+                hotkey,
+                tao,
+                alpha,
+                netuid,
+                fee,
+            },
+            PalletEvent::StakeRemoved(coldkey, hotkey, tao, alpha, netuid, fee) => {
+                Self::StakeRemoved {
+                    coldkey,
+                    hotkey,
+                    tao,
+                    alpha,
+                    netuid,
+                    fee,
+                }
+            }
+            PalletEvent::StakeMoved(
+                coldkey,
+                origin_hotkey,
+                origin_netuid,
+                destination_hotkey,
+                destination_netuid,
+                amount,
+            ) => Self::StakeMoved {
+                coldkey,
+                origin_hotkey,
+                origin_netuid,
+                destination_hotkey,
+                destination_netuid,
+                amount,
+            },
+            PalletEvent::StakeTransferred(
+                origin_coldkey,
+                destination_coldkey,
+                hotkey,
+                origin_netuid,
+                destination_netuid,
+                amount,
+            ) => Self::StakeTransferred {
+                origin_coldkey,
+                destination_coldkey,
+                hotkey,
+                origin_netuid,
+                destination_netuid,
+                amount,
+            },
+            PalletEvent::StakeSwapped(
+                coldkey,
+                hotkey,
+                origin_netuid,
+                destination_netuid,
+                amount,
+            ) => Self::StakeSwapped {
+                coldkey,
+                hotkey,
+                origin_netuid,
+                destination_netuid,
+                amount,
+            },
+            PalletEvent::HotkeySwapped {
+                coldkey,
+                old_hotkey,
+                new_hotkey,
+            } => Self::HotkeySwapped {
+                coldkey,
+                old_hotkey,
+                new_hotkey,
+                netuid: None,
+            },
+            PalletEvent::HotkeySwappedOnSubnet {
+                coldkey,
+                old_hotkey,
+                new_hotkey,
+                netuid,
+            } => Self::HotkeySwapped {
+                coldkey,
+                old_hotkey,
+                new_hotkey,
+                netuid: Some(netuid),
+            },
+            PalletEvent::ColdkeySwapped {
+                old_coldkey,
+                new_coldkey,
+                swap_cost,
+            } => Self::ColdkeySwapped {
+                old_coldkey,
+                new_coldkey,
+                swap_cost,
+            },
+            event => Self::Other(event),
+        }
+    }
+}
+
+impl SubtensorEvent {
+    /// The subnets the event concerns, empty for events without one and for [`SubtensorEvent::Other`].
+    pub fn netuids(&self) -> Vec<u16> {
+        match self {
+            Self::NetworkAdded { netuid, .. }
+            | Self::NetworkRemoved { netuid }
+            | Self::NeuronRegistered { netuid, .. }
+            | Self::WeightsSet { netuid, .. }
+            | Self::WeightsCommitted { netuid, .. }
+            | Self::WeightsRevealed { netuid, .. }
+            | Self::AxonServed { netuid, .. }
+            | Self::PrometheusServed { netuid, .. }
+            | Self::StakeAdded { netuid, .. }
+            | Self::StakeRemoved { netuid, .. } => vec![*netuid],
+            Self::StakeMoved {
+                origin_netuid,
+                destination_netuid,
+                ..
+            }
+            | Self::StakeTransferred {
+                origin_netuid,
+                destination_netuid,
+                ..
+            }
+            | Self::StakeSwapped {
+                origin_netuid,
+                destination_netuid,
+                ..
+            } => vec![*origin_netuid, *destination_netuid],
+            Self::HotkeySwapped { netuid, .. } => netuid.iter().copied().collect(),
+            Self::ColdkeySwapped { .. } | Self::Other(_) => Vec::new(),
+        }
+    }
+
+    pub fn hotkeys(&self) -> Vec<&AccountId> {
+        match self {
+            Self::NeuronRegistered { hotkey, .. }
+            | Self::WeightsCommitted { hotkey, .. }
+            | Self::WeightsRevealed { hotkey, .. }
+            | Self::AxonServed { hotkey, .. }
+            | Self::PrometheusServed { hotkey, .. }
+            | Self::StakeAdded { hotkey, .. }
+            | Self::StakeRemoved { hotkey, .. }
+            | Self::StakeTransferred { hotkey, .. }
+            | Self::StakeSwapped { hotkey, .. } => vec![hotkey],
+            Self::StakeMoved {
+                origin_hotkey,
+                destination_hotkey,
+                ..
+            } => vec![origin_hotkey, destination_hotkey],
+            Self::HotkeySwapped {
+                old_hotkey,
+                new_hotkey,
+                ..
+            } => vec![old_hotkey, new_hotkey],
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn coldkeys(&self) -> Vec<&AccountId> {
+        match self {
+            Self::StakeAdded { coldkey, .. }
+            | Self::StakeRemoved { coldkey, .. }
+            | Self::StakeMoved { coldkey, .. }
+            | Self::StakeSwapped { coldkey, .. }
+            | Self::HotkeySwapped { coldkey, .. } => vec![coldkey],
+            Self::StakeTransferred {
+                origin_coldkey,
+                destination_coldkey,
+                ..
+            } => vec![origin_coldkey, destination_coldkey],
+            Self::ColdkeySwapped {
+                old_coldkey,
+                new_coldkey,
+                ..
+            } => vec![old_coldkey, new_coldkey],
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Which events to deliver. Every criterion that is set must match, so an unset filter matches every event.
+#[derive(Clone, Debug, Default)]
+pub struct EventFilter {
+    pub netuid: Option<u16>,
+    pub hotkey: Option<AccountId>,
+    pub coldkey: Option<AccountId>,
+}
+
+impl EventFilter {
+    pub fn matches(&self, event: &SubtensorEvent) -> bool {
+        self.netuid
+            .is_none_or(|netuid| event.netuids().contains(&netuid))
+            && self
+                .hotkey
+                .as_ref()
+                .is_none_or(|hotkey| event.hotkeys().contains(&hotkey))
+            && self
+                .coldkey
+                .as_ref()
+                .is_none_or(|coldkey| event.coldkeys().contains(&coldkey))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EventRecord {
+    pub block_number: BlockNumber,
+    pub block_hash: Hash,
+    /// The index of the extrinsic that emitted the event, `None` for events emitted while initializing or
+    /// finalizing the block.
+    pub extrinsic_index: Option<u32>,
+    pub event: SubtensorEvent,
+}
+
+#[derive(Clone, Debug)]
+pub enum EventNotification {
+    Event(EventRecord),
+    /// Blocks whose events were delivered are no longer part of the best chain, and their events should be
+    /// discarded. Events from the blocks replacing them follow.
+    Reorg {
+        retracted: Vec<(BlockNumber, Hash)>,
+    },
+}
+
+/// The `SubtensorModule` events in `block` matching `filter`.
+pub async fn block_events(
+    block: &Block,
+    filter: &EventFilter,
+) -> Result<Vec<EventRecord>, subxt::Error> {
+    let mut records = Vec::new();
+
+    for event in block.events().await?.iter() {
+        let event = event?;
+
+        if event.pallet_name() != "SubtensorModule" {
+            continue;
+        }
+
+        let RuntimeEvent::SubtensorModule(pallet_event) = event.as_root_event::<RuntimeEvent>()?
+        else {
+            continue;
+        };
+
+        let subtensor_event = SubtensorEvent::from(pallet_event);
+
+        if !filter.matches(&subtensor_event) {
+            continue;
+        }
+
+        let extrinsic_index = match event.phase() {
+            Phase::ApplyExtrinsic(index) => Some(index),
+            _ => None,
+        };
+
+        records.push(EventRecord {
+            block_number: block.number(),
+            block_hash: block.hash(),
+            extrinsic_index,
+            event: subtensor_event,
+        });
+    }
+
+    Ok(records)
+}
+
+/// Streams `SubtensorModule` events matching `filter`, ending after the first error.
+///
+/// In [`BlockStreamKind::Best`] mode, a [`EventNotification::Reorg`] is delivered when the best chain switches to a
+/// fork, followed by the events of the blocks on the new fork that were not delivered yet.
+pub fn subscribe_events(
+    client: &Subtensor,
+    kind: BlockStreamKind,
+    filter: EventFilter,
+) -> EventSubscription {
+    let (sender, receiver) = mpsc::channel(64);
+    let client = client.clone();
+
+    tokio::spawn(async move {
+        if let Err(error) = stream_events(&client, kind, &filter, &sender).await {
+            let _ = sender.send(Err(error)).await;
+        }
+    });
+
+    EventSubscription(receiver)
+}
+
+type NotificationSender = mpsc::Sender<Result<EventNotification, subxt::Error>>;
+
+async fn stream_events(
+    client: &Subtensor,
+    kind: BlockStreamKind,
+    filter: &EventFilter,
+    sender: &NotificationSender,
+) -> Result<(), subxt::Error> {
+    let mut blocks = match kind {
+        BlockStreamKind::Best => client.blocks().subscribe_best().await?,
+        BlockStreamKind::Finalized => client.blocks().subscribe_finalized().await?,
+    };
+
+    // The hashes of the best blocks delivered, by number
+    let mut delivered: BTreeMap<BlockNumber, Hash> = BTreeMap::new();
+
+    while let Some(block) = blocks.next().await {
+        let block = block?;
+
+        let enacted = match kind {
+            BlockStreamKind::Finalized => vec![block],
+            BlockStreamKind::Best => {
+                // A best block announced again is still the head, not a fork
+                if delivered.last_key_value().map(|(_, hash)| *hash) == Some(block.hash()) {
+                    continue;
+                }
+
+                let (retracted, enacted) = fork_blocks(client, &delivered, block).await?;
+
+                if !retracted.is_empty() {
+                    for (number, _) in &retracted {
+                        delivered.remove(number);
+                    }
+
+                    if sender
+                        .send(Ok(EventNotification::Reorg { retracted }))
+                        .await
+                        .is_err()
+                    {
+                        return Ok(());
+                    }
+                }
+
+                enacted
+            }
+        };
+
+        for block in enacted {
+            for record in block_events(&block, filter).await? {
+                if sender
+                    .send(Ok(EventNotification::Event(record)))
+                    .await
+                    .is_err()
+                {
+                    return Ok(());
+                }
+            }
+
+            if matches!(kind, BlockStreamKind::Best) {
+                delivered.insert(block.number(), block.hash());
+
+                while delivered.len() > REORG_WINDOW {
+                    delivered.pop_first();
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Where [`fork_blocks`] fetches the ancestors of a new best block from.
+trait BlockSource {
+    type Block;
+
+    fn number(block: &Self::Block) -> BlockNumber;
+
+    fn parent_hash(block: &Self::Block) -> Hash;
+
+    async fn block(&self, hash: Hash) -> Result<Self::Block, subxt::Error>;
+}
+
+impl BlockSource for Subtensor {
+    type Block = Block;
+
+    fn number(block: &Block) -> BlockNumber {
+        block.number()
+    }
+
+    fn parent_hash(block: &Block) -> Hash {
+        block.header().parent_hash
+    }
+
+    async fn block(&self, hash: Hash) -> Result<Block, subxt::Error> {
+        self.blocks().at(hash).await
+    }
+}
+
+/// The delivered blocks `block` retracts, and the blocks from the common ancestor up to and including `block` that
+/// were not delivered yet, oldest first.
+async fn fork_blocks<S: BlockSource>(
+    source: &S,
+    delivered: &BTreeMap<BlockNumber, Hash>,
+    block: S::Block,
+) -> Result<(Vec<(BlockNumber, Hash)>, Vec<S::Block>), subxt::Error> {
+    let Some(&oldest) = delivered.keys().next() else {
+        return Ok((Vec::new(), vec![block]));
+    };
+
+    let mut ancestor_number = S::number(&block).saturating_sub(1);
+    let mut ancestor_hash = S::parent_hash(&block);
+    let mut enacted = vec![block];
+
+    // Walk back until reaching a delivered block, fetching the ones the subscription skipped or that are on the new
+    // fork
+    while ancestor_number >= oldest && delivered.get(&ancestor_number) != Some(&ancestor_hash) {
+        let ancestor = source.block(ancestor_hash).await?;
+
+        ancestor_hash = S::parent_hash(&ancestor);
+        enacted.push(ancestor);
+
+        if ancestor_number == 0 {
+            break;
+        }
+
+        ancestor_number -= 1;
+    }
+
+    let retracted = delivered
+        .range(ancestor_number + 1..)
+        .map(|(&number, &hash)| (number, hash))
+        .collect();
+
+    enacted.reverse();
+
+    Ok((retracted, enacted))
+}
+
+pub struct EventSubscription(mpsc::Receiver<Result<EventNotification, subxt::Error>>);
+
+impl EventSubscription {
+    pub async fn next(&mut self) -> Option<Result<EventNotification, subxt::Error>> {
+        self.0.recv().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct TestBlock {
+        number: BlockNumber,
+        hash: Hash,
+        parent_hash: Hash,
+    }
+
+    /// Blocks by hash, on forks told apart by the high bytes of their hashes.
+    #[derive(Default)]
+    struct TestChain(BTreeMap<Hash, TestBlock>);
+
+    fn hash(fork: u64, number: BlockNumber) -> Hash {
+        Hash::from_low_u64_be(fork << 32 | number as u64)
+    }
+
+    impl TestChain {
+        /// Adds blocks `from..=to` of `fork`, the first of them a child of `parent`, returning the last.
+        fn extend(
+            &mut self,
+            fork: u64,
+            parent: Hash,
+            from: BlockNumber,
+            to: BlockNumber,
+        ) -> TestBlock {
+            let mut parent_hash = parent;
+            let mut block = None;
+
+            for number in from..=to {
+                let added = TestBlock {
+                    number,
+                    hash: hash(fork, number),
+                    parent_hash,
+                };
+
+                self.0.insert(added.hash, added);
+                parent_hash = added.hash;
+                block = Some(added);
+            }
+
+            block.unwrap()
+        }
+    }
+
+    impl BlockSource for TestChain {
+        type Block = TestBlock;
+
+        fn number(block: &TestBlock) -> BlockNumber {
+            block.number
+        }
+
+        fn parent_hash(block: &TestBlock) -> Hash {
+            block.parent_hash
+        }
+
+        async fn block(&self, hash: Hash) -> Result<TestBlock, subxt::Error> {
+            self.0
+                .get(&hash)
+                .copied()
+                .ok_or_else(|| subxt::Error::Other(format!("Unknown block {hash:?}")))
+        }
+    }
+
+    /// Blocks `from..=to` of `fork` as delivered.
+    fn delivered(fork: u64, from: BlockNumber, to: BlockNumber) -> BTreeMap<BlockNumber, Hash> {
+        (from..=to)
+            .map(|number| (number, hash(fork, number)))
+            .collect()
+    }
+
+    fn numbers(blocks: &[TestBlock]) -> Vec<(BlockNumber, Hash)> {
+        blocks
+            .iter()
+            .map(|block| (block.number, block.hash))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn extends_the_delivered_chain() {
+        let mut chain = TestChain::default();
+        chain.extend(0, Hash::zero(), 0, 3);
+        let block = chain.extend(0, hash(0, 3), 4, 4);
+
+        let (retracted, enacted) = fork_blocks(&chain, &delivered(0, 0, 3), block)
+            .await
+            .unwrap();
+
+        assert!(retracted.is_empty());
+        assert_eq!(enacted, vec![block]);
+
+        let (retracted, enacted) = fork_blocks(&chain, &BTreeMap::new(), block).await.unwrap();
+
+        assert!(retracted.is_empty());
+        assert_eq!(enacted, vec![block]);
+    }
+
+    #[tokio::test]
+    async fn backfills_blocks_the_subscription_skipped() {
+        let mut chain = TestChain::default();
+        chain.extend(0, Hash::zero(), 0, 2);
+        let block = chain.extend(0, hash(0, 2), 3, 6);
+
+        let (retracted, enacted) = fork_blocks(&chain, &delivered(0, 0, 2), block)
+            .await
+            .unwrap();
+
+        assert!(retracted.is_empty());
+        assert_eq!(
+            numbers(&enacted),
+            (3..=6)
+                .map(|number| (number, hash(0, number)))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn retracts_a_fork_of_the_same_height() {
+        let mut chain = TestChain::default();
+        chain.extend(0, Hash::zero(), 0, 5);
+        let block = chain.extend(1, hash(0, 2), 3, 5);
+
+        let (retracted, enacted) = fork_blocks(&chain, &delivered(0, 0, 5), block)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            retracted,
+            vec![(3, hash(0, 3)), (4, hash(0, 4)), (5, hash(0, 5))]
+        );
+        assert_eq!(
+            numbers(&enacted),
+            vec![(3, hash(1, 3)), (4, hash(1, 4)), (5, hash(1, 5))]
+        );
+    }
+
+    #[tokio::test]
+    async fn retracts_blocks_above_a_shorter_fork() {
+        let mut chain = TestChain::default();
+        chain.extend(0, Hash::zero(), 0, 6);
+        let block = chain.extend(1, hash(0, 3), 4, 4);
+
+        let (retracted, enacted) = fork_blocks(&chain, &delivered(0, 0, 6), block)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            retracted,
+            vec![(4, hash(0, 4)), (5, hash(0, 5)), (6, hash(0, 6))]
+        );
+        assert_eq!(numbers(&enacted), vec![(4, hash(1, 4))]);
+    }
+
+    #[tokio::test]
+    async fn retracts_every_delivered_block_of_a_fork_deeper_than_the_window() {
+        let mut chain = TestChain::default();
+        chain.extend(0, Hash::zero(), 0, 10);
+
+        // Only the last blocks are still remembered once more than `REORG_WINDOW` were delivered
+        let from = 11;
+        let to = from + REORG_WINDOW as BlockNumber - 1;
+        chain.extend(0, hash(0, 10), from, to);
+        let block = chain.extend(1, hash(0, 5), 6, to + 1);
+
+        let (retracted, enacted) = fork_blocks(&chain, &delivered(0, from, to), block)
+            .await
+            .unwrap();
+
+        // The walk stops at the oldest remembered block, without reaching the common ancestor
+        assert_eq!(retracted.len(), REORG_WINDOW);
+        assert_eq!(retracted.first(), Some(&(from, hash(0, from))));
+        assert_eq!(retracted.last(), Some(&(to, hash(0, to))));
+        assert_eq!(enacted.len(), REORG_WINDOW + 1);
+        assert_eq!(enacted.first().map(|block| block.hash), Some(hash(1, from)));
+        assert_eq!(enacted.last(), Some(&block));
+    }
+
+    #[test]
+    fn filters_match_every_set_criterion() {
+        let alice = AccountId::from([1; 32]);
+        let bob = AccountId::from([2; 32]);
+        let carol = AccountId::from([3; 32]);
+
+        let stake_added = SubtensorEvent::StakeAdded {
+            coldkey: alice.clone(),
+            hotkey: bob.clone(),
+            tao: 1,
+            alpha: 1,
+            netuid: 1,
+            fee: 0,
+        };
+        let stake_moved = SubtensorEvent::StakeMoved {
+            coldkey: alice.clone(),
+            origin_hotkey: bob.clone(),
+            origin_netuid: 1,
+            destination_hotkey: carol.clone(),
+            destination_netuid: 2,
+            amount: 1,
+        };
+        let transferred = SubtensorEvent::StakeTransferred {
+            origin_coldkey: alice.clone(),
+            destination_coldkey: carol.clone(),
+            hotkey: bob.clone(),
+            origin_netuid: 3,
+            destination_netuid: 3,
+            amount: 1,
+        };
+        let weights_set = SubtensorEvent::WeightsSet { netuid: 1, uid: 0 };
+        let swapped = SubtensorEvent::HotkeySwapped {
+            coldkey: alice.clone(),
+            old_hotkey: bob.clone(),
+            new_hotkey: carol.clone(),
+            netuid: None,
+        };
+
+        let filter =
+            |netuid, hotkey: Option<&AccountId>, coldkey: Option<&AccountId>| EventFilter {
+                netuid,
+                hotkey: hotkey.cloned(),
+                coldkey: coldkey.cloned(),
+            };
+
+        let cases = [
+            (filter(None, None, None), &weights_set, true),
+            (filter(Some(1), None, None), &weights_set, true),
+            (filter(Some(2), None, None), &weights_set, false),
+            (filter(None, Some(&bob), None), &weights_set, false),
+            (filter(None, None, Some(&alice)), &weights_set, false),
+            (
+                filter(Some(1), Some(&bob), Some(&alice)),
+                &stake_added,
+                true,
+            ),
+            (
+                filter(Some(2), Some(&bob), Some(&alice)),
+                &stake_added,
+                false,
+            ),
+            (
+                filter(Some(1), Some(&carol), Some(&alice)),
+                &stake_added,
+                false,
+            ),
+            (filter(Some(1), Some(&bob), Some(&bob)), &stake_added, false),
+            // Either subnet, hotkey or coldkey of an event involving two matches
+            (filter(Some(2), Some(&bob), None), &stake_moved, true),
+            (filter(Some(1), Some(&carol), None), &stake_moved, true),
+            (filter(None, None, Some(&carol)), &transferred, true),
+            (filter(None, Some(&carol), None), &transferred, false),
+            (filter(None, Some(&carol), Some(&alice)), &swapped, true),
+            // A swap on every subnet concerns none in particular
+            (filter(Some(1), None, None), &swapped, false),
+        ];
+
+        for (index, (filter, event, expected)) in cases.iter().enumerate() {
+            assert_eq!(filter.matches(event), *expected, "case {index}");
+        }
+    }
+}
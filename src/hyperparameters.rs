@@ -0,0 +1,524 @@
+use std::fmt;
+
+use subxt::ext::scale_value::Composite;
+use tokio::sync::mpsc;
+
+use crate::api::runtime_types::pallet_subtensor::rpc_info::subnet_info::SubnetHyperparamsV2;
+use crate::dynamic::{self, Value};
+use crate::subtensor::Subtensor;
+use crate::{Block, BlockNumber, BlockRef, Hash};
+
+/// `SubtensorModule` events the `AdminUtils` setters emit, each carrying the netuid followed by the new value.
+///
+/// Some setters, such as the liquid alpha ones, emit no event, so their changes only show up in [`diff_between`].
+pub const SUBTENSOR_MODULE_EVENTS: &[&str] = &[
+    "ActivityCutoffSet",
+    "AdjustmentAlphaSet",
+    "AdjustmentIntervalSet",
+    "AlphaSigmoidSteepnessSet",
+    "BondsMovingAverageSet",
+    "BondsPenaltySet",
+    "BondsResetOnSet",
+    "BurnSet",
+    "CommitRevealEnabled",
+    "CommitRevealPeriodsSet",
+    "DifficultySet",
+    "ImmunityPeriodSet",
+    "KappaSet",
+    "MaxAllowedUidsSet",
+    "MaxAllowedValidatorsSet",
+    "MaxBurnSet",
+    "MaxDifficultySet",
+    "MaxRegistrationsPerBlockSet",
+    "MaxWeightLimitSet",
+    "MinAllowedWeightSet",
+    "MinBurnSet",
+    "MinDifficultySet",
+    "PowRegistrationAllowed",
+    "RegistrationAllowed",
+    "RegistrationPerIntervalSet",
+    "RhoSet",
+    "ScalingLawPowerSet",
+    "ServingRateLimitSet",
+    "TempoSet",
+    "TransferToggle",
+    "ValidatorPruneLenSet",
+    "WeightsSetRateLimitSet",
+    "WeightsVersionKeySet",
+];
+
+/// Subnet hyperparameters as returned by `get_subnet_hyperparams_v2`, with fixed-point values converted to floats.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubnetHyperparameters {
+    pub rho: u16,
+    pub kappa: u16,
+    pub immunity_period: u16,
+    pub min_allowed_weights: u16,
+    pub max_weights_limit: u16,
+    pub tempo: u16,
+    pub min_difficulty: u64,
+    pub max_difficulty: u64,
+    pub weights_version: u64,
+    pub weights_rate_limit: u64,
+    pub adjustment_interval: u16,
+    pub activity_cutoff: u16,
+    pub registration_allowed: bool,
+    pub target_regs_per_interval: u16,
+    pub min_burn: u64,
+    pub max_burn: u64,
+    pub bonds_moving_avg: u64,
+    pub max_regs_per_block: u16,
+    pub serving_rate_limit: u64,
+    pub max_validators: u16,
+    pub adjustment_alpha: u64,
+    pub difficulty: u64,
+    pub commit_reveal_period: u64,
+    pub commit_reveal_weights_enabled: bool,
+    pub alpha_high: u16,
+    pub alpha_low: u16,
+    pub liquid_alpha_enabled: bool,
+    pub alpha_sigmoid_steepness: f64,
+    pub yuma_version: u16,
+    pub subnet_is_active: bool,
+    pub transfers_enabled: bool,
+    pub bonds_reset_enabled: bool,
+    pub user_liquidity_enabled: bool,
+}
+
+impl SubnetHyperparameters {
+    /// Kappa as a fraction of `u16::MAX`.
+    pub fn kappa_fraction(&self) -> f64 {
+        self.kappa as f64 / u16::MAX as f64
+    }
+
+    pub fn alpha_high_fraction(&self) -> f64 {
+        self.alpha_high as f64 / u16::MAX as f64
+    }
+
+    pub fn alpha_low_fraction(&self) -> f64 {
+        self.alpha_low as f64 / u16::MAX as f64
+    }
+
+    /// The bonds moving average, which the pallet scales by 1,000,000.
+    pub fn bonds_moving_avg_fraction(&self) -> f64 {
+        self.bonds_moving_avg as f64 / 1_000_000.0
+    }
+
+    pub fn adjustment_alpha_fraction(&self) -> f64 {
+        self.adjustment_alpha as f64 / u64::MAX as f64
+    }
+
+    pub fn yuma3_enabled(&self) -> bool {
+        self.yuma_version >= 3
+    }
+
+    /// Every hyperparameter by name, in declaration order.
+    pub fn values(&self) -> Vec<(&'static str, HyperparameterValue)> {
+        use HyperparameterValue::{Bool, Float, Integer};
+
+        vec![
+            ("rho", Integer(self.rho.into())),
+            ("kappa", Integer(self.kappa.into())),
+            ("immunity_period", Integer(self.immunity_period.into())),
+            (
+                "min_allowed_weights",
+                Integer(self.min_allowed_weights.into()),
+            ),
+            ("max_weights_limit", Integer(self.max_weights_limit.into())),
+            ("tempo", Integer(self.tempo.into())),
+            ("min_difficulty", Integer(self.min_difficulty)),
+            ("max_difficulty", Integer(self.max_difficulty)),
+            ("weights_version", Integer(self.weights_version)),
+            ("weights_rate_limit", Integer(self.weights_rate_limit)),
+            (
+                "adjustment_interval",
+                Integer(self.adjustment_interval.into()),
+            ),
+            ("activity_cutoff", Integer(self.activity_cutoff.into())),
+            ("registration_allowed", Bool(self.registration_allowed)),
+            (
+                "target_regs_per_interval",
+                Integer(self.target_regs_per_interval.into()),
+            ),
+            ("min_burn", Integer(self.min_burn)),
+            ("max_burn", Integer(self.max_burn)),
+            ("bonds_moving_avg", Integer(self.bonds_moving_avg)),
+            (
+                "max_regs_per_block",
+                Integer(self.max_regs_per_block.into()),
+            ),
+            ("serving_rate_limit", Integer(self.serving_rate_limit)),
+            ("max_validators", Integer(self.max_validators.into())),
+            ("adjustment_alpha", Integer(self.adjustment_alpha)),
+            ("difficulty", Integer(self.difficulty)),
+            ("commit_reveal_period", Integer(self.commit_reveal_period)),
+            (
+                "commit_reveal_weights_enabled",
+                Bool(self.commit_reveal_weights_enabled),
+            ),
+            ("alpha_high", Integer(self.alpha_high.into())),
+            ("alpha_low", Integer(self.alpha_low.into())),
+            ("liquid_alpha_enabled", Bool(self.liquid_alpha_enabled)),
+            (
+                "alpha_sigmoid_steepness",
+                Float(self.alpha_sigmoid_steepness),
+            ),
+            ("yuma_version", Integer(self.yuma_version.into())),
+            ("subnet_is_active", Bool(self.subnet_is_active)),
+            ("transfers_enabled", Bool(self.transfers_enabled)),
+            ("bonds_reset_enabled", Bool(self.bonds_reset_enabled)),
+            ("user_liquidity_enabled", Bool(self.user_liquidity_enabled)),
+        ]
+    }
+
+    /// The hyperparameters that differ between `self` and `other`, with `self` as the before side.
+    pub fn diff(&self, other: &Self) -> Vec<HyperparameterChange> {
+        self.values()
+            .into_iter()
+            .zip(other.values())
+            .filter(|((_, before), (_, after))| before != after)
+            .map(|((name, before), (_, after))| HyperparameterChange {
+                name,
+                before,
+                after,
+            })
+            .collect()
+    }
+}
+
+impl From<SubnetHyperparamsV2> for SubnetHyperparameters {
+    fn from(params: SubnetHyperparamsV2) -> Self {
+        Self {
+            rho: params.rho,
+            kappa: params.kappa,
+            immunity_period: params.immunity_period,
+            min_allowed_weights: params.min_allowed_weights,
+            max_weights_limit: params.max_weights_limit,
+            tempo: params.tempo,
+            min_difficulty: params.min_difficulty,
+            max_difficulty: params.max_difficulty,
+            weights_version: params.weights_version,
+            weights_rate_limit: params.weights_rate_limit,
+            adjustment_interval: params.adjustment_interval,
+            activity_cutoff: params.activity_cutoff,
+            registration_allowed: params.registration_allowed,
+            target_regs_per_interval: params.target_regs_per_interval,
+            min_burn: params.min_burn,
+            max_burn: params.max_burn,
+            bonds_moving_avg: params.bonds_moving_avg,
+            max_regs_per_block: params.max_regs_per_block,
+            serving_rate_limit: params.serving_rate_limit,
+            max_validators: params.max_validators,
+            adjustment_alpha: params.adjustment_alpha,
+            difficulty: params.difficulty,
+            commit_reveal_period: params.commit_reveal_period,
+            commit_reveal_weights_enabled: params.commit_reveal_weights_enabled,
+            alpha_high: params.alpha_high,
+            alpha_low: params.alpha_low,
+            liquid_alpha_enabled: params.liquid_alpha_enabled,
+            // I32F32
+            alpha_sigmoid_steepness: params.alpha_sigmoid_steepness.bits as f64
+                / (1u64 << 32) as f64,
+            yuma_version: params.yuma_version,
+            subnet_is_active: params.subnet_is_active,
+            transfers_enabled: params.transfers_enabled,
+            bonds_reset_enabled: params.bonds_reset_enabled,
+            user_liquidity_enabled: params.user_liquidity_enabled,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HyperparameterValue {
+    Bool(bool),
+    Integer(u64),
+    Float(f64),
+}
+
+impl fmt::Display for HyperparameterValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bool(value) => value.fmt(f),
+            Self::Integer(value) => value.fmt(f),
+            Self::Float(value) => value.fmt(f),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HyperparameterChange {
+    pub name: &'static str,
+    pub before: HyperparameterValue,
+    pub after: HyperparameterValue,
+}
+
+impl fmt::Display for HyperparameterChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} -> {}", self.name, self.before, self.after)
+    }
+}
+
+pub async fn hyperparameters(
+    client: &Subtensor,
+    at: BlockRef,
+    netuid: u16,
+) -> Result<Option<SubnetHyperparameters>, subxt::Error> {
+    Ok(dynamic::hyperparameters(client, at, netuid)
+        .await?
+        .map(Into::into))
+}
+
+/// The hyperparameters of `netuid` that changed between two blocks, or `None` if the subnet does not exist at either.
+pub async fn diff_between(
+    client: &Subtensor,
+    from: BlockRef,
+    to: BlockRef,
+    netuid: u16,
+) -> Result<Option<Vec<HyperparameterChange>>, subxt::Error> {
+    let before = hyperparameters(client, from, netuid).await?;
+    let after = hyperparameters(client, to, netuid).await?;
+
+    Ok(before.zip(after).map(|(before, after)| before.diff(&after)))
+}
+
+#[derive(Clone, Debug)]
+pub struct HyperparameterEvent {
+    pub block_number: BlockNumber,
+    pub block_hash: Hash,
+    pub netuid: u16,
+    pub pallet: String,
+    pub variant: String,
+    /// The new value, if the event carries exactly one field besides the netuid.
+    pub value: Option<Value>,
+}
+
+/// Events in `block` that changed the hyperparameters of a subnet, optionally only those of `netuid`.
+///
+/// These are the `AdminUtils` events carrying a netuid, and the [`SUBTENSOR_MODULE_EVENTS`] its setters emit.
+pub async fn hyperparameter_events(
+    block: &Block,
+    netuid: Option<u16>,
+) -> Result<Vec<HyperparameterEvent>, subxt::Error> {
+    let mut changes = Vec::new();
+
+    for event in block.events().await?.iter() {
+        let event = event?;
+
+        let relevant = match event.pallet_name() {
+            "AdminUtils" => true,
+            "SubtensorModule" => SUBTENSOR_MODULE_EVENTS.contains(&event.variant_name()),
+            _ => false,
+        };
+
+        if !relevant {
+            continue;
+        }
+
+        // `SubtensorModule` events are tuples starting with the netuid, `AdminUtils` ones name it
+        let (subnet, mut rest): (_, Vec<Value>) = match event.field_values()? {
+            Composite::Named(fields) => {
+                let (subnet, rest): (Vec<_>, Vec<_>) =
+                    fields.into_iter().partition(|(name, _)| name == "netuid");
+
+                (
+                    subnet.into_iter().next().map(|(_, value)| value),
+                    rest.into_iter()
+                        .map(|(_, value)| value.remove_context())
+                        .collect(),
+                )
+            }
+            Composite::Unnamed(values) => {
+                let mut values = values.into_iter();
+
+                (
+                    values.next(),
+                    values.map(|value| value.remove_context()).collect(),
+                )
+            }
+        };
+
+        let Some(subnet) = subnet
+            .and_then(|value| value.as_u128())
+            .and_then(|value| u16::try_from(value).ok())
+        else {
+            continue;
+        };
+
+        if netuid.is_some_and(|netuid| netuid != subnet) {
+            continue;
+        }
+
+        changes.push(HyperparameterEvent {
+            block_number: block.number(),
+            block_hash: block.hash(),
+            netuid: subnet,
+            pallet: event.pallet_name().to_string(),
+            variant: event.variant_name().to_string(),
+            value: (rest.len() == 1).then(|| rest.remove(0)),
+        });
+    }
+
+    Ok(changes)
+}
+
+/// Streams [`hyperparameter_events`] from finalized blocks, ending after the first error.
+pub fn subscribe_hyperparameter_events(
+    client: &Subtensor,
+    netuid: Option<u16>,
+) -> HyperparameterEvents {
+    let (sender, receiver) = mpsc::channel(16);
+    let client = client.clone();
+
+    tokio::spawn(async move {
+        let mut blocks = match client.blocks().subscribe_finalized().await {
+            Ok(blocks) => blocks,
+            Err(error) => {
+                let _ = sender.send(Err(error)).await;
+                return;
+            }
+        };
+
+        while let Some(block) = blocks.next().await {
+            let events = match block {
+                Ok(block) => hyperparameter_events(&block, netuid).await,
+                Err(error) => Err(error),
+            };
+
+            match events {
+                Ok(events) => {
+                    for event in events {
+                        if sender.send(Ok(event)).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+                Err(error) => {
+                    let _ = sender.send(Err(error)).await;
+                    return;
+                }
+            }
+        }
+    });
+
+    HyperparameterEvents(receiver)
+}
+
+pub struct HyperparameterEvents(mpsc::Receiver<Result<HyperparameterEvent, subxt::Error>>);
+
+impl HyperparameterEvents {
+    pub async fn next(&mut self) -> Option<Result<HyperparameterEvent, subxt::Error>> {
+        self.0.recv().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::marker::PhantomData;
+
+    use super::*;
+    use crate::api::runtime_types::substrate_fixed::FixedI64;
+
+    fn params() -> SubnetHyperparamsV2 {
+        SubnetHyperparamsV2 {
+            rho: 10,
+            kappa: 32_767,
+            immunity_period: 5000,
+            min_allowed_weights: 1,
+            max_weights_limit: 65_535,
+            tempo: 360,
+            min_difficulty: 10_000_000,
+            max_difficulty: 4_611_686_018_427_387_903,
+            weights_version: 0,
+            weights_rate_limit: 100,
+            adjustment_interval: 112,
+            activity_cutoff: 5000,
+            registration_allowed: true,
+            target_regs_per_interval: 2,
+            min_burn: 500_000,
+            max_burn: 100_000_000_000,
+            bonds_moving_avg: 900_000,
+            max_regs_per_block: 1,
+            serving_rate_limit: 50,
+            max_validators: 64,
+            adjustment_alpha: 0,
+            difficulty: 10_000_000,
+            commit_reveal_period: 1,
+            commit_reveal_weights_enabled: false,
+            alpha_high: 58_982,
+            alpha_low: 45_875,
+            liquid_alpha_enabled: false,
+            alpha_sigmoid_steepness: FixedI64 {
+                bits: 1000 << 32,
+                __ignore: PhantomData,
+            },
+            yuma_version: 2,
+            subnet_is_active: true,
+            transfers_enabled: true,
+            bonds_reset_enabled: false,
+            user_liquidity_enabled: false,
+        }
+    }
+
+    #[test]
+    fn converts_fixed_point_steepness_to_a_float() {
+        assert_eq!(
+            SubnetHyperparameters::from(params()).alpha_sigmoid_steepness,
+            1000.0
+        );
+
+        let mut negative = params();
+        negative.alpha_sigmoid_steepness.bits = -(1 << 31);
+
+        assert_eq!(
+            SubnetHyperparameters::from(negative).alpha_sigmoid_steepness,
+            -0.5
+        );
+    }
+
+    #[test]
+    fn diff_reports_exactly_the_changed_fields() {
+        let before = SubnetHyperparameters::from(params());
+
+        assert!(before.diff(&before).is_empty());
+
+        let mut after = before.clone();
+        after.tempo = 99;
+        after.commit_reveal_weights_enabled = true;
+        after.alpha_sigmoid_steepness = 500.0;
+
+        assert_eq!(
+            before.diff(&after),
+            vec![
+                HyperparameterChange {
+                    name: "tempo",
+                    before: HyperparameterValue::Integer(360),
+                    after: HyperparameterValue::Integer(99),
+                },
+                HyperparameterChange {
+                    name: "commit_reveal_weights_enabled",
+                    before: HyperparameterValue::Bool(false),
+                    after: HyperparameterValue::Bool(true),
+                },
+                HyperparameterChange {
+                    name: "alpha_sigmoid_steepness",
+                    before: HyperparameterValue::Float(1000.0),
+                    after: HyperparameterValue::Float(500.0),
+                },
+            ]
+        );
+        assert_eq!(before.diff(&after)[0].to_string(), "tempo: 360 -> 99");
+    }
+
+    #[test]
+    fn values_name_every_field_once() {
+        let values = SubnetHyperparameters::from(params()).values();
+        let mut names: Vec<_> = values.iter().map(|(name, _)| *name).collect();
+
+        assert_eq!(values.len(), 33);
+
+        names.sort();
+        names.dedup();
+
+        assert_eq!(names.len(), values.len());
+    }
+}
@@ -0,0 +1,521 @@
+//! On-chain identities, set by coldkeys and subnet owners with `SubtensorModule`, or by the `Registry` pallet.
+
+use std::collections::BTreeMap;
+
+use subxt::ext::codec::Encode;
+use subxt::storage::Address;
+use subxt::tx::Payload;
+use subxt::utils::Yes;
+use thiserror::Error;
+
+use crate::api::runtime_types::pallet_registry::types::Data as RegistryData;
+use crate::api::runtime_types::pallet_subtensor::pallet::{
+    ChainIdentity, ChainIdentityV2, SubnetIdentity as SubnetIdentityV1, SubnetIdentityV2,
+    SubnetIdentityV3,
+};
+use crate::subtensor::{trailing_account_id, trailing_netuid, Subtensor};
+use crate::{api, AccountId, BlockRef};
+
+#[derive(Debug, Error)]
+#[error("Identity field {field} is {length} bytes, the maximum is {max}")]
+pub struct IdentityTooLongError {
+    pub field: &'static str,
+    pub length: usize,
+    pub max: usize,
+}
+
+fn check_lengths(fields: &[(&'static str, &str, usize)]) -> Result<(), IdentityTooLongError> {
+    for &(field, value, max) in fields {
+        if value.len() > max {
+            return Err(IdentityTooLongError {
+                field,
+                length: value.len(),
+                max,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+fn text(bytes: Vec<u8>) -> String {
+    String::from_utf8(bytes)
+        .unwrap_or_else(|error| String::from_utf8_lossy(error.as_bytes()).into_owned())
+}
+
+/// The identity of a coldkey, as set with `set_identity`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Identity {
+    pub name: String,
+    pub url: String,
+    pub github_repo: String,
+    pub image: String,
+    pub discord: String,
+    pub description: String,
+    pub additional: String,
+}
+
+impl Identity {
+    /// Checks the field lengths against `Pallet::is_valid_identity`, which rejects the extrinsic otherwise.
+    pub fn validate(&self) -> Result<(), IdentityTooLongError> {
+        check_lengths(&[
+            ("name", &self.name, 256),
+            ("url", &self.url, 256),
+            ("github_repo", &self.github_repo, 256),
+            ("image", &self.image, 1024),
+            ("discord", &self.discord, 256),
+            ("description", &self.description, 1024),
+            ("additional", &self.additional, 1024),
+        ])
+    }
+}
+
+impl From<ChainIdentityV2> for Identity {
+    fn from(identity: ChainIdentityV2) -> Self {
+        Self {
+            name: text(identity.name),
+            url: text(identity.url),
+            github_repo: text(identity.github_repo),
+            image: text(identity.image),
+            discord: text(identity.discord),
+            description: text(identity.description),
+            additional: text(identity.additional),
+        }
+    }
+}
+
+impl From<ChainIdentity> for Identity {
+    fn from(identity: ChainIdentity) -> Self {
+        Self {
+            name: text(identity.name),
+            url: text(identity.url),
+            github_repo: String::new(),
+            image: text(identity.image),
+            discord: text(identity.discord),
+            description: text(identity.description),
+            additional: text(identity.additional),
+        }
+    }
+}
+
+/// Sets the identity of the signing coldkey, which must own a registered hotkey.
+pub fn set_identity_payload(identity: &Identity) -> Result<impl Payload, IdentityTooLongError> {
+    identity.validate()?;
+
+    let identity = identity.clone();
+
+    Ok(api::tx().subtensor_module().set_identity(
+        identity.name.into_bytes(),
+        identity.url.into_bytes(),
+        identity.github_repo.into_bytes(),
+        identity.image.into_bytes(),
+        identity.discord.into_bytes(),
+        identity.description.into_bytes(),
+        identity.additional.into_bytes(),
+    ))
+}
+
+/// The identity of a subnet, as set by its owner with `set_subnet_identity`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SubnetIdentity {
+    pub subnet_name: String,
+    pub github_repo: String,
+    pub subnet_contact: String,
+    pub subnet_url: String,
+    pub discord: String,
+    pub description: String,
+    pub logo_url: String,
+    pub additional: String,
+}
+
+impl SubnetIdentity {
+    /// Checks the field lengths against `Pallet::is_valid_subnet_identity`, which rejects the extrinsic otherwise.
+    pub fn validate(&self) -> Result<(), IdentityTooLongError> {
+        check_lengths(&[
+            ("subnet_name", &self.subnet_name, 256),
+            ("github_repo", &self.github_repo, 1024),
+            ("subnet_contact", &self.subnet_contact, 1024),
+            ("subnet_url", &self.subnet_url, 1024),
+            ("discord", &self.discord, 256),
+            ("description", &self.description, 1024),
+            ("logo_url", &self.logo_url, 1024),
+            ("additional", &self.additional, 1024),
+        ])
+    }
+}
+
+impl From<SubnetIdentityV3> for SubnetIdentity {
+    fn from(identity: SubnetIdentityV3) -> Self {
+        Self {
+            subnet_name: text(identity.subnet_name),
+            github_repo: text(identity.github_repo),
+            subnet_contact: text(identity.subnet_contact),
+            subnet_url: text(identity.subnet_url),
+            discord: text(identity.discord),
+            description: text(identity.description),
+            logo_url: text(identity.logo_url),
+            additional: text(identity.additional),
+        }
+    }
+}
+
+impl From<SubnetIdentityV2> for SubnetIdentity {
+    fn from(identity: SubnetIdentityV2) -> Self {
+        Self {
+            subnet_name: text(identity.subnet_name),
+            github_repo: text(identity.github_repo),
+            subnet_contact: text(identity.subnet_contact),
+            subnet_url: text(identity.subnet_url),
+            discord: text(identity.discord),
+            description: text(identity.description),
+            logo_url: String::new(),
+            additional: text(identity.additional),
+        }
+    }
+}
+
+impl From<SubnetIdentityV1> for SubnetIdentity {
+    fn from(identity: SubnetIdentityV1) -> Self {
+        Self {
+            subnet_name: text(identity.subnet_name),
+            github_repo: text(identity.github_repo),
+            subnet_contact: text(identity.subnet_contact),
+            ..Default::default()
+        }
+    }
+}
+
+/// Sets the identity of `netuid`, signed by the subnet owner's coldkey.
+pub fn set_subnet_identity_payload(
+    netuid: u16,
+    identity: &SubnetIdentity,
+) -> Result<impl Payload, IdentityTooLongError> {
+    identity.validate()?;
+
+    let identity = identity.clone();
+
+    Ok(api::tx().subtensor_module().set_subnet_identity(
+        netuid,
+        identity.subnet_name.into_bytes(),
+        identity.github_repo.into_bytes(),
+        identity.subnet_contact.into_bytes(),
+        identity.subnet_url.into_bytes(),
+        identity.discord.into_bytes(),
+        identity.description.into_bytes(),
+        identity.logo_url.into_bytes(),
+        identity.additional.into_bytes(),
+    ))
+}
+
+/// The identity of `coldkey`, falling back to the storage used before `IdentitiesV2` for coldkeys not migrated.
+pub async fn identity(
+    client: &Subtensor,
+    at: BlockRef,
+    coldkey: &AccountId,
+) -> Result<Option<Identity>, subxt::Error> {
+    let storage = client.storage().at(at);
+    let subtensor_module = api::storage().subtensor_module();
+
+    if let Some(identity) = storage
+        .fetch(&subtensor_module.identities_v2(coldkey))
+        .await?
+    {
+        return Ok(Some(identity.into()));
+    }
+
+    Ok(storage
+        .fetch(&subtensor_module.identities(coldkey))
+        .await?
+        .map(Identity::from))
+}
+
+/// Inserts every entry of the map at `address` into `identities`, keyed by `key` decoded from its storage key. Entries
+/// replace those already inserted, so storage versions are read from oldest to newest.
+async fn insert_entries<Addr, Key, Value>(
+    client: &Subtensor,
+    at: BlockRef,
+    address: Addr,
+    key: impl Fn(&[u8]) -> Option<Key>,
+    identities: &mut BTreeMap<Key, Value>,
+) -> Result<(), subxt::Error>
+where
+    Addr: Address<IsIterable = Yes> + 'static,
+    Addr::Keys: 'static + Sized,
+    Addr::Target: Into<Value>,
+    Key: Ord,
+{
+    let mut entries = client.storage().at(at).iter(address).await?;
+
+    while let Some(entry) = entries.next().await {
+        let entry = entry?;
+
+        if let Some(key) = key(&entry.key_bytes) {
+            identities.insert(key, entry.value.into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Every coldkey identity, keyed by coldkey, falling back to the storage used before `IdentitiesV2` like [`identity`].
+pub async fn identities(
+    client: &Subtensor,
+    at: BlockRef,
+) -> Result<BTreeMap<AccountId, Identity>, subxt::Error> {
+    let subtensor_module = api::storage().subtensor_module();
+    let key = trailing_account_id;
+    let mut identities = BTreeMap::new();
+
+    // Oldest first, so a migrated coldkey keeps its `IdentitiesV2` identity
+    let legacy = subtensor_module.identities_iter();
+    insert_entries(client, at.clone(), legacy, key, &mut identities).await?;
+    let current = subtensor_module.identities_v2_iter();
+    insert_entries(client, at, current, key, &mut identities).await?;
+
+    Ok(identities)
+}
+
+/// The identity of `netuid`, falling back to the storage versions preceding `SubnetIdentitiesV3`.
+pub async fn subnet_identity(
+    client: &Subtensor,
+    at: BlockRef,
+    netuid: u16,
+) -> Result<Option<SubnetIdentity>, subxt::Error> {
+    let storage = client.storage().at(at);
+    let subtensor_module = api::storage().subtensor_module();
+
+    if let Some(identity) = storage
+        .fetch(&subtensor_module.subnet_identities_v3(netuid))
+        .await?
+    {
+        return Ok(Some(identity.into()));
+    }
+
+    if let Some(identity) = storage
+        .fetch(&subtensor_module.subnet_identities_v2(netuid))
+        .await?
+    {
+        return Ok(Some(identity.into()));
+    }
+
+    Ok(storage
+        .fetch(&subtensor_module.subnet_identities(netuid))
+        .await?
+        .map(SubnetIdentity::from))
+}
+
+/// Every subnet identity, keyed by netuid, falling back to the storage versions preceding `SubnetIdentitiesV3` like
+/// [`subnet_identity`].
+pub async fn subnet_identities(
+    client: &Subtensor,
+    at: BlockRef,
+) -> Result<BTreeMap<u16, SubnetIdentity>, subxt::Error> {
+    let subtensor_module = api::storage().subtensor_module();
+    // The netuid is hashed with a concat hasher, so it ends the key as is
+    let key = trailing_netuid;
+    let mut identities = BTreeMap::new();
+
+    // Oldest first, so the newest version of each subnet's identity wins
+    let v1 = subtensor_module.subnet_identities_iter();
+    insert_entries(client, at.clone(), v1, key, &mut identities).await?;
+    let v2 = subtensor_module.subnet_identities_v2_iter();
+    insert_entries(client, at.clone(), v2, key, &mut identities).await?;
+    let v3 = subtensor_module.subnet_identities_v3_iter();
+    insert_entries(client, at, v3, key, &mut identities).await?;
+
+    Ok(identities)
+}
+
+/// The text of a registry field, `None` for unset and hashed fields.
+pub fn registry_data_text(data: &RegistryData) -> Option<String> {
+    match data {
+        RegistryData::None
+        | RegistryData::BlakeTwo256(_)
+        | RegistryData::Sha256(_)
+        | RegistryData::Keccak256(_)
+        | RegistryData::ShaThree256(_) => None,
+        // Every remaining variant is `RawN`, which encodes as its index followed by the bytes
+        raw => Some(text(raw.encode()[1..].to_vec())),
+    }
+}
+
+/// An identity registered with the `Registry` pallet, with its fields as text.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RegistryIdentity {
+    pub deposit: u64,
+    pub display: Option<String>,
+    pub legal: Option<String>,
+    pub web: Option<String>,
+    pub riot: Option<String>,
+    pub email: Option<String>,
+    pub pgp_fingerprint: Option<[u8; 20]>,
+    pub image: Option<String>,
+    pub twitter: Option<String>,
+    /// Additional fields, ones whose key or value is hashed are left out.
+    pub additional: Vec<(String, String)>,
+}
+
+pub async fn registry_identity(
+    client: &Subtensor,
+    at: BlockRef,
+    account: &AccountId,
+) -> Result<Option<RegistryIdentity>, subxt::Error> {
+    let registration = client
+        .storage()
+        .at(at)
+        .fetch(&api::storage().registry().identity_of(account))
+        .await?;
+
+    Ok(registration.map(|registration| {
+        let info = registration.info;
+
+        RegistryIdentity {
+            deposit: registration.deposit,
+            display: registry_data_text(&info.display),
+            legal: registry_data_text(&info.legal),
+            web: registry_data_text(&info.web),
+            riot: registry_data_text(&info.riot),
+            email: registry_data_text(&info.email),
+            pgp_fingerprint: info.pgp_fingerprint,
+            image: registry_data_text(&info.image),
+            twitter: registry_data_text(&info.twitter),
+            additional: info
+                .additional
+                .0
+                .iter()
+                .filter_map(|(key, value)| {
+                    Some((registry_data_text(key)?, registry_data_text(value)?))
+                })
+                .collect(),
+        }
+    }))
+}
+
+/// The name `account` goes by in `identities`, or its SS58 address if it has none, for display.
+pub fn display_name(identities: &BTreeMap<AccountId, Identity>, account: &AccountId) -> String {
+    identities
+        .get(account)
+        .map(|identity| identity.name.trim())
+        .filter(|name| !name.is_empty())
+        .map_or_else(|| account.to_string(), str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_identity_lengths_at_the_boundaries() {
+        let identity = Identity {
+            name: "n".repeat(256),
+            url: "u".repeat(256),
+            github_repo: "g".repeat(256),
+            image: "i".repeat(1024),
+            discord: "d".repeat(256),
+            description: "d".repeat(1024),
+            additional: "a".repeat(1024),
+        };
+
+        assert!(identity.validate().is_ok());
+
+        let too_long = Identity {
+            image: "i".repeat(1025),
+            ..identity.clone()
+        };
+
+        assert!(matches!(
+            too_long.validate(),
+            Err(IdentityTooLongError {
+                field: "image",
+                length: 1025,
+                max: 1024,
+            })
+        ));
+
+        // Lengths are counted in bytes, as the pallet does
+        let multibyte = Identity {
+            name: "é".repeat(129),
+            ..identity
+        };
+
+        assert!(matches!(
+            multibyte.validate(),
+            Err(IdentityTooLongError {
+                field: "name",
+                length: 258,
+                max: 256,
+            })
+        ));
+    }
+
+    #[test]
+    fn validates_subnet_identity_lengths_at_the_boundaries() {
+        let identity = SubnetIdentity {
+            subnet_name: "n".repeat(256),
+            github_repo: "g".repeat(1024),
+            subnet_contact: "c".repeat(1024),
+            subnet_url: "u".repeat(1024),
+            discord: "d".repeat(256),
+            description: "d".repeat(1024),
+            logo_url: "l".repeat(1024),
+            additional: "a".repeat(1024),
+        };
+
+        assert!(identity.validate().is_ok());
+
+        let too_long = SubnetIdentity {
+            subnet_name: "n".repeat(257),
+            ..identity.clone()
+        };
+
+        assert!(matches!(
+            too_long.validate(),
+            Err(IdentityTooLongError {
+                field: "subnet_name",
+                length: 257,
+                max: 256,
+            })
+        ));
+
+        let too_long = SubnetIdentity {
+            discord: "d".repeat(257),
+            ..identity
+        };
+
+        assert!(matches!(
+            too_long.validate(),
+            Err(IdentityTooLongError {
+                field: "discord",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn reads_registry_text() {
+        assert_eq!(registry_data_text(&RegistryData::None), None);
+        assert_eq!(registry_data_text(&RegistryData::Sha256([1; 32])), None);
+        assert_eq!(
+            registry_data_text(&RegistryData::BlakeTwo256([1; 32])),
+            None
+        );
+        assert_eq!(
+            registry_data_text(&RegistryData::Raw0([])),
+            Some(String::new())
+        );
+        assert_eq!(
+            registry_data_text(&RegistryData::Raw5(*b"crabs")),
+            Some("crabs".to_string())
+        );
+        assert_eq!(
+            registry_data_text(&RegistryData::Raw64([b'a'; 64])),
+            Some("a".repeat(64))
+        );
+
+        // Invalid UTF-8 is replaced rather than dropped
+        assert_eq!(
+            registry_data_text(&RegistryData::Raw2([b'o', 0xff])),
+            Some("o\u{fffd}".to_string())
+        );
+    }
+}
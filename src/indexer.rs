@@ -0,0 +1,705 @@
+//! Backfills the subtensor and balances history of a range of blocks into a SQLite database.
+//!
+//! Blocks are read from an archive node, usually [`SubtensorUrl::Archive`](crate::subtensor::SubtensorUrl), and
+//! decoded with the metadata of the runtime they were produced by, so fields are looked up by name or position
+//! instead of through the compiled-in types. Each block is written in its own transaction, and indexing a range only
+//! fetches its blocks that are not written yet, which makes indexing resumable.
+//!
+//! Amounts are stored as SQLite integers, so the `tao`, `alpha` and `amount` columns are NULL for amounts above
+//! `i64::MAX`. Balances are `u64` rao, and the total supply of 21 million TAO fits well below that.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::sync::Mutex;
+
+use rusqlite::{params, Connection, OptionalExtension};
+use subxt::backend::legacy::LegacyRpcMethods;
+use subxt::backend::rpc::RpcClient;
+use subxt::client::RuntimeVersion;
+use subxt::events::Phase;
+use subxt::ext::futures::{stream, StreamExt};
+use subxt::ext::scale_value::{Composite, Primitive, Value, ValueDef};
+use thiserror::Error;
+
+use crate::axon::neuron_info_as_addr;
+use crate::subtensor::Subtensor;
+use crate::{AccountId, BlockNumber, Hash, SubtensorConfig};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS blocks (
+    number INTEGER PRIMARY KEY,
+    hash TEXT NOT NULL,
+    spec_version INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS extrinsics (
+    block INTEGER NOT NULL,
+    idx INTEGER NOT NULL,
+    pallet TEXT NOT NULL,
+    call TEXT NOT NULL,
+    signer TEXT,
+    success INTEGER NOT NULL,
+    PRIMARY KEY (block, idx)
+);
+CREATE TABLE IF NOT EXISTS weight_sets (
+    block INTEGER NOT NULL,
+    extrinsic_index INTEGER,
+    netuid INTEGER NOT NULL,
+    uid INTEGER NOT NULL,
+    hotkey TEXT,
+    weights TEXT
+);
+CREATE TABLE IF NOT EXISTS registrations (
+    block INTEGER NOT NULL,
+    extrinsic_index INTEGER,
+    netuid INTEGER NOT NULL,
+    uid INTEGER NOT NULL,
+    hotkey TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS stake_changes (
+    block INTEGER NOT NULL,
+    extrinsic_index INTEGER,
+    kind TEXT NOT NULL,
+    coldkey TEXT,
+    hotkey TEXT NOT NULL,
+    netuid INTEGER,
+    tao INTEGER,
+    alpha INTEGER
+);
+CREATE TABLE IF NOT EXISTS transfers (
+    block INTEGER NOT NULL,
+    extrinsic_index INTEGER,
+    sender TEXT NOT NULL,
+    recipient TEXT NOT NULL,
+    amount INTEGER
+);
+CREATE TABLE IF NOT EXISTS axon_updates (
+    block INTEGER NOT NULL,
+    extrinsic_index INTEGER,
+    netuid INTEGER NOT NULL,
+    hotkey TEXT NOT NULL,
+    address TEXT,
+    protocol INTEGER,
+    version INTEGER
+);
+CREATE INDEX IF NOT EXISTS weight_sets_block ON weight_sets (block);
+CREATE INDEX IF NOT EXISTS registrations_block ON registrations (block);
+CREATE INDEX IF NOT EXISTS stake_changes_block ON stake_changes (block);
+CREATE INDEX IF NOT EXISTS transfers_block ON transfers (block);
+CREATE INDEX IF NOT EXISTS axon_updates_block ON axon_updates (block);
+";
+
+/// Tables holding rows of a single block, cleared before a block is written again.
+const BLOCK_TABLES: [&str; 7] = [
+    "blocks",
+    "extrinsics",
+    "weight_sets",
+    "registrations",
+    "stake_changes",
+    "transfers",
+    "axon_updates",
+];
+
+#[derive(Debug, Error)]
+pub enum IndexerError {
+    #[error(transparent)]
+    Subxt(#[from] subxt::Error),
+
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
+
+    #[error("Block {0} is not known to the node")]
+    MissingBlock(BlockNumber),
+
+    #[error("Block range {start}..={end} is empty")]
+    EmptyRange {
+        start: BlockNumber,
+        end: BlockNumber,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct IndexerOptions {
+    /// How many blocks are fetched at once.
+    pub concurrency: usize,
+    /// Only index subnet rows of this subnet, transfers are always indexed.
+    pub netuid: Option<u16>,
+}
+
+impl Default for IndexerOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 8,
+            netuid: None,
+        }
+    }
+}
+
+/// Builds a client for each runtime version met, since the compiled-in metadata only decodes recent blocks.
+struct Source {
+    rpc: RpcClient,
+    methods: LegacyRpcMethods<SubtensorConfig>,
+    genesis_hash: Hash,
+    clients: Mutex<HashMap<u32, Subtensor>>,
+}
+
+impl Source {
+    async fn client_at(&self, hash: Hash) -> Result<Subtensor, subxt::Error> {
+        let version = self.methods.state_get_runtime_version(Some(hash)).await?;
+
+        if let Some(client) = self.clients.lock().unwrap().get(&version.spec_version) {
+            return Ok(client.clone());
+        }
+
+        let metadata = self.methods.state_get_metadata(Some(hash)).await?;
+
+        let client = Subtensor::from_rpc_client_with(
+            self.genesis_hash,
+            RuntimeVersion {
+                spec_version: version.spec_version,
+                transaction_version: version.transaction_version,
+            },
+            metadata,
+            self.rpc.clone(),
+        )?;
+
+        self.clients
+            .lock()
+            .unwrap()
+            .insert(version.spec_version, client.clone());
+
+        Ok(client)
+    }
+
+    async fn fetch(
+        &self,
+        number: BlockNumber,
+        netuid: Option<u16>,
+    ) -> Result<BlockRows, IndexerError> {
+        let hash = self
+            .methods
+            .chain_get_block_hash(Some(number.into()))
+            .await?
+            .ok_or(IndexerError::MissingBlock(number))?;
+
+        let client = self.client_at(hash).await?;
+        let block = client.blocks().at(hash).await?;
+        let (extrinsics, events) =
+            subxt::ext::futures::try_join!(block.extrinsics(), block.events())?;
+
+        let mut rows = BlockRows {
+            number,
+            hash,
+            spec_version: client.runtime_version().spec_version,
+            ..Default::default()
+        };
+
+        let mut calls = BTreeMap::new();
+
+        for extrinsic in extrinsics.iter() {
+            let signer = extrinsic
+                .address_bytes()
+                .and_then(|bytes| bytes.strip_prefix(&[0]))
+                .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+                .map(AccountId::from);
+
+            rows.extrinsics.push(ExtrinsicRow {
+                index: extrinsic.index(),
+                pallet: extrinsic.pallet_name()?.to_string(),
+                call: extrinsic.variant_name()?.to_string(),
+                signer,
+                success: false,
+            });
+
+            calls.insert(extrinsic.index(), extrinsic.field_values()?);
+        }
+
+        for event in events.iter() {
+            let event = event.map_err(subxt::Error::from)?;
+
+            let extrinsic_index = match event.phase() {
+                Phase::ApplyExtrinsic(index) => Some(index),
+                _ => None,
+            };
+
+            let call = extrinsic_index.and_then(|index| calls.get(&index));
+            let fields = event.field_values().map_err(subxt::Error::from)?;
+
+            match (event.pallet_name(), event.variant_name()) {
+                ("System", "ExtrinsicSuccess") => {
+                    if let Some(row) = rows
+                        .extrinsics
+                        .iter_mut()
+                        .find(|row| Some(row.index) == extrinsic_index)
+                    {
+                        row.success = true;
+                    }
+                }
+                ("Balances", "Transfer") => {
+                    if let (Some(sender), Some(recipient)) = (
+                        field(&fields, "from", 0).and_then(account),
+                        field(&fields, "to", 1).and_then(account),
+                    ) {
+                        rows.transfers.push(TransferRow {
+                            extrinsic_index,
+                            sender,
+                            recipient,
+                            amount: field(&fields, "amount", 2).and_then(Value::as_u128),
+                        });
+                    }
+                }
+                ("SubtensorModule", "WeightsSet") => {
+                    let (Some(netuid), Some(uid)) = (
+                        field(&fields, "", 0).and_then(integer::<u16>),
+                        field(&fields, "", 1).and_then(integer::<u16>),
+                    ) else {
+                        continue;
+                    };
+
+                    rows.weight_sets.push(WeightSetRow {
+                        extrinsic_index,
+                        netuid,
+                        uid,
+                        hotkey: extrinsic_index.and_then(|index| {
+                            rows.extrinsics
+                                .iter()
+                                .find(|row| row.index == index)?
+                                .signer
+                                .clone()
+                        }),
+                        weights: call.and_then(call_weights),
+                    });
+                }
+                ("SubtensorModule", "NeuronRegistered") => {
+                    if let (Some(netuid), Some(uid), Some(hotkey)) = (
+                        field(&fields, "", 0).and_then(integer::<u16>),
+                        field(&fields, "", 1).and_then(integer::<u16>),
+                        field(&fields, "", 2).and_then(account),
+                    ) {
+                        rows.registrations.push(RegistrationRow {
+                            extrinsic_index,
+                            netuid,
+                            uid,
+                            hotkey,
+                        });
+                    }
+                }
+                ("SubtensorModule", variant @ ("StakeAdded" | "StakeRemoved")) => {
+                    let kind = if variant == "StakeAdded" {
+                        "add"
+                    } else {
+                        "remove"
+                    };
+
+                    if let Some(row) = stake_change(kind, extrinsic_index, &fields) {
+                        rows.stake_changes.push(row);
+                    }
+                }
+                ("SubtensorModule", "AxonServed") => {
+                    let (Some(netuid), Some(hotkey)) = (
+                        field(&fields, "", 0).and_then(integer::<u16>),
+                        field(&fields, "", 1).and_then(account),
+                    ) else {
+                        continue;
+                    };
+
+                    let call_field = |name| call.and_then(|call| field(call, name, usize::MAX));
+                    let address = match (
+                        call_field("ip").and_then(Value::as_u128),
+                        call_field("port").and_then(integer::<u16>),
+                        call_field("ip_type").and_then(integer::<u8>),
+                    ) {
+                        (Some(ip), Some(port), Some(ip_type)) => {
+                            Some(neuron_info_as_addr(ip, port, ip_type).to_string())
+                        }
+                        _ => None,
+                    };
+
+                    rows.axon_updates.push(AxonUpdateRow {
+                        extrinsic_index,
+                        netuid,
+                        hotkey,
+                        address,
+                        protocol: call_field("protocol").and_then(integer::<u8>),
+                        version: call_field("version").and_then(integer::<u32>),
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(netuid) = netuid {
+            rows.weight_sets.retain(|row| row.netuid == netuid);
+            rows.registrations.retain(|row| row.netuid == netuid);
+            rows.stake_changes
+                .retain(|row| row.netuid.is_none_or(|row_netuid| row_netuid == netuid));
+            rows.axon_updates.retain(|row| row.netuid == netuid);
+        }
+
+        Ok(rows)
+    }
+}
+
+/// A named field, or the field at `index` when the composite is unnamed.
+fn field<'a>(fields: &'a Composite<u32>, name: &str, index: usize) -> Option<&'a Value<u32>> {
+    match fields {
+        Composite::Named(values) => values
+            .iter()
+            .find(|(field_name, _)| field_name == name)
+            .map(|(_, value)| value),
+        Composite::Unnamed(values) => values.get(index),
+    }
+}
+
+fn integer<T: TryFrom<u128>>(value: &Value<u32>) -> Option<T> {
+    T::try_from(value.as_u128()?).ok()
+}
+
+/// Collects the bytes of a value such as an `AccountId32`, which decodes as nested composites of `u8`s.
+fn bytes(value: &Value<u32>, output: &mut Vec<u8>) -> Option<()> {
+    match &value.value {
+        ValueDef::Composite(composite) => composite
+            .values()
+            .try_for_each(|value| bytes(value, output)),
+        ValueDef::Primitive(Primitive::U128(byte)) => {
+            output.push(u8::try_from(*byte).ok()?);
+            Some(())
+        }
+        _ => None,
+    }
+}
+
+fn account(value: &Value<u32>) -> Option<AccountId> {
+    let mut output = Vec::with_capacity(32);
+    bytes(value, &mut output)?;
+
+    <[u8; 32]>::try_from(output).ok().map(AccountId::from)
+}
+
+/// The weights passed to `set_weights` or `reveal_weights`, as pairs of uid and weight.
+fn call_weights(call: &Composite<u32>) -> Option<Vec<(u16, u16)>> {
+    let (uids, weights) = field(call, "dests", usize::MAX)
+        .zip(field(call, "weights", usize::MAX))
+        .or_else(|| field(call, "uids", usize::MAX).zip(field(call, "values", usize::MAX)))?;
+
+    let list = |value: &Value<u32>| match &value.value {
+        ValueDef::Composite(composite) => composite.values().map(integer::<u16>).collect(),
+        _ => None,
+    };
+
+    let uids: Vec<u16> = list(uids)?;
+    let weights: Vec<u16> = list(weights)?;
+
+    Some(uids.into_iter().zip(weights).collect())
+}
+
+/// Reads stake events across runtime versions, from `(hotkey, amount)` to
+/// `(coldkey, hotkey, tao, alpha, netuid, fee)`.
+fn stake_change(
+    kind: &'static str,
+    extrinsic_index: Option<u32>,
+    fields: &Composite<u32>,
+) -> Option<StakeChangeRow> {
+    let values: Vec<&Value<u32>> = fields.values().collect();
+
+    let row = if values.len() < 5 {
+        StakeChangeRow {
+            extrinsic_index,
+            kind,
+            coldkey: None,
+            hotkey: account(values.first()?)?,
+            netuid: None,
+            tao: values.get(1).and_then(|value| value.as_u128()),
+            alpha: None,
+        }
+    } else {
+        StakeChangeRow {
+            extrinsic_index,
+            kind,
+            coldkey: account(values[0]),
+            hotkey: account(values[1])?,
+            netuid: integer(values[4]),
+            tao: values[2].as_u128(),
+            alpha: values[3].as_u128(),
+        }
+    };
+
+    Some(row)
+}
+
+#[derive(Debug)]
+struct ExtrinsicRow {
+    index: u32,
+    pallet: String,
+    call: String,
+    signer: Option<AccountId>,
+    success: bool,
+}
+
+#[derive(Debug)]
+struct WeightSetRow {
+    extrinsic_index: Option<u32>,
+    netuid: u16,
+    uid: u16,
+    hotkey: Option<AccountId>,
+    weights: Option<Vec<(u16, u16)>>,
+}
+
+#[derive(Debug)]
+struct RegistrationRow {
+    extrinsic_index: Option<u32>,
+    netuid: u16,
+    uid: u16,
+    hotkey: AccountId,
+}
+
+#[derive(Debug)]
+struct StakeChangeRow {
+    extrinsic_index: Option<u32>,
+    kind: &'static str,
+    coldkey: Option<AccountId>,
+    hotkey: AccountId,
+    netuid: Option<u16>,
+    tao: Option<u128>,
+    alpha: Option<u128>,
+}
+
+#[derive(Debug)]
+struct TransferRow {
+    extrinsic_index: Option<u32>,
+    sender: AccountId,
+    recipient: AccountId,
+    amount: Option<u128>,
+}
+
+#[derive(Debug)]
+struct AxonUpdateRow {
+    extrinsic_index: Option<u32>,
+    netuid: u16,
+    hotkey: AccountId,
+    address: Option<String>,
+    protocol: Option<u8>,
+    version: Option<u32>,
+}
+
+#[derive(Debug, Default)]
+struct BlockRows {
+    number: BlockNumber,
+    hash: Hash,
+    spec_version: u32,
+    extrinsics: Vec<ExtrinsicRow>,
+    weight_sets: Vec<WeightSetRow>,
+    registrations: Vec<RegistrationRow>,
+    stake_changes: Vec<StakeChangeRow>,
+    transfers: Vec<TransferRow>,
+    axon_updates: Vec<AxonUpdateRow>,
+}
+
+/// Amounts are stored as integers, which SQLite limits to 64 signed bits, leaving larger amounts NULL.
+fn amount(value: Option<u128>) -> Option<i64> {
+    value.and_then(|value| i64::try_from(value).ok())
+}
+
+fn ss58(account: &Option<AccountId>) -> Option<String> {
+    account.as_ref().map(AccountId::to_string)
+}
+
+impl BlockRows {
+    fn write(&self, connection: &mut Connection) -> Result<(), rusqlite::Error> {
+        let transaction = connection.transaction()?;
+        let block = self.number;
+
+        for table in BLOCK_TABLES {
+            let column = if table == "blocks" { "number" } else { "block" };
+
+            transaction.execute(&format!("DELETE FROM {table} WHERE {column} = ?1"), [block])?;
+        }
+
+        transaction.execute(
+            "INSERT INTO blocks (number, hash, spec_version) VALUES (?1, ?2, ?3)",
+            params![block, format!("{:?}", self.hash), self.spec_version],
+        )?;
+
+        for row in &self.extrinsics {
+            transaction.execute(
+                "INSERT INTO extrinsics (block, idx, pallet, call, signer, success) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![block, row.index, row.pallet, row.call, ss58(&row.signer), row.success],
+            )?;
+        }
+
+        for row in &self.weight_sets {
+            let weights = row
+                .weights
+                .as_ref()
+                .map(|weights| serde_json::to_string(weights).expect("weights serialize to JSON"));
+
+            transaction.execute(
+                "INSERT INTO weight_sets (block, extrinsic_index, netuid, uid, hotkey, weights) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    block,
+                    row.extrinsic_index,
+                    row.netuid,
+                    row.uid,
+                    ss58(&row.hotkey),
+                    weights
+                ],
+            )?;
+        }
+
+        for row in &self.registrations {
+            transaction.execute(
+                "INSERT INTO registrations (block, extrinsic_index, netuid, uid, hotkey) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![block, row.extrinsic_index, row.netuid, row.uid, row.hotkey.to_string()],
+            )?;
+        }
+
+        for row in &self.stake_changes {
+            transaction.execute(
+                "INSERT INTO stake_changes (block, extrinsic_index, kind, coldkey, hotkey, netuid, tao, alpha) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    block,
+                    row.extrinsic_index,
+                    row.kind,
+                    ss58(&row.coldkey),
+                    row.hotkey.to_string(),
+                    row.netuid,
+                    amount(row.tao),
+                    amount(row.alpha),
+                ],
+            )?;
+        }
+
+        for row in &self.transfers {
+            transaction.execute(
+                "INSERT INTO transfers (block, extrinsic_index, sender, recipient, amount) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    block,
+                    row.extrinsic_index,
+                    row.sender.to_string(),
+                    row.recipient.to_string(),
+                    amount(row.amount),
+                ],
+            )?;
+        }
+
+        for row in &self.axon_updates {
+            transaction.execute(
+                "INSERT INTO axon_updates (block, extrinsic_index, netuid, hotkey, address, protocol, version) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    block,
+                    row.extrinsic_index,
+                    row.netuid,
+                    row.hotkey.to_string(),
+                    row.address,
+                    row.protocol,
+                    row.version,
+                ],
+            )?;
+        }
+
+        transaction.commit()
+    }
+}
+
+pub struct Indexer {
+    source: Source,
+    connection: Connection,
+    options: IndexerOptions,
+}
+
+impl Indexer {
+    /// Connects to the node at `url` and opens or creates the database at `database`.
+    pub async fn connect(
+        url: impl AsRef<str>,
+        database: impl AsRef<Path>,
+        options: IndexerOptions,
+    ) -> Result<Self, IndexerError> {
+        let rpc = RpcClient::from_url(url).await?;
+        let connection = Connection::open(database)?;
+
+        Self::new(rpc, connection, options).await
+    }
+
+    pub async fn new(
+        rpc: RpcClient,
+        connection: Connection,
+        options: IndexerOptions,
+    ) -> Result<Self, IndexerError> {
+        connection.execute_batch(SCHEMA)?;
+
+        let methods = LegacyRpcMethods::new(rpc.clone());
+        let genesis_hash = methods.genesis_hash().await?;
+
+        Ok(Self {
+            source: Source {
+                rpc,
+                methods,
+                genesis_hash,
+                clients: Mutex::default(),
+            },
+            connection,
+            options,
+        })
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    /// The highest block written. Ranges can be indexed in any order, so blocks below it are not necessarily written.
+    pub fn last_indexed_block(&self) -> Result<Option<BlockNumber>, rusqlite::Error> {
+        self.connection
+            .query_row("SELECT MAX(number) FROM blocks", [], |row| row.get(0))
+            .optional()
+            .map(Option::flatten)
+    }
+
+    /// The blocks of `range` already written.
+    pub fn indexed_blocks(
+        &self,
+        range: &RangeInclusive<BlockNumber>,
+    ) -> Result<HashSet<BlockNumber>, rusqlite::Error> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT number FROM blocks WHERE number BETWEEN ?1 AND ?2")?;
+
+        let numbers = statement.query_map(params![range.start(), range.end()], |row| row.get(0))?;
+
+        numbers.collect()
+    }
+
+    /// Indexes the blocks of `range` that are not written yet, returning how many were written.
+    pub async fn index(
+        &mut self,
+        range: RangeInclusive<BlockNumber>,
+    ) -> Result<usize, IndexerError> {
+        if range.is_empty() {
+            return Err(IndexerError::EmptyRange {
+                start: *range.start(),
+                end: *range.end(),
+            });
+        }
+
+        let indexed = self.indexed_blocks(&range)?;
+        let missing: Vec<BlockNumber> = range.filter(|number| !indexed.contains(number)).collect();
+
+        let source = &self.source;
+        let netuid = self.options.netuid;
+
+        let mut blocks = stream::iter(missing)
+            .map(|number| source.fetch(number, netuid))
+            .buffered(self.options.concurrency.max(1));
+
+        let mut written = 0;
+
+        while let Some(rows) = blocks.next().await {
+            rows?.write(&mut self.connection)?;
+            written += 1;
+        }
+
+        Ok(written)
+    }
+}
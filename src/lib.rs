@@ -3,12 +3,39 @@ use subxt::{Config, SubstrateConfig};
 use crate::subtensor::Subtensor;
 
 pub mod axon;
+pub mod cache;
+pub mod children;
+pub mod client;
+pub mod commitments;
+pub mod copying;
+pub mod drand;
+pub mod dynamic;
+pub mod events;
+pub mod hyperparameters;
+pub mod identity;
+#[cfg(feature = "indexer")]
+pub mod indexer;
+pub mod matrix;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod network;
+pub mod probe;
+pub mod registration;
+pub mod schedule;
 pub mod sign;
 pub mod subtensor;
+pub mod swap;
+#[cfg(feature = "drand")]
+pub mod timelock;
+#[cfg(feature = "tls")]
+pub mod tls;
+pub mod upgrade;
 pub mod wallet;
 pub mod weights;
+pub mod yuma;
 
-// `api` module from generated metadata.rs (see build.rs for more info)
+// `api` module from generated metadata.rs, along with the `METADATA_NETWORK`, `METADATA_SPEC_VERSION` and generated
+// validation hashes of the snapshot selected by cargo feature (see build.rs for more info)
 include!(concat!(env!("OUT_DIR"), "/metadata.rs"));
 
 pub type SubtensorConfig = SubstrateConfig;
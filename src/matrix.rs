@@ -0,0 +1,344 @@
+use std::fmt::Display;
+use std::io::{self, Write};
+
+use subxt::ext::futures::{stream, StreamExt, TryStreamExt};
+use subxt::storage::Address;
+use subxt::utils::Yes;
+
+use crate::api;
+use crate::subtensor::Subtensor;
+use crate::BlockRef;
+
+/// How many rows are fetched at once when reading a matrix.
+pub const ROW_CONCURRENCY: usize = 32;
+
+/// A square matrix indexed by uid, storing each row as pairs of column uid and value like the `Weights` and `Bonds`
+/// storage maps.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SparseMatrix<T = u16> {
+    rows: Vec<Vec<(u16, T)>>,
+}
+
+impl<T: Copy> SparseMatrix<T> {
+    pub fn new(rows: Vec<Vec<(u16, T)>>) -> Self {
+        Self { rows }
+    }
+
+    /// The number of rows, which is the number of uids in the subnet.
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    pub fn rows(&self) -> &[Vec<(u16, T)>] {
+        &self.rows
+    }
+
+    pub fn into_rows(self) -> Vec<Vec<(u16, T)>> {
+        self.rows
+    }
+
+    /// The entries `uid` set, empty if the uid does not exist.
+    pub fn row(&self, uid: u16) -> &[(u16, T)] {
+        self.rows.get(uid as usize).map_or(&[], Vec::as_slice)
+    }
+
+    /// The entries set for `uid`, as pairs of row uid and value.
+    pub fn column(&self, uid: u16) -> Vec<(u16, T)> {
+        self.iter()
+            .filter(|&(_, column, _)| column == uid)
+            .map(|(row, _, value)| (row, value))
+            .collect()
+    }
+
+    pub fn get(&self, row: u16, column: u16) -> Option<T> {
+        self.row(row)
+            .iter()
+            .find(|&&(uid, _)| uid == column)
+            .map(|&(_, value)| value)
+    }
+
+    /// Every entry as row uid, column uid and value.
+    pub fn iter(&self) -> impl Iterator<Item = (u16, u16, T)> + '_ {
+        self.rows.iter().enumerate().flat_map(|(row, entries)| {
+            entries
+                .iter()
+                .map(move |&(column, value)| (row as u16, column, value))
+        })
+    }
+
+    pub fn map<U: Copy>(&self, f: impl Fn(T) -> U) -> SparseMatrix<U> {
+        SparseMatrix::new(
+            self.rows
+                .iter()
+                .map(|row| row.iter().map(|&(uid, value)| (uid, f(value))).collect())
+                .collect(),
+        )
+    }
+}
+
+impl<T: Copy + Into<f64>> SparseMatrix<T> {
+    /// Scales each row to sum to 1, leaving rows that sum to 0 as is.
+    pub fn normalize_rows(&self) -> SparseMatrix<f64> {
+        SparseMatrix::new(
+            self.rows
+                .iter()
+                .map(|row| {
+                    let sum: f64 = row.iter().map(|&(_, value)| value.into()).sum();
+
+                    row.iter()
+                        .map(|&(uid, value)| (uid, normalize(value.into(), sum)))
+                        .collect()
+                })
+                .collect(),
+        )
+    }
+
+    /// Scales each column to sum to 1, leaving columns that sum to 0 as is.
+    pub fn normalize_columns(&self) -> SparseMatrix<f64> {
+        let mut sums = vec![0.0; self.len()];
+
+        for (_, column, value) in self.iter() {
+            if let Some(sum) = sums.get_mut(column as usize) {
+                *sum += value.into();
+            }
+        }
+
+        SparseMatrix::new(
+            self.rows
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|&(uid, value)| {
+                            let sum = sums.get(uid as usize).copied().unwrap_or(0.0);
+
+                            (uid, normalize(value.into(), sum))
+                        })
+                        .collect()
+                })
+                .collect(),
+        )
+    }
+}
+
+fn normalize(value: f64, sum: f64) -> f64 {
+    if sum == 0.0 {
+        value
+    } else {
+        value / sum
+    }
+}
+
+impl<T: Copy + Display> SparseMatrix<T> {
+    /// Writes the entries as `row,column,value` lines, with a header.
+    pub fn write_csv(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "row,column,value")?;
+
+        for (row, column, value) in self.iter() {
+            writeln!(writer, "{row},{column},{value}")?;
+        }
+
+        writer.flush()
+    }
+}
+
+#[cfg(feature = "arrow")]
+mod arrow {
+    use std::io::Write;
+    use std::sync::Arc;
+
+    use arrow_array::{ArrayRef, Float64Array, RecordBatch, UInt16Array};
+    use arrow_ipc::writer::FileWriter;
+    use arrow_schema::{ArrowError, DataType, Field, Schema};
+
+    use super::SparseMatrix;
+
+    pub trait ArrowValue: Copy {
+        const DATA_TYPE: DataType;
+
+        fn array(values: Vec<Self>) -> ArrayRef;
+    }
+
+    impl ArrowValue for u16 {
+        const DATA_TYPE: DataType = DataType::UInt16;
+
+        fn array(values: Vec<Self>) -> ArrayRef {
+            Arc::new(UInt16Array::from(values))
+        }
+    }
+
+    impl ArrowValue for f64 {
+        const DATA_TYPE: DataType = DataType::Float64;
+
+        fn array(values: Vec<Self>) -> ArrayRef {
+            Arc::new(Float64Array::from(values))
+        }
+    }
+
+    impl<T: ArrowValue> SparseMatrix<T> {
+        /// The entries as a batch with `row`, `column` and `value` columns.
+        pub fn to_record_batch(&self) -> Result<RecordBatch, ArrowError> {
+            let schema = Schema::new(vec![
+                Field::new("row", DataType::UInt16, false),
+                Field::new("column", DataType::UInt16, false),
+                Field::new("value", T::DATA_TYPE, false),
+            ]);
+
+            let mut rows = Vec::new();
+            let mut columns = Vec::new();
+            let mut values = Vec::new();
+
+            for (row, column, value) in self.iter() {
+                rows.push(row);
+                columns.push(column);
+                values.push(value);
+            }
+
+            RecordBatch::try_new(
+                Arc::new(schema),
+                vec![
+                    Arc::new(UInt16Array::from(rows)),
+                    Arc::new(UInt16Array::from(columns)),
+                    T::array(values),
+                ],
+            )
+        }
+
+        /// Writes the entries in the Arrow IPC file format.
+        pub fn write_arrow(&self, writer: impl Write) -> Result<(), ArrowError> {
+            let batch = self.to_record_batch()?;
+            let mut writer = FileWriter::try_new(writer, &batch.schema())?;
+
+            writer.write(&batch)?;
+            writer.finish()
+        }
+    }
+}
+
+#[cfg(feature = "arrow")]
+pub use arrow::ArrowValue;
+
+/// Fetches the row of every uid in the subnet, [`ROW_CONCURRENCY`] at a time.
+async fn fetch_rows<Addr>(
+    client: &Subtensor,
+    at: BlockRef,
+    netuid: u16,
+    address: impl Fn(u16) -> Addr,
+) -> Result<SparseMatrix, subxt::Error>
+where
+    Addr: Address<IsFetchable = Yes, IsDefaultable = Yes, Target = Vec<(u16, u16)>>,
+{
+    let storage = client.storage().at(at);
+
+    let n = storage
+        .fetch_or_default(&api::storage().subtensor_module().subnetwork_n(netuid))
+        .await?;
+
+    let storage = &storage;
+    let address = &address;
+
+    let rows = stream::iter(0..n)
+        .map(|uid| async move { storage.fetch_or_default(&address(uid)).await })
+        .buffered(ROW_CONCURRENCY)
+        .try_collect()
+        .await?;
+
+    Ok(SparseMatrix::new(rows))
+}
+
+/// The weights each uid of `netuid` set, as stored in `Weights`.
+pub async fn weights(
+    client: &Subtensor,
+    at: BlockRef,
+    netuid: u16,
+) -> Result<SparseMatrix, subxt::Error> {
+    fetch_rows(client, at, netuid, |uid| {
+        api::storage().subtensor_module().weights(netuid, uid)
+    })
+    .await
+}
+
+/// The bonds of each uid of `netuid`, as stored in `Bonds`.
+pub async fn bonds(
+    client: &Subtensor,
+    at: BlockRef,
+    netuid: u16,
+) -> Result<SparseMatrix, subxt::Error> {
+    fetch_rows(client, at, netuid, |uid| {
+        api::storage().subtensor_module().bonds(netuid, uid)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Uid 0 sets weights on 1 and 2, uid 1 sets none, and uid 2 sets a zero weight on 0.
+    fn matrix() -> SparseMatrix {
+        SparseMatrix::new(vec![vec![(1, 1), (2, 3)], vec![], vec![(0, 0), (2, 1)]])
+    }
+
+    #[test]
+    fn looks_up_sparse_entries() {
+        let matrix = matrix();
+
+        assert_eq!(matrix.len(), 3);
+        assert_eq!(matrix.row(0), &[(1, 1), (2, 3)]);
+        assert!(matrix.row(1).is_empty());
+        assert!(matrix.row(7).is_empty());
+        assert_eq!(matrix.column(2), vec![(0, 3), (2, 1)]);
+        assert_eq!(matrix.column(0), vec![(2, 0)]);
+        assert_eq!(matrix.column(1), vec![(0, 1)]);
+        assert!(matrix.column(3).is_empty());
+        assert_eq!(matrix.get(0, 2), Some(3));
+        assert_eq!(matrix.get(0, 0), None);
+        assert_eq!(matrix.get(9, 0), None);
+    }
+
+    #[test]
+    fn normalizes_rows_leaving_zero_rows_as_is() {
+        let matrix = SparseMatrix::new(vec![vec![(1, 1), (2, 3)], vec![], vec![(0, 0)]]);
+
+        assert_eq!(
+            matrix.normalize_rows(),
+            SparseMatrix::new(vec![vec![(1, 0.25), (2, 0.75)], vec![], vec![(0, 0.0)]])
+        );
+    }
+
+    #[test]
+    fn normalizes_columns_leaving_zero_columns_as_is() {
+        // Column 0 sums to 0, column 1 has no entries, and column 5 lies outside the matrix
+        let matrix = SparseMatrix::new(vec![vec![(2, 1), (5, 4)], vec![(2, 3)], vec![(0, 0)]]);
+
+        assert_eq!(
+            matrix.normalize_columns(),
+            SparseMatrix::new(vec![
+                vec![(2, 0.25), (5, 4.0)],
+                vec![(2, 0.75)],
+                vec![(0, 0.0)],
+            ])
+        );
+    }
+
+    #[test]
+    fn writes_entries_as_csv() {
+        let mut csv = Vec::new();
+
+        matrix().write_csv(&mut csv).unwrap();
+
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "row,column,value\n0,1,1\n0,2,3\n2,0,0\n2,2,1\n"
+        );
+
+        let mut csv = Vec::new();
+
+        SparseMatrix::<u16>::default().write_csv(&mut csv).unwrap();
+
+        assert_eq!(String::from_utf8(csv).unwrap(), "row,column,value\n");
+    }
+}
@@ -0,0 +1,571 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use serde_json::value::{to_raw_value, RawValue};
+use serde_json::{json, Value};
+use sp_core::hashing::{blake2_256, twox_128};
+use subxt::backend::rpc::{RawRpcFuture, RawRpcSubscription, RpcClient, RpcClientT};
+use subxt::blocks::StaticExtrinsic;
+use subxt::client::RuntimeVersion;
+use subxt::config::substrate::Digest;
+use subxt::config::Header as _;
+use subxt::error::RpcError;
+use subxt::ext::codec::{self, Compact, Encode};
+use subxt::ext::futures::{stream, StreamExt};
+use subxt::ext::subxt_core;
+use subxt::runtime_api::Payload;
+use subxt::storage::Address;
+use subxt::Metadata;
+use thiserror::Error;
+use tokio::sync::broadcast;
+
+use crate::subtensor::Subtensor;
+use crate::{BlockNumber, Hash, Header, SubtensorConfig};
+
+type Storage = BTreeMap<Vec<u8>, Vec<u8>>;
+
+/// A storage address, runtime API call or extrinsic that does not match the mock's metadata.
+#[derive(Debug, Error)]
+#[error(transparent)]
+pub struct MetadataMismatchError(Box<subxt_core::Error>);
+
+impl From<subxt_core::Error> for MetadataMismatchError {
+    fn from(error: subxt_core::Error) -> Self {
+        Self(Box::new(error))
+    }
+}
+
+struct MockBlock {
+    hash: Hash,
+    header: Header,
+    extrinsics: Vec<Vec<u8>>,
+    storage: Arc<Storage>,
+}
+
+struct State {
+    metadata: Metadata,
+    metadata_bytes: Vec<u8>,
+    runtime_version: RuntimeVersion,
+    blocks: Vec<MockBlock>,
+    runtime_apis: HashMap<(String, Vec<u8>), Vec<u8>>,
+    submitted: Vec<Vec<u8>>,
+}
+
+struct Inner {
+    state: Mutex<State>,
+    heads: broadcast::Sender<Header>,
+    versions: broadcast::Sender<RuntimeVersion>,
+}
+
+/// An in-process subtensor node serving canned metadata, storage and runtime API responses over subxt's RPC client
+/// trait, so code taking a [`Subtensor`] can be tested without a running node.
+///
+/// Storage is kept per block, values set with [`MockSubtensor::set_storage`] apply to the latest block and are
+/// inherited by later ones. Submitted extrinsics are recorded and each is sealed into a new block, which is
+/// reported as both best and finalized.
+#[derive(Clone)]
+pub struct MockSubtensor {
+    inner: Arc<Inner>,
+}
+
+impl MockSubtensor {
+    /// Starts a chain at a genesis block, from SCALE encoded metadata such as `subxt metadata` outputs.
+    pub fn new(metadata: &[u8]) -> Result<Self, codec::Error> {
+        let decoded = subxt_core::metadata::decode_from(metadata)?;
+
+        let header = Header {
+            parent_hash: Hash::zero(),
+            number: 0,
+            state_root: Hash::zero(),
+            extrinsics_root: Hash::zero(),
+            digest: Digest::default(),
+        };
+
+        let (heads, _) = broadcast::channel(64);
+        let (versions, _) = broadcast::channel(16);
+
+        Ok(Self {
+            inner: Arc::new(Inner {
+                state: Mutex::new(State {
+                    metadata: decoded,
+                    metadata_bytes: metadata.to_vec(),
+                    runtime_version: RuntimeVersion {
+                        spec_version: 0,
+                        transaction_version: 0,
+                    },
+                    blocks: vec![MockBlock {
+                        hash: header.hash(),
+                        header,
+                        extrinsics: Vec::new(),
+                        storage: Arc::default(),
+                    }],
+                    runtime_apis: HashMap::new(),
+                    submitted: Vec::new(),
+                }),
+                heads,
+                versions,
+            }),
+        })
+    }
+
+    /// A client backed by this mock. Clients share the mock's state.
+    pub fn client(&self) -> Subtensor {
+        let state = self.inner.state.lock().unwrap();
+
+        Subtensor::from_rpc_client_with(
+            state.blocks[0].hash,
+            state.runtime_version,
+            state.metadata.clone(),
+            RpcClient::new(self.clone()),
+        )
+        .expect("Creating a client from known parts can not fail")
+    }
+
+    pub fn metadata(&self) -> Metadata {
+        self.inner.state.lock().unwrap().metadata.clone()
+    }
+
+    /// Replaces the metadata served to clients, which take it up on their next runtime upgrade.
+    pub fn set_metadata(&self, metadata: &[u8]) -> Result<(), codec::Error> {
+        let decoded = subxt_core::metadata::decode_from(metadata)?;
+
+        let mut state = self.inner.state.lock().unwrap();
+        state.metadata = decoded;
+        state.metadata_bytes = metadata.to_vec();
+
+        Ok(())
+    }
+
+    /// Upgrades the runtime version as a node does, recording it in `System::LastRuntimeUpgrade` and announcing it to
+    /// runtime version subscribers. Clients created afterwards sign transactions with it.
+    pub fn set_runtime_version(&self, spec_version: u32, transaction_version: u32) {
+        let version = RuntimeVersion {
+            spec_version,
+            transaction_version,
+        };
+
+        let mut key = twox_128(b"System").to_vec();
+        key.extend(twox_128(b"LastRuntimeUpgrade"));
+
+        // `LastRuntimeUpgradeInfo` is a compact spec version followed by the spec name
+        self.set_raw_storage(key, (Compact(spec_version), "node-subtensor").encode());
+        self.inner.state.lock().unwrap().runtime_version = version;
+
+        let _ = self.inner.versions.send(version);
+    }
+
+    /// Sets the value at `address` from the latest block on.
+    pub fn set_storage<Addr: Address>(
+        &self,
+        address: &Addr,
+        value: &impl Encode,
+    ) -> Result<(), MetadataMismatchError> {
+        let key = subxt_core::storage::get_address_bytes(address, &self.metadata())?;
+
+        self.set_raw_storage(key, value.encode());
+
+        Ok(())
+    }
+
+    pub fn remove_storage<Addr: Address>(
+        &self,
+        address: &Addr,
+    ) -> Result<(), MetadataMismatchError> {
+        let key = subxt_core::storage::get_address_bytes(address, &self.metadata())?;
+
+        let mut state = self.inner.state.lock().unwrap();
+        let block = state.blocks.last_mut().unwrap();
+        Arc::make_mut(&mut block.storage).remove(&key);
+
+        Ok(())
+    }
+
+    pub fn set_raw_storage(&self, key: Vec<u8>, value: Vec<u8>) {
+        let mut state = self.inner.state.lock().unwrap();
+        let block = state.blocks.last_mut().unwrap();
+        Arc::make_mut(&mut block.storage).insert(key, value);
+    }
+
+    /// Sets the response to a runtime API call, matching on the call's arguments.
+    pub fn set_runtime_api<P: Payload>(
+        &self,
+        payload: &P,
+        response: &P::ReturnType,
+    ) -> Result<(), MetadataMismatchError>
+    where
+        P::ReturnType: Encode,
+    {
+        let method = format!("{}_{}", payload.trait_name(), payload.method_name());
+        let args = payload.encode_args(&self.metadata())?;
+
+        self.inner
+            .state
+            .lock()
+            .unwrap()
+            .runtime_apis
+            .insert((method, args), response.encode());
+
+        Ok(())
+    }
+
+    /// Seals a new block on top of the latest one, returning its hash.
+    pub fn produce_block(&self) -> Hash {
+        self.seal(Vec::new())
+    }
+
+    /// Produces blocks until the latest block is `number`.
+    pub fn produce_blocks_until(&self, number: BlockNumber) {
+        while self.block_number() < number {
+            self.produce_block();
+        }
+    }
+
+    pub fn block_number(&self) -> BlockNumber {
+        self.inner.state.lock().unwrap().blocks.len() as BlockNumber - 1
+    }
+
+    pub fn latest_hash(&self) -> Hash {
+        self.inner.state.lock().unwrap().blocks.last().unwrap().hash
+    }
+
+    /// How many head subscriptions are open, so a test can wait for a subscriber before producing the blocks it
+    /// should see.
+    pub fn head_subscribers(&self) -> usize {
+        self.inner.heads.receiver_count()
+    }
+
+    /// Every extrinsic submitted so far, in submission order.
+    pub fn submitted(&self) -> Vec<Vec<u8>> {
+        self.inner.state.lock().unwrap().submitted.clone()
+    }
+
+    /// The decoded calls of every submitted extrinsic calling `E`.
+    pub fn submitted_calls<E: StaticExtrinsic>(&self) -> Result<Vec<E>, MetadataMismatchError> {
+        let extrinsics = subxt_core::blocks::Extrinsics::<SubtensorConfig>::decode_from(
+            self.submitted(),
+            self.metadata(),
+        )?;
+
+        extrinsics
+            .find::<E>()
+            .map(|found| Ok(found?.value))
+            .collect()
+    }
+
+    fn seal(&self, extrinsics: Vec<Vec<u8>>) -> Hash {
+        let mut state = self.inner.state.lock().unwrap();
+        let parent = state.blocks.last().unwrap();
+
+        let header = Header {
+            parent_hash: parent.hash,
+            number: parent.header.number + 1,
+            state_root: Hash::zero(),
+            extrinsics_root: Hash::from(blake2_256(&extrinsics.encode())),
+            digest: Digest::default(),
+        };
+
+        let block = MockBlock {
+            hash: header.hash(),
+            header: header.clone(),
+            extrinsics,
+            storage: parent.storage.clone(),
+        };
+
+        let hash = block.hash;
+        state.blocks.push(block);
+
+        let _ = self.inner.heads.send(header);
+
+        hash
+    }
+
+    fn submit(&self, params: &[Value]) -> Result<(Hash, Hash), RpcError> {
+        let extrinsic = bytes_param(params, 0)?;
+        let extrinsic_hash = Hash::from(blake2_256(&extrinsic));
+
+        self.inner
+            .state
+            .lock()
+            .unwrap()
+            .submitted
+            .push(extrinsic.clone());
+
+        Ok((extrinsic_hash, self.seal(vec![extrinsic])))
+    }
+
+    fn handle(&self, method: &str, params: &[Value]) -> Result<Value, RpcError> {
+        // Submission seals a block, so it must not hold the state lock
+        if method == "author_submitExtrinsic" {
+            let (extrinsic_hash, _) = self.submit(params)?;
+
+            return Ok(json!(extrinsic_hash));
+        }
+
+        let state = self.inner.state.lock().unwrap();
+
+        match method {
+            "state_getMetadata" => Ok(hex_value(&state.metadata_bytes)),
+            "chain_getBlockHash" => {
+                let block = match params.first() {
+                    None | Some(Value::Null) => state.blocks.last(),
+                    Some(number) => number_param(number).and_then(|n| state.blocks.get(n)),
+                };
+
+                Ok(json!(block.map(|block| block.hash)))
+            }
+            "chain_getFinalizedHead" => Ok(json!(state.blocks.last().unwrap().hash)),
+            "chain_getHeader" => {
+                let block = state.block(params, 0)?;
+
+                Ok(json!(block.map(|block| &block.header)))
+            }
+            "chain_getBlock" => {
+                let block = state.block(params, 0)?;
+
+                Ok(json!(block.map(|block| json!({
+                    "block": {
+                        "header": block.header,
+                        "extrinsics": block.extrinsics.iter().map(|e| hex_value(e)).collect::<Vec<_>>(),
+                    },
+                    "justifications": null,
+                }))))
+            }
+            "state_getRuntimeVersion" => Ok(runtime_version_value(&state.runtime_version)),
+            "state_getStorage" => {
+                let key = bytes_param(params, 0)?;
+                let block = state.existing_block(params, 1)?;
+
+                Ok(json!(block.storage.get(&key).map(|value| hex_value(value))))
+            }
+            "state_getKeysPaged" => {
+                let prefix = bytes_param(params, 0)?;
+                let count = params.get(1).and_then(number_param).unwrap_or(usize::MAX);
+                let start = match params.get(2) {
+                    None | Some(Value::Null) => None,
+                    Some(_) => Some(bytes_param(params, 2)?),
+                };
+                let block = state.existing_block(params, 3)?;
+
+                let keys: Vec<Value> = block
+                    .storage
+                    .keys()
+                    .filter(|key| key.starts_with(&prefix))
+                    .filter(|key| start.as_ref().is_none_or(|start| *key > start))
+                    .take(count)
+                    .map(|key| hex_value(key))
+                    .collect();
+
+                Ok(json!(keys))
+            }
+            "state_queryStorageAt" => {
+                let keys: Vec<Value> = params
+                    .first()
+                    .and_then(Value::as_array)
+                    .cloned()
+                    .unwrap_or_default();
+                let block = state.existing_block(params, 1)?;
+
+                let mut changes = Vec::new();
+
+                for index in 0..keys.len() {
+                    let key = bytes_param(&keys, index)?;
+                    let value = block.storage.get(&key).map(|value| hex_value(value));
+
+                    changes.push(json!([hex_value(&key), value]));
+                }
+
+                Ok(json!([{ "block": block.hash, "changes": changes }]))
+            }
+            "state_call" => {
+                let name = params.first().and_then(Value::as_str).unwrap_or_default();
+                let args = bytes_param(params, 1)?;
+
+                if let Some(response) = state.runtime_apis.get(&(name.to_string(), args)) {
+                    return Ok(hex_value(response));
+                }
+
+                match name {
+                    "AccountNonceApi_account_nonce" => Ok(hex_value(&0u32.encode())),
+                    "Metadata_metadata_versions" => Ok(hex_value(&vec![14u32, 15].encode())),
+                    "Metadata_metadata_at_version" => {
+                        Ok(hex_value(&Some(state.metadata_bytes.clone()).encode()))
+                    }
+                    _ => Err(RpcError::request_rejected(format!(
+                        "No response set for runtime API {name}"
+                    ))),
+                }
+            }
+            "system_accountNextIndex" => Ok(json!(0)),
+            _ => Err(RpcError::request_rejected(format!(
+                "Mock subtensor does not support {method}"
+            ))),
+        }
+    }
+
+    fn handle_subscription(
+        &self,
+        method: &str,
+        params: &[Value],
+    ) -> Result<RawRpcSubscription, RpcError> {
+        match method {
+            "chain_subscribeNewHeads"
+            | "chain_subscribeAllHeads"
+            | "chain_subscribeFinalizedHeads" => {
+                // Subscribing before reading the current head so no block is missed in between
+                let heads = broadcast_stream(self.inner.heads.subscribe());
+                let current = self
+                    .inner
+                    .state
+                    .lock()
+                    .unwrap()
+                    .blocks
+                    .last()
+                    .unwrap()
+                    .header
+                    .clone();
+
+                Ok(subscription(
+                    stream::once(async move { current })
+                        .chain(heads)
+                        .map(|header| json!(header)),
+                ))
+            }
+            "state_subscribeRuntimeVersion" => {
+                let versions = broadcast_stream(self.inner.versions.subscribe());
+                let current = self.inner.state.lock().unwrap().runtime_version;
+
+                Ok(subscription(
+                    stream::once(async move { current })
+                        .chain(versions)
+                        .map(|version| runtime_version_value(&version)),
+                ))
+            }
+            "author_submitAndWatchExtrinsic" => {
+                let (_, block) = self.submit(params)?;
+
+                let statuses = vec![
+                    json!("ready"),
+                    json!({ "inBlock": block }),
+                    json!({ "finalized": block }),
+                ];
+
+                Ok(subscription(stream::iter(statuses)))
+            }
+            _ => Err(RpcError::request_rejected(format!(
+                "Mock subtensor does not support {method}"
+            ))),
+        }
+    }
+}
+
+impl State {
+    /// The block whose hash is at `index` in `params`, or the latest block if absent.
+    fn block(&self, params: &[Value], index: usize) -> Result<Option<&MockBlock>, RpcError> {
+        let hash: Option<Hash> = match params.get(index) {
+            Some(hash) => serde_json::from_value(hash.clone()).map_err(invalid_params)?,
+            None => None,
+        };
+
+        Ok(match hash {
+            Some(hash) => self.blocks.iter().find(|block| block.hash == hash),
+            None => self.blocks.last(),
+        })
+    }
+
+    fn existing_block(&self, params: &[Value], index: usize) -> Result<&MockBlock, RpcError> {
+        self.block(params, index)?
+            .ok_or_else(|| RpcError::request_rejected("Unknown block"))
+    }
+}
+
+impl RpcClientT for MockSubtensor {
+    fn request_raw<'a>(
+        &'a self,
+        method: &'a str,
+        params: Option<Box<RawValue>>,
+    ) -> RawRpcFuture<'a, Box<RawValue>> {
+        Box::pin(async move {
+            let params = parse_params(params)?;
+            let result = self.handle(method, &params)?;
+
+            to_raw_value(&result).map_err(invalid_params)
+        })
+    }
+
+    fn subscribe_raw<'a>(
+        &'a self,
+        sub: &'a str,
+        params: Option<Box<RawValue>>,
+        _unsub: &'a str,
+    ) -> RawRpcFuture<'a, RawRpcSubscription> {
+        Box::pin(async move {
+            let params = parse_params(params)?;
+
+            self.handle_subscription(sub, &params)
+        })
+    }
+}
+
+fn subscription(items: impl stream::Stream<Item = Value> + Send + 'static) -> RawRpcSubscription {
+    RawRpcSubscription {
+        stream: items
+            .map(|item| to_raw_value(&item).map_err(invalid_params))
+            .boxed(),
+        id: None,
+    }
+}
+
+fn broadcast_stream<T: Clone + Send + 'static>(
+    receiver: broadcast::Receiver<T>,
+) -> impl stream::Stream<Item = T> + Send + 'static {
+    stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(item) => return Some((item, receiver)),
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    })
+}
+
+fn parse_params(params: Option<Box<RawValue>>) -> Result<Vec<Value>, RpcError> {
+    match params {
+        Some(params) => serde_json::from_str(params.get()).map_err(invalid_params),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn invalid_params(error: serde_json::Error) -> RpcError {
+    RpcError::ClientError(Box::new(error))
+}
+
+fn hex_value(bytes: &[u8]) -> Value {
+    json!(format!("0x{}", hex::encode(bytes)))
+}
+
+fn bytes_param(params: &[Value], index: usize) -> Result<Vec<u8>, RpcError> {
+    let param = params
+        .get(index)
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+
+    hex::decode(param.trim_start_matches("0x"))
+        .map_err(|_| RpcError::request_rejected(format!("Invalid hex parameter {param:?}")))
+}
+
+/// Block numbers and counts are sent either as JSON numbers or as hex strings.
+fn number_param(param: &Value) -> Option<usize> {
+    match param {
+        Value::Number(number) => number.as_u64().map(|number| number as usize),
+        Value::String(number) => usize::from_str_radix(number.trim_start_matches("0x"), 16).ok(),
+        _ => None,
+    }
+}
+
+fn runtime_version_value(version: &RuntimeVersion) -> Value {
+    json!({
+        "specVersion": version.spec_version,
+        "transactionVersion": version.transaction_version,
+    })
+}
@@ -0,0 +1,301 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::client::{ConnectionError, ReconnectPolicy, SubtensorClient};
+use crate::subtensor::{self, ConnectionOptions, Subtensor, SubtensorUrl, UnknownNetworkError};
+
+pub const NETWORK_ENV: &str = "SUBTENSOR_NETWORK";
+pub const CHAIN_ENDPOINT_ENV: &str = "SUBTENSOR_CHAIN_ENDPOINT";
+
+#[derive(Debug, Error)]
+pub enum NetworkConfigError {
+    #[error(transparent)]
+    UnknownNetwork(#[from] UnknownNetworkError),
+
+    #[error(transparent)]
+    Toml(#[from] toml::de::Error),
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum NetworkConnectError {
+    #[error(transparent)]
+    UnknownNetwork(#[from] UnknownNetworkError),
+
+    #[error(transparent)]
+    Connection(#[from] ConnectionError),
+
+    #[error(transparent)]
+    Subxt(#[from] subxt::Error),
+}
+
+/// How to reach a subtensor network, resolved from defaults, a TOML file and the environment in that order.
+///
+/// ```toml
+/// network = "finney"
+/// chain_endpoint = "wss://my-own-node:443"
+/// fallback_endpoints = ["wss://entrypoint-finney.opentensor.ai:443"]
+/// use_archive = true
+/// request_timeout_secs = 30
+/// max_message_size = 33554432
+/// ```
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    /// A network name (`finney`, `test`, `archive`, `local`) or a websocket URL.
+    pub network: String,
+    /// Overrides the endpoint of `network` when set.
+    pub chain_endpoint: Option<String>,
+    pub fallback_endpoints: Vec<String>,
+    /// Whether historical queries go to an archive node rather than the main endpoint.
+    pub use_archive: bool,
+    /// The archive node to use, [`SubtensorUrl::Archive`] when unset.
+    pub archive_endpoint: Option<String>,
+    pub connection_timeout_secs: u64,
+    pub request_timeout_secs: u64,
+    pub max_message_size: u32,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        let options = ConnectionOptions::default();
+
+        Self {
+            network: "finney".to_string(),
+            chain_endpoint: None,
+            fallback_endpoints: Vec::new(),
+            use_archive: false,
+            archive_endpoint: None,
+            connection_timeout_secs: options.connection_timeout.as_secs(),
+            request_timeout_secs: options.request_timeout.as_secs(),
+            max_message_size: options.max_message_size,
+        }
+    }
+}
+
+impl NetworkConfig {
+    pub fn from_toml(toml: &str) -> Result<Self, NetworkConfigError> {
+        Ok(toml::from_str(toml)?)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, NetworkConfigError> {
+        Self::from_toml(&fs::read_to_string(path)?)
+    }
+
+    /// The default configuration with environment overrides applied.
+    pub fn from_env() -> Self {
+        Self::default().with_env()
+    }
+
+    /// Loads `path` if given, then applies environment overrides. This is the resolution every binary should use.
+    pub fn load(path: Option<impl AsRef<Path>>) -> Result<Self, NetworkConfigError> {
+        let config = match path {
+            Some(path) => Self::from_file(path)?,
+            None => Self::default(),
+        };
+
+        Ok(config.with_env())
+    }
+
+    /// Applies `SUBTENSOR_NETWORK` and `SUBTENSOR_CHAIN_ENDPOINT`, setting a network also clears the endpoint
+    /// override, as in btcli.
+    pub fn with_env(mut self) -> Self {
+        if let Ok(network) = env::var(NETWORK_ENV) {
+            self.network = network;
+            self.chain_endpoint = None;
+        }
+
+        if let Ok(endpoint) = env::var(CHAIN_ENDPOINT_ENV) {
+            self.chain_endpoint = Some(endpoint);
+        }
+
+        self
+    }
+
+    pub fn options(&self) -> ConnectionOptions {
+        ConnectionOptions {
+            connection_timeout: Duration::from_secs(self.connection_timeout_secs),
+            request_timeout: Duration::from_secs(self.request_timeout_secs),
+            max_message_size: self.max_message_size,
+        }
+    }
+
+    pub fn endpoint(&self) -> Result<SubtensorUrl, UnknownNetworkError> {
+        match &self.chain_endpoint {
+            Some(endpoint) => endpoint.parse(),
+            None => self.network.parse(),
+        }
+    }
+
+    /// The main endpoint followed by the fallbacks, for use with [`SubtensorClient`].
+    pub fn endpoints(&self) -> Result<Vec<SubtensorUrl>, UnknownNetworkError> {
+        let mut endpoints = vec![self.endpoint()?];
+
+        for endpoint in &self.fallback_endpoints {
+            endpoints.push(endpoint.parse()?);
+        }
+
+        Ok(endpoints)
+    }
+
+    /// The endpoint to send queries at old blocks to, which the main endpoint may have pruned.
+    pub fn historical_endpoint(&self) -> Result<SubtensorUrl, UnknownNetworkError> {
+        if !self.use_archive {
+            return self.endpoint();
+        }
+
+        match &self.archive_endpoint {
+            Some(endpoint) => endpoint.parse(),
+            None => Ok(SubtensorUrl::Archive),
+        }
+    }
+
+    pub async fn connect(&self) -> Result<Subtensor, NetworkConnectError> {
+        Ok(subtensor::from_url_with_options(self.endpoint()?, &self.options()).await?)
+    }
+
+    pub async fn connect_historical(&self) -> Result<Subtensor, NetworkConnectError> {
+        Ok(subtensor::from_url_with_options(self.historical_endpoint()?, &self.options()).await?)
+    }
+
+    /// Connects a [`SubtensorClient`] to [`NetworkConfig::endpoints`]. Each attempt is bounded by the policy's
+    /// `connect_timeout`, which caps the timeouts configured here.
+    pub async fn connect_failover(
+        &self,
+        policy: ReconnectPolicy,
+    ) -> Result<SubtensorClient, NetworkConnectError> {
+        Ok(
+            SubtensorClient::connect_with_options(self.endpoints()?, policy, self.options())
+                .await?,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// Serializes the tests touching the process environment.
+    static ENV: Mutex<()> = Mutex::new(());
+
+    fn clear_env() {
+        env::remove_var(NETWORK_ENV);
+        env::remove_var(CHAIN_ENDPOINT_ENV);
+    }
+
+    #[test]
+    fn parses_toml_over_defaults() {
+        let config = NetworkConfig::from_toml(
+            r#"
+            network = "test"
+            fallback_endpoints = ["wss://fallback-a:443", "wss://fallback-b:443"]
+            use_archive = true
+            request_timeout_secs = 30
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.network, "test");
+        assert_eq!(config.chain_endpoint, None);
+        assert!(config.use_archive);
+        assert_eq!(config.options().request_timeout, Duration::from_secs(30));
+        assert_eq!(
+            config.options().connection_timeout,
+            ConnectionOptions::default().connection_timeout
+        );
+        assert_eq!(
+            config.endpoints().unwrap(),
+            [
+                SubtensorUrl::Test,
+                SubtensorUrl::Custom("wss://fallback-a:443".to_string()),
+                SubtensorUrl::Custom("wss://fallback-b:443".to_string()),
+            ]
+        );
+        assert_eq!(config.historical_endpoint().unwrap(), SubtensorUrl::Archive);
+
+        assert!(NetworkConfig::from_toml("network = 1").is_err());
+        assert!(
+            NetworkConfig::from_toml("fallback_endpoints = [\"mainnet\"]")
+                .unwrap()
+                .endpoints()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn chain_endpoint_overrides_the_network() {
+        let config = NetworkConfig::from_toml(
+            r#"
+            network = "test"
+            chain_endpoint = "ws://127.0.0.1:9945"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.endpoint().unwrap(),
+            SubtensorUrl::Custom("ws://127.0.0.1:9945".to_string())
+        );
+        // Without `use_archive`, historical queries go to the main endpoint
+        assert_eq!(
+            config.historical_endpoint().unwrap(),
+            config.endpoint().unwrap()
+        );
+    }
+
+    #[test]
+    fn network_from_the_environment_clears_the_endpoint() {
+        let _env = ENV.lock().unwrap();
+        clear_env();
+
+        let config = NetworkConfig {
+            chain_endpoint: Some("ws://127.0.0.1:9945".to_string()),
+            ..NetworkConfig::default()
+        };
+
+        env::set_var(NETWORK_ENV, "local");
+
+        let config = config.with_env();
+
+        assert_eq!(config.network, "local");
+        assert_eq!(config.chain_endpoint, None);
+        assert_eq!(config.endpoint().unwrap(), SubtensorUrl::Local);
+
+        // An endpoint from the environment wins over a network from the environment
+        env::set_var(CHAIN_ENDPOINT_ENV, "wss://node.example:443");
+
+        let config = NetworkConfig::from_env();
+
+        assert_eq!(config.network, "local");
+        assert_eq!(
+            config.endpoint().unwrap(),
+            SubtensorUrl::Custom("wss://node.example:443".to_string())
+        );
+
+        clear_env();
+    }
+
+    #[test]
+    fn loads_defaults_without_a_file() {
+        let _env = ENV.lock().unwrap();
+        clear_env();
+
+        let config = NetworkConfig::load(None::<&Path>).unwrap();
+
+        assert_eq!(config.endpoint().unwrap(), SubtensorUrl::Finney);
+        assert!(matches!(
+            NetworkConfig::load(Some("/nonexistent/crabtensor.toml")),
+            Err(NetworkConfigError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,203 @@
+//! Checks which axons of a metagraph accept TCP connections, to find miners that are registered but unreachable.
+
+use std::io;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use subxt::ext::futures::{stream, StreamExt};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+use crate::api::runtime_types::pallet_subtensor::pallet::AxonInfo;
+use crate::axon::{AsEndpoint, AxonEndpoint};
+use crate::dynamic::metagraph;
+use crate::subtensor::Subtensor;
+use crate::{AccountId, BlockRef};
+
+#[derive(Clone, Debug)]
+pub struct ProbeOptions {
+    /// How many connections are attempted at once.
+    pub concurrency: usize,
+    /// How long a connection may take before the axon is considered unreachable.
+    pub timeout: Duration,
+    /// Also connect to private and loopback endpoints, which only makes sense when probing from the same network.
+    pub probe_private: bool,
+}
+
+impl Default for ProbeOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 64,
+            timeout: Duration::from_secs(3),
+            probe_private: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProbeOutcome {
+    Reachable {
+        latency: Duration,
+    },
+    Failed(io::ErrorKind),
+    TimedOut,
+    /// Not probed, as the endpoint is unset, invalid, or private without [`ProbeOptions::probe_private`].
+    Skipped,
+}
+
+#[derive(Clone, Debug)]
+pub struct AxonProbe {
+    pub uid: u16,
+    pub hotkey: AccountId,
+    pub endpoint: AxonEndpoint,
+    pub outcome: ProbeOutcome,
+}
+
+impl AxonProbe {
+    pub fn is_reachable(&self) -> bool {
+        matches!(self.outcome, ProbeOutcome::Reachable { .. })
+    }
+}
+
+/// Opens and closes a TCP connection to `address`, measuring how long connecting took.
+pub async fn probe_address(address: SocketAddr, connect_timeout: Duration) -> ProbeOutcome {
+    let start = Instant::now();
+
+    match timeout(connect_timeout, TcpStream::connect(address)).await {
+        Ok(Ok(_)) => ProbeOutcome::Reachable {
+            latency: start.elapsed(),
+        },
+        Ok(Err(error)) => ProbeOutcome::Failed(error.kind()),
+        Err(_) => ProbeOutcome::TimedOut,
+    }
+}
+
+/// Probes every `(uid, hotkey, axon)`, returning results in the same order.
+pub async fn probe_axons(
+    axons: impl IntoIterator<Item = (u16, AccountId, AxonInfo)>,
+    options: &ProbeOptions,
+) -> Vec<AxonProbe> {
+    stream::iter(axons)
+        .map(|(uid, hotkey, axon)| async move {
+            let endpoint = axon.endpoint();
+
+            let address = match endpoint {
+                AxonEndpoint::Public(address) | AxonEndpoint::Ipv6(address) => Some(address),
+                AxonEndpoint::Private(address) | AxonEndpoint::Loopback(address)
+                    if options.probe_private =>
+                {
+                    Some(address)
+                }
+                _ => None,
+            };
+
+            let outcome = match address {
+                Some(address) => probe_address(address, options.timeout).await,
+                None => ProbeOutcome::Skipped,
+            };
+
+            AxonProbe {
+                uid,
+                hotkey,
+                endpoint,
+                outcome,
+            }
+        })
+        .buffered(options.concurrency.max(1))
+        .collect()
+        .await
+}
+
+/// Probes the axons of every neuron of `netuid`, or returns `None` if the subnet does not exist.
+pub async fn probe_metagraph(
+    client: &Subtensor,
+    at: BlockRef,
+    netuid: u16,
+    options: &ProbeOptions,
+) -> Result<Option<Vec<AxonProbe>>, subxt::Error> {
+    let Some(metagraph) = metagraph(client, at, netuid).await? else {
+        return Ok(None);
+    };
+
+    let axons = metagraph
+        .hotkeys
+        .into_iter()
+        .zip(metagraph.axons)
+        .enumerate()
+        .map(|(uid, (hotkey, axon))| (uid as u16, hotkey, axon));
+
+    Ok(Some(probe_axons(axons, options).await))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    fn axon(port: u16) -> AxonInfo {
+        AxonInfo {
+            block: 0,
+            version: 0,
+            ip: u128::from(u32::from(Ipv4Addr::LOCALHOST)),
+            port,
+            ip_type: 4,
+            protocol: 4,
+            placeholder1: 0,
+            placeholder2: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn probes_listening_and_closed_ports() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let open = listener.local_addr().unwrap().port();
+
+        // Binding then dropping a listener leaves a port nothing listens on
+        let closed = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        let axons = vec![
+            (0, AccountId::from([0; 32]), axon(open)),
+            (1, AccountId::from([1; 32]), axon(closed)),
+            (2, AccountId::from([2; 32]), axon(0)),
+        ];
+
+        let options = ProbeOptions {
+            probe_private: true,
+            ..ProbeOptions::default()
+        };
+
+        let probes = probe_axons(axons.clone(), &options).await;
+
+        assert_eq!(
+            probes.iter().map(|probe| probe.uid).collect::<Vec<_>>(),
+            [0, 1, 2]
+        );
+        assert_eq!(
+            probes[0].endpoint,
+            AxonEndpoint::Loopback(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), open))
+        );
+        assert!(probes[0].is_reachable());
+        assert_eq!(
+            probes[1].outcome,
+            ProbeOutcome::Failed(io::ErrorKind::ConnectionRefused)
+        );
+        assert_eq!(probes[2].outcome, ProbeOutcome::Skipped);
+
+        // Loopback endpoints are only probed when asked to
+        let probes = probe_axons(axons, &ProbeOptions::default()).await;
+
+        assert!(probes
+            .iter()
+            .all(|probe| probe.outcome == ProbeOutcome::Skipped));
+
+        drop(listener);
+    }
+}
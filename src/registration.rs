@@ -0,0 +1,421 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use sp_core::hashing::{keccak_256, sha2_256};
+use sp_core::U256;
+use subxt::tx::Payload;
+use thiserror::Error;
+
+use crate::subtensor::Subtensor;
+use crate::{api, AccountId, BlockNumber};
+
+/// The pallet rejects work whose block is this many blocks (or more) behind the current block.
+pub const POW_BLOCK_WINDOW: u64 = 3;
+
+const NONCE_BATCH: u64 = 10_000;
+
+#[derive(Debug, Error)]
+pub enum RegistrationError {
+    #[error("Proof of work registration is not allowed on netuid {0}")]
+    PowRegistrationDisabled(u16),
+
+    #[error("Proof of work solver thread panicked")]
+    SolverPanicked,
+
+    #[error(transparent)]
+    Subxt(#[from] subxt::Error),
+}
+
+#[derive(Clone, Debug)]
+pub struct PowSolution {
+    pub block_number: u64,
+    pub nonce: u64,
+    pub seal: [u8; 32],
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PowProgress {
+    pub block_number: u64,
+    pub difficulty: u64,
+    pub hashes: u64,
+    pub elapsed: Duration,
+}
+
+impl PowProgress {
+    pub fn hash_rate(&self) -> f64 {
+        self.hashes as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SolverOptions {
+    pub threads: usize,
+    pub progress_interval: Duration,
+}
+
+impl Default for SolverOptions {
+    fn default() -> Self {
+        Self {
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+            progress_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+/// A cloneable flag used to stop a running solver.
+#[derive(Clone, Debug, Default)]
+pub struct CancelHandle(Arc<AtomicBool>);
+
+impl CancelHandle {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Mirrors `Pallet::hash_block_and_hotkey`, `keccak256(block_hash ++ hotkey)`.
+pub fn hash_block_and_hotkey(block_hash: &[u8; 32], hotkey: &AccountId) -> [u8; 32] {
+    let mut bytes = [0u8; 64];
+    bytes[..32].copy_from_slice(block_hash);
+    bytes[32..].copy_from_slice(&hotkey.0);
+
+    keccak_256(&bytes)
+}
+
+/// Mirrors `Pallet::create_seal_hash`, `keccak256(sha256(nonce_le ++ block_and_hotkey_hash))`.
+pub fn create_seal_hash(block_and_hotkey_hash: &[u8; 32], nonce: u64) -> [u8; 32] {
+    let mut bytes = [0u8; 40];
+    bytes[..8].copy_from_slice(&nonce.to_le_bytes());
+    bytes[8..].copy_from_slice(block_and_hotkey_hash);
+
+    keccak_256(&sha2_256(&bytes))
+}
+
+/// Mirrors `Pallet::hash_meets_difficulty`, the seal as a big endian integer times the difficulty must not overflow.
+pub fn seal_meets_difficulty(seal: &[u8; 32], difficulty: u64) -> bool {
+    !U256::from_big_endian(seal)
+        .overflowing_mul(U256::from(difficulty))
+        .1
+}
+
+/// Searches for a nonce on all configured threads, blocking until a solution is found or `cancel` is set.
+pub fn solve_for_block(
+    block_number: u64,
+    block_hash: &[u8; 32],
+    hotkey: &AccountId,
+    difficulty: u64,
+    options: SolverOptions,
+    cancel: &CancelHandle,
+    mut progress: impl FnMut(PowProgress),
+) -> Option<PowSolution> {
+    let block_and_hotkey_hash = hash_block_and_hotkey(block_hash, hotkey);
+    let threads = options.threads.max(1) as u64;
+    let hashes = AtomicU64::new(0);
+    let solution = Mutex::new(None);
+    let found = AtomicBool::new(false);
+    let start = Instant::now();
+
+    thread::scope(|scope| {
+        for offset in 0..threads {
+            let hashes = &hashes;
+            let solution = &solution;
+            let found = &found;
+
+            scope.spawn(move || {
+                let mut batch_start = offset * NONCE_BATCH;
+
+                while !found.load(Ordering::Relaxed) && !cancel.is_cancelled() {
+                    let mut tried = 0;
+
+                    for nonce in batch_start..batch_start + NONCE_BATCH {
+                        let seal = create_seal_hash(&block_and_hotkey_hash, nonce);
+                        tried += 1;
+
+                        if seal_meets_difficulty(&seal, difficulty) {
+                            found.store(true, Ordering::Relaxed);
+                            solution.lock().unwrap().get_or_insert(PowSolution {
+                                block_number,
+                                nonce,
+                                seal,
+                            });
+
+                            break;
+                        }
+                    }
+
+                    hashes.fetch_add(tried, Ordering::Relaxed);
+                    batch_start = batch_start.wrapping_add(threads * NONCE_BATCH);
+                }
+            });
+        }
+
+        let mut last_report = start;
+
+        while !found.load(Ordering::Relaxed) && !cancel.is_cancelled() {
+            thread::sleep(options.progress_interval.min(Duration::from_millis(50)));
+
+            if last_report.elapsed() >= options.progress_interval {
+                last_report = Instant::now();
+
+                progress(PowProgress {
+                    block_number,
+                    difficulty,
+                    hashes: hashes.load(Ordering::Relaxed),
+                    elapsed: start.elapsed(),
+                });
+            }
+        }
+    });
+
+    solution.into_inner().unwrap()
+}
+
+/// Solves the registration proof of work for `hotkey` on `netuid`, restarting against a fresh block whenever the
+/// current one ages out of [`POW_BLOCK_WINDOW`] before a solution is found.
+///
+/// Returns `Ok(None)` if `cancel` is set before a solution is found.
+pub async fn solve_pow(
+    client: &Subtensor,
+    netuid: u16,
+    hotkey: &AccountId,
+    options: SolverOptions,
+    cancel: CancelHandle,
+    progress: impl Fn(PowProgress) + Send + Sync + 'static,
+) -> Result<Option<PowSolution>, RegistrationError> {
+    let progress = Arc::new(progress);
+    let mut blocks = client.blocks().subscribe_best().await?;
+    let mut ticker = tokio::time::interval(Duration::from_millis(100));
+
+    // The pallet checks the work block against the chain head, so work on best blocks rather than finalized ones
+    let Some(mut block) = blocks.next().await.transpose()? else {
+        return Ok(None);
+    };
+
+    while !cancel.is_cancelled() {
+        let block_number = u64::from(block.number());
+        let block_hash = block.hash().0;
+
+        let storage = client.storage().at(block.reference());
+
+        let allowed = storage
+            .fetch_or_default(
+                &api::storage()
+                    .subtensor_module()
+                    .network_pow_registration_allowed(netuid),
+            )
+            .await?;
+
+        if !allowed {
+            return Err(RegistrationError::PowRegistrationDisabled(netuid));
+        }
+
+        let difficulty = storage
+            .fetch_or_default(&api::storage().subtensor_module().difficulty(netuid))
+            .await?;
+
+        let attempt = CancelHandle::default();
+        let solver = {
+            let attempt = attempt.clone();
+            let hotkey = hotkey.clone();
+            let progress = progress.clone();
+
+            tokio::task::spawn_blocking(move || {
+                solve_for_block(
+                    block_number,
+                    &block_hash,
+                    &hotkey,
+                    difficulty,
+                    options,
+                    &attempt,
+                    |update| progress(update),
+                )
+            })
+        };
+
+        tokio::pin!(solver);
+
+        loop {
+            tokio::select! {
+                solution = &mut solver => {
+                    match solution.map_err(|_| RegistrationError::SolverPanicked)? {
+                        // A solution racing a stale block or a cancellation is discarded
+                        Some(solution) if !attempt.is_cancelled() => return Ok(Some(solution)),
+                        _ => break,
+                    }
+                }
+                latest = blocks.next() => {
+                    let Some(latest) = latest.transpose()? else {
+                        attempt.cancel();
+                        return Ok(None);
+                    };
+
+                    if is_stale(block_number, latest.number()) {
+                        attempt.cancel();
+                        block = latest;
+                    }
+                }
+                _ = ticker.tick() => {
+                    if cancel.is_cancelled() {
+                        attempt.cancel();
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+fn is_stale(work_block: u64, latest: BlockNumber) -> bool {
+    // The extrinsic lands in the block after `latest` at the earliest
+    u64::from(latest) + 1 >= work_block + POW_BLOCK_WINDOW
+}
+
+pub fn register_payload(
+    netuid: u16,
+    solution: &PowSolution,
+    hotkey: AccountId,
+    coldkey: AccountId,
+) -> impl Payload {
+    api::tx().subtensor_module().register(
+        netuid,
+        solution.block_number,
+        solution.nonce,
+        solution.seal.to_vec(),
+        hotkey,
+        coldkey,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex32(value: &str) -> [u8; 32] {
+        hex::decode(value).unwrap().try_into().unwrap()
+    }
+
+    fn vector_inputs() -> ([u8; 32], AccountId) {
+        let mut block_hash = [0u8; 32];
+
+        for (index, byte) in block_hash.iter_mut().enumerate() {
+            *byte = index as u8;
+        }
+
+        (block_hash, AccountId::from([0xaa; 32]))
+    }
+
+    #[test]
+    fn block_and_hotkey_hash_matches_vector() {
+        let (block_hash, hotkey) = vector_inputs();
+
+        assert_eq!(
+            hash_block_and_hotkey(&block_hash, &hotkey),
+            hex32("f05eb6026d277ecd8f81e0236d2717ea5bbba02b69b3ba77506fba3db728b434"),
+        );
+    }
+
+    #[test]
+    fn seal_hash_matches_vectors() {
+        let (block_hash, hotkey) = vector_inputs();
+        let block_and_hotkey_hash = hash_block_and_hotkey(&block_hash, &hotkey);
+
+        for (nonce, seal) in [
+            (
+                0,
+                "e5079b2da71e3c5fa90f02c91100e87ca15a2b9f976e2b4648e41f49b1b5ed0a",
+            ),
+            (
+                1,
+                "9ece858e90fb54fdb25f63750c957a756d2ae2928eb8fc92b5285770a9fe24e8",
+            ),
+            (
+                12345,
+                "a860aef0628d12fbc92988b5b607130389ebdca42d771925467571c2c29d7d3e",
+            ),
+        ] {
+            assert_eq!(create_seal_hash(&block_and_hotkey_hash, nonce), hex32(seal));
+        }
+    }
+
+    #[test]
+    fn difficulty_boundaries() {
+        let zero = [0u8; 32];
+        let max = [0xff; 32];
+
+        assert!(seal_meets_difficulty(&zero, u64::MAX));
+        assert!(seal_meets_difficulty(&max, 1));
+        assert!(!seal_meets_difficulty(&max, 2));
+
+        // The largest seal accepted at a difficulty is `U256::MAX / difficulty`
+        let difficulty = 1_000_000;
+        let limit = U256::MAX / U256::from(difficulty);
+
+        assert!(seal_meets_difficulty(&limit.to_big_endian(), difficulty));
+        assert!(!seal_meets_difficulty(
+            &(limit + 1).to_big_endian(),
+            difficulty
+        ));
+    }
+
+    #[test]
+    fn work_goes_stale_at_the_window() {
+        assert!(!is_stale(100, 100));
+        assert!(!is_stale(100, 101));
+        assert!(is_stale(100, 102));
+        assert!(is_stale(100, 110));
+    }
+
+    #[test]
+    fn solves_trivial_difficulty_on_the_cpu() {
+        let (block_hash, hotkey) = vector_inputs();
+        let options = SolverOptions {
+            threads: 2,
+            progress_interval: Duration::from_millis(10),
+        };
+
+        let solution = solve_for_block(
+            100,
+            &block_hash,
+            &hotkey,
+            1_000,
+            options,
+            &CancelHandle::default(),
+            |_| {},
+        )
+        .unwrap();
+
+        let block_and_hotkey_hash = hash_block_and_hotkey(&block_hash, &hotkey);
+
+        assert_eq!(solution.block_number, 100);
+        assert_eq!(
+            create_seal_hash(&block_and_hotkey_hash, solution.nonce),
+            solution.seal
+        );
+        assert!(seal_meets_difficulty(&solution.seal, 1_000));
+    }
+
+    #[test]
+    fn cancelled_solver_returns_nothing() {
+        let (block_hash, hotkey) = vector_inputs();
+        let cancel = CancelHandle::default();
+        cancel.cancel();
+
+        let solution = solve_for_block(
+            100,
+            &block_hash,
+            &hotkey,
+            u64::MAX,
+            SolverOptions::default(),
+            &cancel,
+            |_| {},
+        );
+
+        assert!(solution.is_none());
+    }
+}
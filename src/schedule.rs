@@ -0,0 +1,393 @@
+//! Conversions between block numbers, wall-clock time and subnet epochs.
+
+use std::future::Future;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use subxt::backend::legacy::LegacyRpcMethods;
+use subxt::config::Header as _;
+use subxt::ext::codec::Decode;
+use subxt::ext::futures::try_join;
+use subxt::ext::subxt_core::storage::get_address_root_bytes;
+use tokio::task::JoinHandle;
+
+use crate::client::BlockStreamKind;
+use crate::subtensor::Subtensor;
+use crate::{api, BlockNumber, BlockRef, SubtensorConfig};
+
+/// The target time between two blocks.
+pub const BLOCK_TIME: Duration = Duration::from_secs(12);
+
+/// Mirrors `Pallet::blocks_until_next_epoch`, 0 when the epoch runs in `block` and `u64::MAX` when `tempo` is 0,
+/// which disables epochs.
+pub fn blocks_until_next_epoch(netuid: u16, tempo: u16, block: u64) -> u64 {
+    if tempo == 0 {
+        return u64::MAX;
+    }
+
+    let tempo = u64::from(tempo);
+    let remainder = block.wrapping_add(u64::from(netuid) + 1) % (tempo + 1);
+
+    tempo - remainder
+}
+
+/// The block the next epoch of `netuid` runs in, which is `block` itself if the epoch runs in it.
+pub fn next_epoch_block(netuid: u16, tempo: u16, block: u64) -> Option<u64> {
+    match blocks_until_next_epoch(netuid, tempo, block) {
+        u64::MAX => None,
+        blocks => Some(block + blocks),
+    }
+}
+
+pub fn blocks_to_duration(blocks: u64) -> Duration {
+    Duration::from_secs(BLOCK_TIME.as_secs().saturating_mul(blocks))
+}
+
+/// The number of blocks produced over `duration`, rounded up.
+pub fn duration_to_blocks(duration: Duration) -> u64 {
+    duration.as_millis().div_ceil(BLOCK_TIME.as_millis()) as u64
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct EpochSchedule {
+    pub netuid: u16,
+    pub tempo: u16,
+    pub block: u64,
+    pub blocks_since_last_step: u64,
+}
+
+impl EpochSchedule {
+    pub fn blocks_until_next_epoch(&self) -> u64 {
+        blocks_until_next_epoch(self.netuid, self.tempo, self.block)
+    }
+
+    pub fn next_epoch_block(&self) -> Option<u64> {
+        next_epoch_block(self.netuid, self.tempo, self.block)
+    }
+
+    /// The expected time until the next epoch, `None` if epochs are disabled.
+    pub fn time_until_next_epoch(&self) -> Option<Duration> {
+        match self.blocks_until_next_epoch() {
+            u64::MAX => None,
+            blocks => Some(blocks_to_duration(blocks)),
+        }
+    }
+}
+
+/// Reads the tempo and steps of `netuid` at `at`.
+pub async fn epoch_schedule(
+    client: &Subtensor,
+    at: BlockRef,
+    netuid: u16,
+) -> Result<EpochSchedule, subxt::Error> {
+    let storage = client.storage().at(at.clone());
+    let subtensor_module = api::storage().subtensor_module();
+    let tempo = subtensor_module.tempo(netuid);
+    let blocks_since_last_step = subtensor_module.blocks_since_last_step(netuid);
+
+    let (block, tempo, blocks_since_last_step) = try_join!(
+        client.blocks().at(at),
+        storage.fetch_or_default(&tempo),
+        storage.fetch_or_default(&blocks_since_last_step),
+    )?;
+
+    Ok(EpochSchedule {
+        netuid,
+        tempo,
+        block: block.number().into(),
+        blocks_since_last_step,
+    })
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct WeightsRateLimit {
+    pub block: u64,
+    /// The block the uid last set weights in, 0 if it never did.
+    pub last_update: u64,
+    pub rate_limit: u64,
+}
+
+impl WeightsRateLimit {
+    /// Mirrors `Pallet::check_rate_limit`, which lets uids that never set weights through.
+    pub fn can_set_weights(&self) -> bool {
+        self.last_update == 0 || self.block.saturating_sub(self.last_update) >= self.rate_limit
+    }
+
+    /// The first block weights can be set in again.
+    pub fn next_allowed_block(&self) -> u64 {
+        if self.can_set_weights() {
+            self.block
+        } else {
+            self.last_update + self.rate_limit
+        }
+    }
+
+    pub fn blocks_until_allowed(&self) -> u64 {
+        self.next_allowed_block() - self.block
+    }
+}
+
+/// Reads the weights rate limit window of `uid` in `netuid` at `at`.
+pub async fn weights_rate_limit(
+    client: &Subtensor,
+    at: BlockRef,
+    netuid: u16,
+    uid: u16,
+) -> Result<WeightsRateLimit, subxt::Error> {
+    let storage = client.storage().at(at.clone());
+    let subtensor_module = api::storage().subtensor_module();
+    let rate_limit = subtensor_module.weights_set_rate_limit(netuid);
+    let last_update = subtensor_module.last_update(netuid);
+
+    let (block, rate_limit, last_update) = try_join!(
+        client.blocks().at(at),
+        storage.fetch_or_default(&rate_limit),
+        storage.fetch_or_default(&last_update),
+    )?;
+
+    Ok(WeightsRateLimit {
+        block: block.number().into(),
+        last_update: last_update.get(uid as usize).copied().unwrap_or(0),
+        rate_limit,
+    })
+}
+
+async fn timestamp_at(
+    rpc: &LegacyRpcMethods<SubtensorConfig>,
+    key: &[u8],
+    number: BlockNumber,
+) -> Result<u64, subxt::Error> {
+    let hash = rpc
+        .chain_get_block_hash(Some(number.into()))
+        .await?
+        .ok_or_else(|| subxt::Error::Other(format!("Block {number} not found")))?;
+
+    match rpc.state_get_storage(key, Some(hash)).await? {
+        Some(bytes) => Ok(u64::decode(&mut bytes.as_slice())?),
+        None => Ok(0),
+    }
+}
+
+/// The last finalized block produced at or before `time`, found by binary search over the `Timestamp::Now` of
+/// blocks, `None` if `time` is before the first block.
+///
+/// Blocks are looked up by number, which needs the legacy RPC methods, and older blocks need an archive node.
+pub async fn block_at_time(
+    rpc: &LegacyRpcMethods<SubtensorConfig>,
+    time: SystemTime,
+) -> Result<Option<BlockNumber>, subxt::Error> {
+    let target = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_millis() as u64);
+
+    // A plain storage value, so its root bytes are the whole key
+    let key = get_address_root_bytes(&api::storage().timestamp().now());
+
+    let finalized = rpc.chain_get_finalized_head().await?;
+    let head = rpc
+        .chain_get_header(Some(finalized))
+        .await?
+        .ok_or_else(|| subxt::Error::Other("Finalized head not found".to_string()))?
+        .number();
+
+    // The genesis block has no timestamp
+    let (mut low, mut high) = (1, head);
+
+    if head == 0 || timestamp_at(rpc, &key, low).await? > target {
+        return Ok(None);
+    }
+
+    while low < high {
+        let middle = low + (high - low).div_ceil(2);
+
+        if timestamp_at(rpc, &key, middle).await? <= target {
+            low = middle;
+        } else {
+            high = middle - 1;
+        }
+    }
+
+    Ok(Some(low))
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct EpochNotice {
+    pub netuid: u16,
+    /// The block the notice was triggered by.
+    pub block: u64,
+    pub epoch_block: u64,
+    pub blocks_until_epoch: u64,
+}
+
+/// Calls `callback` once per epoch of `netuid`, in the first block at most `blocks_before` blocks before it.
+///
+/// The tempo is read again in every block, so tempo changes are picked up. A block whose tempo can not be read uses
+/// the last tempo read instead, so a transient RPC error does not end the task. The returned handle resolves with the
+/// error that did end it: a block subscription error, or failing to read the tempo before any was read. It resolves
+/// with `Ok` when the subscription ends.
+pub fn schedule_epochs<F, Fut>(
+    client: &Subtensor,
+    kind: BlockStreamKind,
+    netuid: u16,
+    blocks_before: u64,
+    mut callback: F,
+) -> JoinHandle<Result<(), subxt::Error>>
+where
+    F: FnMut(EpochNotice) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send,
+{
+    let client = client.clone();
+
+    tokio::spawn(async move {
+        let mut blocks = match kind {
+            BlockStreamKind::Best => client.blocks().subscribe_best().await?,
+            BlockStreamKind::Finalized => client.blocks().subscribe_finalized().await?,
+        };
+
+        let mut last_notified = None;
+        let mut last_tempo = None;
+
+        while let Some(block) = blocks.next().await {
+            let block = block?;
+
+            let tempo = match client
+                .storage()
+                .at(block.reference())
+                .fetch_or_default(&api::storage().subtensor_module().tempo(netuid))
+                .await
+            {
+                Ok(tempo) => tempo,
+                Err(error) => last_tempo.ok_or(error)?,
+            };
+
+            last_tempo = Some(tempo);
+
+            let number = u64::from(block.number());
+
+            let Some(epoch_block) = next_epoch_block(netuid, tempo, number) else {
+                continue;
+            };
+
+            let blocks_until_epoch = epoch_block - number;
+
+            if blocks_until_epoch > blocks_before || last_notified == Some(epoch_block) {
+                continue;
+            }
+
+            last_notified = Some(epoch_block);
+
+            callback(EpochNotice {
+                netuid,
+                block: number,
+                epoch_block,
+                blocks_until_epoch,
+            })
+            .await;
+        }
+
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_blocks_until_the_epoch_like_the_pallet() {
+        // The vectors of the pallet's `test_blocks_until_epoch`
+        assert_eq!(blocks_until_next_epoch(0, 0, 0), u64::MAX);
+        assert_eq!(blocks_until_next_epoch(7, 0, 1234), u64::MAX);
+
+        for block in 0..100 {
+            let (even, odd) = if block % 2 == 0 { (0, 1) } else { (1, 0) };
+
+            assert_eq!(blocks_until_next_epoch(0, 1, block), even);
+            assert_eq!(blocks_until_next_epoch(1, 1, block), odd);
+        }
+
+        for netuid in 0..30 {
+            for block in 0..30 {
+                for tempo in 1..30 {
+                    assert_eq!(
+                        blocks_until_next_epoch(netuid, tempo, block),
+                        tempo as u64 - (block + netuid as u64 + 1) % (tempo as u64 + 1)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn finds_the_next_epoch_block_per_subnet() {
+        // With a tempo of 360, the epoch of netuid 1 runs in blocks 358, 719, ... and that of netuid 2 one earlier
+        assert_eq!(blocks_until_next_epoch(1, 360, 358), 0);
+        assert_eq!(next_epoch_block(1, 360, 358), Some(358));
+        assert_eq!(next_epoch_block(1, 360, 359), Some(719));
+        assert_eq!(next_epoch_block(2, 360, 358), Some(718));
+        assert_eq!(next_epoch_block(2, 360, 357), Some(357));
+        assert_eq!(next_epoch_block(1, 0, 359), None);
+
+        // The pallet wraps the adjusted block instead of overflowing
+        assert_eq!(blocks_until_next_epoch(1, 360, u64::MAX), 360 - 1);
+
+        let schedule = EpochSchedule {
+            netuid: 1,
+            tempo: 360,
+            block: 700,
+            blocks_since_last_step: 340,
+        };
+
+        assert_eq!(schedule.blocks_until_next_epoch(), 19);
+        assert_eq!(
+            schedule.time_until_next_epoch(),
+            Some(Duration::from_secs(228))
+        );
+        assert_eq!(
+            EpochSchedule {
+                tempo: 0,
+                ..schedule
+            }
+            .time_until_next_epoch(),
+            None
+        );
+    }
+
+    #[test]
+    fn checks_the_weights_rate_limit_like_the_pallet() {
+        let limit = |block, last_update, rate_limit| WeightsRateLimit {
+            block,
+            last_update,
+            rate_limit,
+        };
+
+        // Uids that never set weights are let through, even in block 0
+        assert!(limit(0, 0, 100).can_set_weights());
+        assert!(limit(50, 0, 100).can_set_weights());
+
+        assert!(!limit(1099, 1000, 100).can_set_weights());
+        assert_eq!(limit(1099, 1000, 100).next_allowed_block(), 1100);
+        assert_eq!(limit(1099, 1000, 100).blocks_until_allowed(), 1);
+
+        assert!(limit(1100, 1000, 100).can_set_weights());
+        assert_eq!(limit(1100, 1000, 100).next_allowed_block(), 1100);
+        assert_eq!(limit(1100, 1000, 100).blocks_until_allowed(), 0);
+
+        assert!(limit(1000, 1000, 0).can_set_weights());
+
+        // A last update ahead of the block, as read from a later block, saturates to no blocks since
+        assert!(!limit(990, 1000, 100).can_set_weights());
+        assert_eq!(limit(990, 1000, 100).blocks_until_allowed(), 110);
+    }
+
+    #[test]
+    fn converts_between_durations_and_blocks() {
+        assert_eq!(duration_to_blocks(Duration::ZERO), 0);
+        assert_eq!(duration_to_blocks(Duration::from_millis(1)), 1);
+        assert_eq!(duration_to_blocks(Duration::from_secs(12)), 1);
+        assert_eq!(duration_to_blocks(Duration::from_millis(12_001)), 2);
+        assert_eq!(duration_to_blocks(Duration::from_secs(3600)), 300);
+
+        assert_eq!(blocks_to_duration(300), Duration::from_secs(3600));
+        assert_eq!(blocks_to_duration(u64::MAX), Duration::from_secs(u64::MAX));
+    }
+}
@@ -1,14 +1,23 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use jsonrpsee::ws_client::WsClientBuilder;
+use subxt::backend::rpc::RpcClient;
 use subxt::{Error, OnlineClient};
+use thiserror::Error;
 
-use crate::SubtensorConfig;
+use crate::{api, AccountId, SubtensorConfig, METADATA_NETWORK, METADATA_SPEC_VERSION};
 
 pub type Subtensor = OnlineClient<SubtensorConfig>;
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum SubtensorUrl {
     Finney,
     Archive,
     Test,
     Local,
+    Custom(String),
 }
 
 impl AsRef<str> for SubtensorUrl {
@@ -18,6 +27,62 @@ impl AsRef<str> for SubtensorUrl {
             SubtensorUrl::Archive => "wss://archive.chain.opentensor.ai:443/",
             SubtensorUrl::Test => "wss://test.finney.opentensor.ai:443/",
             SubtensorUrl::Local => "ws://127.0.0.1:9944",
+            SubtensorUrl::Custom(url) => url,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("Unknown network {0:?}, expected finney, test, archive, local or a ws:// or wss:// URL")]
+pub struct UnknownNetworkError(pub String);
+
+impl FromStr for SubtensorUrl {
+    type Err = UnknownNetworkError;
+
+    /// Parses a network name the way btcli does, or a websocket URL.
+    fn from_str(network: &str) -> Result<Self, Self::Err> {
+        match network.to_ascii_lowercase().as_str() {
+            "finney" => Ok(SubtensorUrl::Finney),
+            "archive" => Ok(SubtensorUrl::Archive),
+            "test" => Ok(SubtensorUrl::Test),
+            "local" => Ok(SubtensorUrl::Local),
+            url if url.starts_with("ws://") || url.starts_with("wss://") => {
+                Ok(SubtensorUrl::Custom(network.to_string()))
+            }
+            _ => Err(UnknownNetworkError(network.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum RuntimeMismatchError {
+    #[error(
+        "Node runs spec version {node}, but crabtensor was built with {METADATA_NETWORK} metadata from spec version \
+         {compiled}"
+    )]
+    SpecVersion { node: u32, compiled: u32 },
+
+    #[error(
+        "Node metadata does not match the {METADATA_NETWORK} metadata crabtensor was built with, select another \
+         network feature or regenerate the snapshot with crabtensor-codegen"
+    )]
+    MetadataHash,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ConnectionOptions {
+    pub connection_timeout: Duration,
+    pub request_timeout: Duration,
+    /// Maximum size in bytes of a single request or response.
+    pub max_message_size: u32,
+}
+
+impl Default for ConnectionOptions {
+    fn default() -> Self {
+        Self {
+            connection_timeout: Duration::from_secs(10),
+            request_timeout: Duration::from_secs(60),
+            max_message_size: 16 * 1024 * 1024,
         }
     }
 }
@@ -25,3 +90,123 @@ impl AsRef<str> for SubtensorUrl {
 pub async fn from_url(url: impl AsRef<str>) -> Result<Subtensor, Error> {
     Subtensor::from_url(url).await
 }
+
+pub async fn from_url_with_options(
+    url: impl AsRef<str>,
+    options: &ConnectionOptions,
+) -> Result<Subtensor, Error> {
+    Subtensor::from_rpc_client(rpc_client_with_options(url, options).await?).await
+}
+
+/// The RPC client [`from_url_with_options`] connects through, for callers that also need raw RPC methods.
+pub async fn rpc_client_with_options(
+    url: impl AsRef<str>,
+    options: &ConnectionOptions,
+) -> Result<RpcClient, Error> {
+    subxt::utils::validate_url_is_secure(url.as_ref())?;
+
+    let client = WsClientBuilder::default()
+        .connection_timeout(options.connection_timeout)
+        .request_timeout(options.request_timeout)
+        .max_request_size(options.max_message_size)
+        .max_response_size(options.max_message_size)
+        .build(url.as_ref())
+        .await
+        .map_err(|error| Error::Other(error.to_string()))?;
+
+    Ok(RpcClient::new(client))
+}
+
+/// Checks that the node runs the runtime the compiled-in metadata was generated from, so a mismatch surfaces as one
+/// clear error rather than as decode failures in later calls.
+///
+/// The spec version is only compared when the snapshot records it, the metadata hash always is.
+pub fn check_runtime(client: &Subtensor) -> Result<(), RuntimeMismatchError> {
+    let node = client.runtime_version().spec_version;
+
+    if let Some(compiled) = METADATA_SPEC_VERSION {
+        if node != compiled {
+            return Err(RuntimeMismatchError::SpecVersion { node, compiled });
+        }
+    }
+
+    if !api::is_codegen_valid_for(&client.metadata()) {
+        return Err(RuntimeMismatchError::MetadataHash);
+    }
+
+    Ok(())
+}
+
+/// Decodes the account ID at the end of a storage key whose last hasher is a concat hasher, such as `Twox64Concat`, or
+/// `None` if the key is too short to hold one.
+pub(crate) fn trailing_account_id(key_bytes: &[u8]) -> Option<AccountId> {
+    let start = key_bytes.len().checked_sub(32)?;
+    let account_id: [u8; 32] = key_bytes.get(start..)?.try_into().ok()?;
+
+    Some(AccountId::from(account_id))
+}
+
+/// Decodes the netuid at the end of a storage key whose last hasher is `Identity` or a concat hasher, or `None` if
+/// the key is too short to hold one.
+pub(crate) fn trailing_netuid(key_bytes: &[u8]) -> Option<u16> {
+    let start = key_bytes.len().checked_sub(2)?;
+
+    Some(u16::from_le_bytes(key_bytes.get(start..)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_trailing_keys_without_panicking_on_short_ones() {
+        let mut key = vec![0xee; 16];
+        key.extend([7; 32]);
+
+        assert_eq!(trailing_account_id(&key), Some(AccountId::from([7; 32])));
+        assert_eq!(trailing_account_id(&key[..31]), None);
+
+        assert_eq!(trailing_netuid(&[0xee, 0xee, 3, 1]), Some(259));
+        assert_eq!(trailing_netuid(&[3]), None);
+        assert_eq!(trailing_netuid(&[]), None);
+    }
+
+    #[test]
+    fn parses_network_names_and_urls() {
+        assert_eq!(
+            "finney".parse::<SubtensorUrl>().unwrap(),
+            SubtensorUrl::Finney
+        );
+        assert_eq!("Test".parse::<SubtensorUrl>().unwrap(), SubtensorUrl::Test);
+        assert_eq!(
+            "ARCHIVE".parse::<SubtensorUrl>().unwrap(),
+            SubtensorUrl::Archive
+        );
+        assert_eq!(
+            "local".parse::<SubtensorUrl>().unwrap(),
+            SubtensorUrl::Local
+        );
+
+        // URLs keep their case, as paths can be case sensitive
+        let url = "WSS://Node.example:443/Path"
+            .parse::<SubtensorUrl>()
+            .unwrap();
+
+        assert_eq!(
+            url,
+            SubtensorUrl::Custom("WSS://Node.example:443/Path".to_string())
+        );
+        assert_eq!(url.as_ref(), "WSS://Node.example:443/Path");
+        assert_eq!(
+            "ws://127.0.0.1:9944"
+                .parse::<SubtensorUrl>()
+                .unwrap()
+                .as_ref(),
+            SubtensorUrl::Local.as_ref()
+        );
+
+        for invalid in ["", "mainnet", "https://node.example", "127.0.0.1:9944"] {
+            assert!(invalid.parse::<SubtensorUrl>().is_err(), "{invalid:?}");
+        }
+    }
+}
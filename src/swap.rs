@@ -0,0 +1,622 @@
+//! Hotkey and coldkey swaps, checked before submitting and reported on once executed.
+//!
+//! Both swaps are irreversible and charge a fee, so the `plan_*` functions check what the pallet would reject ahead of
+//! time and list what is about to move. The plans build the payloads to sign, and are then compared against the chain
+//! once the swap went through.
+
+use std::collections::BTreeMap;
+
+use subxt::ext::futures::future::try_join_all;
+use subxt::ext::futures::try_join;
+use subxt::tx::Payload;
+use thiserror::Error;
+
+use crate::api::runtime_types::pallet_subtensor::rpc_info::stake_info::StakeInfo;
+use crate::dynamic::stake_info;
+use crate::events::{block_events, EventFilter, EventRecord, SubtensorEvent};
+use crate::subtensor::{trailing_netuid, Subtensor};
+use crate::{api, AccountId, BlockNumber, BlockRef, Hash};
+
+#[derive(Debug, Error)]
+pub enum SwapError {
+    // Boxed, as it is large and most checks fail with another variant
+    #[error(transparent)]
+    Subxt(Box<subxt::Error>),
+
+    #[error("The new key is the same as the current one")]
+    SameKey,
+
+    #[error("{hotkey} is owned by {owner:?}, not {coldkey}")]
+    NotOwner {
+        hotkey: AccountId,
+        coldkey: AccountId,
+        owner: Option<AccountId>,
+    },
+
+    #[error("New hotkey {hotkey} is already registered on subnets {netuids:?}")]
+    NewHotkeyRegistered {
+        hotkey: AccountId,
+        netuids: Vec<u16>,
+    },
+
+    #[error("New coldkey {0} already owns or stakes to hotkeys")]
+    NewColdkeyInUse(AccountId),
+
+    #[error("The swap costs {cost} rao but {coldkey} only has {free} free")]
+    InsufficientBalance {
+        coldkey: AccountId,
+        cost: u64,
+        free: u64,
+    },
+
+    #[error(
+        "A swap of {coldkey} to {new_coldkey} is already scheduled at block {execution_block}"
+    )]
+    AlreadyScheduled {
+        coldkey: AccountId,
+        new_coldkey: AccountId,
+        execution_block: BlockNumber,
+    },
+
+    #[error("No coldkey swap of {0} is scheduled")]
+    NotScheduled(AccountId),
+
+    #[error("The coldkey swap of {coldkey} scheduled at block {execution_block} was not executed")]
+    NotExecuted {
+        coldkey: AccountId,
+        execution_block: BlockNumber,
+    },
+
+    #[error("Block subscription ended before the coldkey swap was executed")]
+    SubscriptionEnded,
+}
+
+impl From<subxt::Error> for SwapError {
+    fn from(error: subxt::Error) -> Self {
+        Self::Subxt(Box::new(error))
+    }
+}
+
+/// A registration of a hotkey, with the total alpha staked to it on that subnet.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SubnetHolding {
+    pub netuid: u16,
+    pub uid: u16,
+    pub alpha: u64,
+}
+
+/// The subnets `hotkey` is registered on.
+pub async fn registered_netuids(
+    client: &Subtensor,
+    at: BlockRef,
+    hotkey: &AccountId,
+) -> Result<Vec<u16>, subxt::Error> {
+    let mut members = client
+        .storage()
+        .at(at)
+        .iter(
+            api::storage()
+                .subtensor_module()
+                .is_network_member_iter1(hotkey),
+        )
+        .await?;
+
+    let mut netuids = Vec::new();
+
+    while let Some(member) = members.next().await {
+        let member = member?;
+
+        // The netuid is hashed with `Identity`, so it ends the key as is
+        if let (true, Some(netuid)) = (member.value, trailing_netuid(&member.key_bytes)) {
+            netuids.push(netuid);
+        }
+    }
+
+    netuids.sort_unstable();
+
+    Ok(netuids)
+}
+
+/// The registrations of `hotkey` along with the alpha staked to it on each subnet.
+pub async fn hotkey_holdings(
+    client: &Subtensor,
+    at: BlockRef,
+    hotkey: &AccountId,
+) -> Result<Vec<SubnetHolding>, subxt::Error> {
+    let netuids = registered_netuids(client, at.clone(), hotkey).await?;
+    let storage = client.storage().at(at);
+    let subtensor_module = api::storage().subtensor_module();
+
+    try_join_all(netuids.into_iter().map(|netuid| {
+        let storage = storage.clone();
+        let uid = subtensor_module.uids(netuid, hotkey);
+        let alpha = subtensor_module.total_hotkey_alpha(hotkey, netuid);
+
+        async move {
+            let (uid, alpha) = try_join!(storage.fetch(&uid), storage.fetch_or_default(&alpha))?;
+
+            Ok(uid.map(|uid| SubnetHolding { netuid, uid, alpha }))
+        }
+    }))
+    .await
+    .map(|holdings| holdings.into_iter().flatten().collect())
+}
+
+async fn free_balance(
+    client: &Subtensor,
+    at: BlockRef,
+    coldkey: &AccountId,
+) -> Result<u64, subxt::Error> {
+    let account = client
+        .storage()
+        .at(at)
+        .fetch_or_default(&api::storage().system().account(coldkey))
+        .await?;
+
+    Ok(account.data.free)
+}
+
+/// A checked hotkey swap, see [`plan_hotkey_swap`].
+#[derive(Clone, Debug)]
+pub struct HotkeySwapPlan {
+    pub coldkey: AccountId,
+    pub old_hotkey: AccountId,
+    pub new_hotkey: AccountId,
+    /// Only swap on this subnet rather than on all of them.
+    pub netuid: Option<u16>,
+    /// The fee in rao, burned from the coldkey.
+    pub cost: u64,
+    pub free_balance: u64,
+    /// What the old hotkey holds on the affected subnets, and is expected to move to the new hotkey.
+    pub holdings: Vec<SubnetHolding>,
+}
+
+impl HotkeySwapPlan {
+    pub fn netuids(&self) -> Vec<u16> {
+        self.holdings.iter().map(|holding| holding.netuid).collect()
+    }
+
+    /// The `swap_hotkey` call, signed by the coldkey.
+    pub fn payload(&self) -> impl Payload {
+        api::tx().subtensor_module().swap_hotkey(
+            self.old_hotkey.clone(),
+            self.new_hotkey.clone(),
+            self.netuid,
+        )
+    }
+}
+
+/// Checks that `coldkey` owns `old_hotkey` and can pay for the swap, and that `new_hotkey` is not registered on the
+/// subnets it would be swapped on, which the pallet would reject after charging for the transaction.
+pub async fn plan_hotkey_swap(
+    client: &Subtensor,
+    at: BlockRef,
+    coldkey: &AccountId,
+    old_hotkey: &AccountId,
+    new_hotkey: &AccountId,
+    netuid: Option<u16>,
+) -> Result<HotkeySwapPlan, SwapError> {
+    if old_hotkey == new_hotkey {
+        return Err(SwapError::SameKey);
+    }
+
+    let storage = client.storage().at(at.clone());
+    let owner = api::storage().subtensor_module().owner(old_hotkey);
+
+    let (owner, new_netuids, holdings, free_balance) = try_join!(
+        storage.fetch(&owner),
+        registered_netuids(client, at.clone(), new_hotkey),
+        hotkey_holdings(client, at.clone(), old_hotkey),
+        free_balance(client, at, coldkey),
+    )?;
+
+    if owner.as_ref() != Some(coldkey) {
+        return Err(SwapError::NotOwner {
+            hotkey: old_hotkey.clone(),
+            coldkey: coldkey.clone(),
+            owner,
+        });
+    }
+
+    let in_scope = |candidate: u16| netuid.is_none_or(|netuid| netuid == candidate);
+    let conflicts: Vec<u16> = new_netuids
+        .into_iter()
+        .filter(|&netuid| in_scope(netuid))
+        .collect();
+
+    if !conflicts.is_empty() {
+        return Err(SwapError::NewHotkeyRegistered {
+            hotkey: new_hotkey.clone(),
+            netuids: conflicts,
+        });
+    }
+
+    let constants = api::constants().subtensor_module();
+    let cost = match netuid {
+        Some(_) => constants.key_swap_on_subnet_cost(),
+        None => constants.key_swap_cost(),
+    };
+    let cost = client.constants().at(&cost)?;
+
+    if free_balance < cost {
+        return Err(SwapError::InsufficientBalance {
+            coldkey: coldkey.clone(),
+            cost,
+            free: free_balance,
+        });
+    }
+
+    Ok(HotkeySwapPlan {
+        coldkey: coldkey.clone(),
+        old_hotkey: old_hotkey.clone(),
+        new_hotkey: new_hotkey.clone(),
+        netuid,
+        cost,
+        free_balance,
+        holdings: holdings
+            .into_iter()
+            .filter(|holding| in_scope(holding.netuid))
+            .collect(),
+    })
+}
+
+/// A subnet holding before and after a swap, `None` where the key was not registered.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MovedHolding {
+    pub netuid: u16,
+    pub before: Option<SubnetHolding>,
+    pub after: Option<SubnetHolding>,
+}
+
+impl MovedHolding {
+    pub fn moved(&self) -> bool {
+        self.before.is_some() && self.after.is_some()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct HotkeySwapReport {
+    pub block_number: BlockNumber,
+    pub block_hash: Hash,
+    /// The `SubtensorModule` events of the swap's block mentioning the old hotkey.
+    pub events: Vec<EventRecord>,
+    /// The holdings of the old hotkey in the plan, matched with those of the new hotkey after the swap.
+    pub holdings: Vec<MovedHolding>,
+}
+
+/// Compares `plan` against the new hotkey's holdings in `block_hash`, the block the swap was included in.
+pub async fn hotkey_swap_report(
+    client: &Subtensor,
+    plan: &HotkeySwapPlan,
+    block_hash: Hash,
+) -> Result<HotkeySwapReport, subxt::Error> {
+    let block = client.blocks().at(block_hash).await?;
+
+    let filter = EventFilter {
+        hotkey: Some(plan.old_hotkey.clone()),
+        ..Default::default()
+    };
+
+    let (events, after) = try_join!(
+        block_events(&block, &filter),
+        hotkey_holdings(client, block.reference(), &plan.new_hotkey),
+    )?;
+
+    let mut holdings: BTreeMap<u16, MovedHolding> = BTreeMap::new();
+
+    for holding in &plan.holdings {
+        holdings.insert(
+            holding.netuid,
+            MovedHolding {
+                netuid: holding.netuid,
+                before: Some(holding.clone()),
+                after: None,
+            },
+        );
+    }
+
+    for holding in after {
+        let netuid = holding.netuid;
+
+        if plan.netuid.is_some_and(|scope| scope != netuid) {
+            continue;
+        }
+
+        holdings
+            .entry(netuid)
+            .or_insert(MovedHolding {
+                netuid,
+                before: None,
+                after: None,
+            })
+            .after = Some(holding);
+    }
+
+    Ok(HotkeySwapReport {
+        block_number: block.number(),
+        block_hash: block.hash(),
+        events,
+        holdings: holdings.into_values().collect(),
+    })
+}
+
+/// A checked coldkey swap, see [`plan_coldkey_swap`].
+#[derive(Clone, Debug)]
+pub struct ColdkeySwapPlan {
+    pub old_coldkey: AccountId,
+    pub new_coldkey: AccountId,
+    /// The fee in rao, the pallet charges `KeySwapCost` for coldkey swaps too.
+    pub cost: u64,
+    pub free_balance: u64,
+    /// The hotkeys the old coldkey owns, which move to the new coldkey.
+    pub hotkeys: Vec<AccountId>,
+    /// The stakes of the old coldkey, which move to the new coldkey.
+    pub stakes: Vec<StakeInfo<AccountId>>,
+}
+
+impl ColdkeySwapPlan {
+    /// The subnets the old coldkey has stake on.
+    pub fn netuids(&self) -> Vec<u16> {
+        let mut netuids: Vec<u16> = self.stakes.iter().map(|stake| stake.netuid).collect();
+        netuids.sort_unstable();
+        netuids.dedup();
+
+        netuids
+    }
+
+    /// The `schedule_swap_coldkey` call, signed by the old coldkey.
+    pub fn payload(&self) -> impl Payload {
+        api::tx()
+            .subtensor_module()
+            .schedule_swap_coldkey(self.new_coldkey.clone())
+    }
+}
+
+/// The block a coldkey swap of `coldkey` is scheduled to execute in, along with the new coldkey.
+pub async fn scheduled_coldkey_swap(
+    client: &Subtensor,
+    at: BlockRef,
+    coldkey: &AccountId,
+) -> Result<Option<(BlockNumber, AccountId)>, subxt::Error> {
+    let scheduled = client
+        .storage()
+        .at(at)
+        .fetch(
+            &api::storage()
+                .subtensor_module()
+                .coldkey_swap_scheduled(coldkey),
+        )
+        .await?;
+
+    // Unscheduled swaps decode as the default value, at block 0
+    Ok(scheduled.filter(|&(execution_block, _)| execution_block != 0))
+}
+
+/// Checks that no swap of `old_coldkey` is pending and that it can pay for the swap, and that `new_coldkey` neither
+/// owns nor stakes to hotkeys.
+pub async fn plan_coldkey_swap(
+    client: &Subtensor,
+    at: BlockRef,
+    old_coldkey: &AccountId,
+    new_coldkey: &AccountId,
+) -> Result<ColdkeySwapPlan, SwapError> {
+    if old_coldkey == new_coldkey {
+        return Err(SwapError::SameKey);
+    }
+
+    let storage = client.storage().at(at.clone());
+    let subtensor_module = api::storage().subtensor_module();
+    let hotkeys = subtensor_module.owned_hotkeys(old_coldkey);
+    let new_owned = subtensor_module.owned_hotkeys(new_coldkey);
+    let new_staking = subtensor_module.staking_hotkeys(new_coldkey);
+
+    let (block, scheduled, hotkeys, new_owned, new_staking, stakes, free_balance) = try_join!(
+        client.blocks().at(at.clone()),
+        scheduled_coldkey_swap(client, at.clone(), old_coldkey),
+        storage.fetch_or_default(&hotkeys),
+        storage.fetch_or_default(&new_owned),
+        storage.fetch_or_default(&new_staking),
+        stake_info(client, at.clone(), old_coldkey.clone()),
+        free_balance(client, at, old_coldkey),
+    )?;
+
+    if let Some((execution_block, scheduled_coldkey)) = scheduled {
+        if execution_block >= block.number() {
+            return Err(SwapError::AlreadyScheduled {
+                coldkey: old_coldkey.clone(),
+                new_coldkey: scheduled_coldkey,
+                execution_block,
+            });
+        }
+    }
+
+    if !new_owned.is_empty() || !new_staking.is_empty() {
+        return Err(SwapError::NewColdkeyInUse(new_coldkey.clone()));
+    }
+
+    let cost = client
+        .constants()
+        .at(&api::constants().subtensor_module().key_swap_cost())?;
+
+    if free_balance < cost {
+        return Err(SwapError::InsufficientBalance {
+            coldkey: old_coldkey.clone(),
+            cost,
+            free: free_balance,
+        });
+    }
+
+    Ok(ColdkeySwapPlan {
+        old_coldkey: old_coldkey.clone(),
+        new_coldkey: new_coldkey.clone(),
+        cost,
+        free_balance,
+        hotkeys,
+        stakes,
+    })
+}
+
+/// How many blocks past its scheduled block [`wait_for_coldkey_swap`] keeps waiting for a swap the scheduler deferred.
+pub const COLDKEY_SWAP_GRACE_BLOCKS: BlockNumber = 20;
+
+/// The block a scheduled coldkey swap was executed in.
+#[derive(Clone, Debug)]
+pub struct ColdkeySwapExecution {
+    pub block_number: BlockNumber,
+    pub block_hash: Hash,
+    pub new_coldkey: AccountId,
+    pub swap_cost: u64,
+    /// The `SubtensorModule` events of the block mentioning the old coldkey.
+    pub events: Vec<EventRecord>,
+}
+
+/// Follows finalized blocks until the swap of `old_coldkey` scheduled with `schedule_swap_coldkey` executes, following
+/// reschedules. The scheduler can defer the swap past its block, so waiting continues while it is still scheduled and
+/// the old coldkey still owns its hotkeys. Fails once [`COLDKEY_SWAP_GRACE_BLOCKS`] more blocks pass without a
+/// `ColdkeySwapped` event, or earlier if the swap is no longer pending.
+pub async fn wait_for_coldkey_swap(
+    client: &Subtensor,
+    old_coldkey: &AccountId,
+) -> Result<ColdkeySwapExecution, SwapError> {
+    let latest = client.blocks().at_latest().await?;
+
+    let (mut execution_block, _) = scheduled_coldkey_swap(client, latest.reference(), old_coldkey)
+        .await?
+        .ok_or_else(|| SwapError::NotScheduled(old_coldkey.clone()))?;
+
+    let owned = api::storage().subtensor_module().owned_hotkeys(old_coldkey);
+    let hotkeys = client
+        .storage()
+        .at(latest.reference())
+        .fetch_or_default(&owned)
+        .await?;
+
+    let filter = EventFilter {
+        coldkey: Some(old_coldkey.clone()),
+        ..Default::default()
+    };
+
+    let mut blocks = client.blocks().subscribe_finalized().await?;
+
+    while let Some(block) = blocks.next().await {
+        let block = block?;
+
+        if block.number() < execution_block {
+            continue;
+        }
+
+        let events = block_events(&block, &filter).await?;
+
+        let swapped = events.iter().find_map(|record| match &record.event {
+            SubtensorEvent::ColdkeySwapped {
+                old_coldkey: swapped,
+                new_coldkey,
+                swap_cost,
+            } if swapped == old_coldkey => Some((new_coldkey.clone(), *swap_cost)),
+            _ => None,
+        });
+
+        if let Some((new_coldkey, swap_cost)) = swapped {
+            return Ok(ColdkeySwapExecution {
+                block_number: block.number(),
+                block_hash: block.hash(),
+                new_coldkey,
+                swap_cost,
+                events,
+            });
+        }
+
+        let (scheduled, still_owned) = try_join!(
+            scheduled_coldkey_swap(client, block.reference(), old_coldkey),
+            client
+                .storage()
+                .at(block.reference())
+                .fetch_or_default(&owned),
+        )?;
+
+        // A swap still pending past its block was deferred rather than dropped
+        let deferred = hotkeys.iter().all(|hotkey| still_owned.contains(hotkey))
+            && block.number() < execution_block.saturating_add(COLDKEY_SWAP_GRACE_BLOCKS);
+
+        match scheduled {
+            Some((rescheduled, _)) if rescheduled > block.number() => execution_block = rescheduled,
+            Some(_) if deferred => {}
+            _ => {
+                return Err(SwapError::NotExecuted {
+                    coldkey: old_coldkey.clone(),
+                    execution_block,
+                })
+            }
+        }
+    }
+
+    Err(SwapError::SubscriptionEnded)
+}
+
+/// A stake of the old coldkey and what the new coldkey holds on the same hotkey and subnet after the swap.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MovedStake {
+    pub hotkey: AccountId,
+    pub netuid: u16,
+    pub before: u64,
+    pub after: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct ColdkeySwapReport {
+    pub execution: ColdkeySwapExecution,
+    pub stakes: Vec<MovedStake>,
+    /// The hotkeys of the plan now owned by the new coldkey.
+    pub moved_hotkeys: Vec<AccountId>,
+    /// The hotkeys of the plan the new coldkey does not own after the swap.
+    pub unmoved_hotkeys: Vec<AccountId>,
+}
+
+/// Compares `plan` against the new coldkey's stakes and hotkeys in the block the swap executed in.
+pub async fn coldkey_swap_report(
+    client: &Subtensor,
+    plan: &ColdkeySwapPlan,
+    execution: ColdkeySwapExecution,
+) -> Result<ColdkeySwapReport, subxt::Error> {
+    let at = BlockRef::from_hash(execution.block_hash);
+    let owned = api::storage()
+        .subtensor_module()
+        .owned_hotkeys(&execution.new_coldkey);
+
+    let (stakes, owned) = try_join!(
+        stake_info(client, at.clone(), execution.new_coldkey.clone()),
+        client.storage().at(at).fetch_or_default(&owned),
+    )?;
+
+    let after: BTreeMap<(&AccountId, u16), u64> = stakes
+        .iter()
+        .map(|stake| ((&stake.hotkey, stake.netuid), stake.stake))
+        .collect();
+
+    let stakes = plan
+        .stakes
+        .iter()
+        .map(|stake| MovedStake {
+            hotkey: stake.hotkey.clone(),
+            netuid: stake.netuid,
+            before: stake.stake,
+            after: after
+                .get(&(&stake.hotkey, stake.netuid))
+                .copied()
+                .unwrap_or_default(),
+        })
+        .collect();
+
+    let (moved_hotkeys, unmoved_hotkeys) = plan
+        .hotkeys
+        .iter()
+        .cloned()
+        .partition(|hotkey| owned.contains(hotkey));
+
+    Ok(ColdkeySwapReport {
+        execution,
+        stakes,
+        moved_hotkeys,
+        unmoved_hotkeys,
+    })
+}
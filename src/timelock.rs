@@ -0,0 +1,191 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sp_core::hashing::sha2_256;
+use thiserror::Error;
+use timelock::curves::drand::TinyBLS381;
+use timelock::ibe::fullident::Identity;
+use timelock::stream_ciphers::AESGCMStreamCipherProvider;
+use timelock::tlock::{tld, tle, EngineBLS, TLECiphertext};
+
+use crate::api::runtime_types::bounded_collections::bounded_vec::BoundedVec;
+use crate::commitments::{self, Data, DataTooLongError, MAX_TIMELOCK_DATA_LENGTH};
+use crate::drand::{self, QUICKNET_PUBLIC_KEY};
+use crate::subtensor::Subtensor;
+use crate::{AccountId, BlockRef};
+
+#[derive(Debug, Error)]
+pub enum TimelockError {
+    #[error("Invalid drand public key or signature encoding")]
+    InvalidPoint,
+
+    #[error("Malformed timelock ciphertext")]
+    InvalidCiphertext,
+
+    #[error("Timelock encryption failed: {0:?}")]
+    Encryption(timelock::tlock::Error),
+
+    #[error("Timelock decryption failed: {0:?}")]
+    Decryption(timelock::tlock::Error),
+
+    #[error(transparent)]
+    DataTooLong(#[from] DataTooLongError),
+}
+
+#[derive(Clone, Debug)]
+pub struct DecryptedCommitment {
+    pub hotkey: AccountId,
+    pub reveal_round: u64,
+    pub plaintext: Vec<u8>,
+}
+
+/// The identity a quicknet round signs, `sha256(round_be)`.
+fn round_identity(round: u64) -> Identity {
+    Identity::new(b"", vec![sha2_256(&round.to_be_bytes()).to_vec()])
+}
+
+/// Encrypts `plaintext` so that it can only be decrypted with the quicknet signature for `reveal_round`.
+pub fn encrypt(plaintext: &[u8], reveal_round: u64) -> Result<Vec<u8>, TimelockError> {
+    let public_key = hex::decode(QUICKNET_PUBLIC_KEY).expect("Quicknet public key is valid hex");
+    let public_key =
+        <TinyBLS381 as EngineBLS>::PublicKeyGroup::deserialize_compressed(public_key.as_slice())
+            .map_err(|_| TimelockError::InvalidPoint)?;
+
+    let mut secret_key = [0; 32];
+    OsRng.fill_bytes(&mut secret_key);
+
+    let ciphertext = tle::<TinyBLS381, AESGCMStreamCipherProvider, _>(
+        public_key,
+        secret_key,
+        plaintext,
+        round_identity(reveal_round),
+        OsRng,
+    )
+    .map_err(TimelockError::Encryption)?;
+
+    let mut encoded = Vec::new();
+    ciphertext
+        .serialize_compressed(&mut encoded)
+        .expect("Serializing to a Vec can not fail");
+
+    Ok(encoded)
+}
+
+/// Decrypts a ciphertext produced by [`encrypt`] with the pulse signature of its reveal round.
+pub fn decrypt(ciphertext: &[u8], signature: &[u8]) -> Result<Vec<u8>, TimelockError> {
+    let ciphertext = TLECiphertext::<TinyBLS381>::deserialize_compressed(ciphertext)
+        .map_err(|_| TimelockError::InvalidCiphertext)?;
+
+    let signature = <TinyBLS381 as EngineBLS>::SignatureGroup::deserialize_compressed(signature)
+        .map_err(|_| TimelockError::InvalidPoint)?;
+
+    tld::<TinyBLS381, AESGCMStreamCipherProvider>(ciphertext, signature)
+        .map_err(TimelockError::Decryption)
+}
+
+/// Builds a `TimelockEncrypted` commitment field, to be passed to [`commitments::commitment_info`].
+pub fn timelock_data(plaintext: &[u8], reveal_round: u64) -> Result<Data, TimelockError> {
+    let encrypted = encrypt(plaintext, reveal_round)?;
+
+    if encrypted.len() > MAX_TIMELOCK_DATA_LENGTH {
+        return Err(DataTooLongError {
+            length: encrypted.len(),
+            max: MAX_TIMELOCK_DATA_LENGTH,
+        }
+        .into());
+    }
+
+    Ok(Data::TimelockEncrypted {
+        encrypted: BoundedVec(encrypted),
+        reveal_round,
+    })
+}
+
+/// Decrypts every timelocked commitment on `netuid` whose reveal round has a pulse stored on chain at the given
+/// block, without waiting for the pallet to reveal it.
+///
+/// Commitments that fail to decrypt are skipped, as the pallet does when revealing them.
+pub async fn decrypt_commitments(
+    client: &Subtensor,
+    at: BlockRef,
+    netuid: u16,
+) -> Result<Vec<DecryptedCommitment>, subxt::Error> {
+    let mut signatures = HashMap::new();
+    let mut decrypted = Vec::new();
+
+    for (hotkey, registration) in commitments::commitments(client, at.clone(), netuid).await? {
+        for field in registration.info.fields.0 {
+            let Data::TimelockEncrypted {
+                encrypted,
+                reveal_round,
+            } = field
+            else {
+                continue;
+            };
+
+            if let Entry::Vacant(entry) = signatures.entry(reveal_round) {
+                let pulse = drand::pulse(client, at.clone(), reveal_round).await?;
+
+                entry.insert(pulse.map(|pulse| pulse.signature.0));
+            }
+
+            let Some(signature) = &signatures[&reveal_round] else {
+                continue;
+            };
+
+            if let Ok(plaintext) = decrypt(&encrypted.0, signature) {
+                decrypted.push(DecryptedCommitment {
+                    hotkey: hotkey.clone(),
+                    reveal_round,
+                    plaintext,
+                });
+            }
+        }
+    }
+
+    Ok(decrypted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Quicknet round 1000, from https://api.drand.sh/52db9ba70e0cc0f6eaf7803dd07447a1f5477735fd3f661792ba94600c84e971/public/1000
+    const ROUND: u64 = 1000;
+    const SIGNATURE: &str =
+        "b44679b9a59af2ec876b1a6b1ad52ea9b1615fc3982b19576350f93447cb1125e342b73a8dd2bacbe47e4b6b63ed5e39";
+
+    #[test]
+    fn decrypts_with_the_round_signature() {
+        let ciphertext = encrypt(b"weights for epoch 7", ROUND).unwrap();
+        let signature = hex::decode(SIGNATURE).unwrap();
+
+        assert_eq!(
+            decrypt(&ciphertext, &signature).unwrap(),
+            b"weights for epoch 7"
+        );
+    }
+
+    #[test]
+    fn fails_to_decrypt_with_another_round_signature() {
+        // Encrypted for the next round, which the round 1000 signature does not unlock
+        let ciphertext = encrypt(b"weights for epoch 7", ROUND + 1).unwrap();
+        let signature = hex::decode(SIGNATURE).unwrap();
+
+        assert!(matches!(
+            decrypt(&ciphertext, &signature),
+            Err(TimelockError::Decryption(_))
+        ));
+        assert!(matches!(
+            decrypt(&ciphertext, &signature[1..]),
+            Err(TimelockError::InvalidPoint)
+        ));
+        assert!(matches!(
+            decrypt(&ciphertext[1..], &signature),
+            Err(TimelockError::InvalidCiphertext)
+        ));
+    }
+}
@@ -0,0 +1,408 @@
+//! TLS between neurons without a certificate authority. An axon publishes the public key of its self-signed
+//! certificate with `serve_axon_tls`, and peers pin their connections to the published key.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use rcgen::{CertificateParams, KeyPair, PKCS_ED25519};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{
+    ring, verify_tls12_signature, verify_tls13_signature, WebPkiSupportedAlgorithms,
+};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName, UnixTime};
+use rustls::{ClientConfig, DigitallySignedStruct, ServerConfig, SignatureScheme};
+use thiserror::Error;
+use x509_parser::oid_registry::OID_SIG_ED25519;
+
+use crate::api::runtime_types::pallet_subtensor::pallet::NeuronCertificate;
+use crate::axon::neuron_certificate;
+use crate::subtensor::Subtensor;
+use crate::{AccountId, BlockRef};
+
+/// The algorithm byte of Ed25519 certificates, the only kind crabtensor generates and pins.
+///
+/// The chain assigns no meaning to this byte: pallet-subtensor's `NeuronCertificate` (`TryFrom<Vec<u8>>` in
+/// `pallets/subtensor/src/lib.rs`) stores the first byte of the `serve_axon_tls` certificate as `algorithm` and the
+/// rest as `public_key`, and bittensor's `Certificate` (`bittensor/utils/__init__.py`) prepends whatever byte the
+/// caller passes. 1 is therefore a convention, which peers must share to pin each other.
+pub const ED25519_ALGORITHM: u8 = 1;
+
+#[derive(Debug, Error)]
+pub enum TlsError {
+    // Boxed, as it is large and only `pinned_verifier` returns it
+    #[error(transparent)]
+    Subxt(Box<subxt::Error>),
+
+    #[error(transparent)]
+    Rcgen(#[from] rcgen::Error),
+
+    #[error(transparent)]
+    Rustls(#[from] rustls::Error),
+
+    #[error(transparent)]
+    Pem(#[from] rustls::pki_types::pem::Error),
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error("Invalid certificate: {0}")]
+    InvalidCertificate(String),
+
+    #[error("Unsupported certificate algorithm {0}, only Ed25519 is supported")]
+    UnsupportedAlgorithm(u8),
+
+    #[error("No certificate published for {hotkey} on subnet {netuid}")]
+    NoCertificate { netuid: u16, hotkey: AccountId },
+}
+
+impl From<subxt::Error> for TlsError {
+    fn from(error: subxt::Error) -> Self {
+        Self::Subxt(Box::new(error))
+    }
+}
+
+/// The raw Ed25519 public key of `certificate`.
+fn ed25519_public_key(certificate: &CertificateDer) -> Result<Vec<u8>, TlsError> {
+    let (_, certificate) = x509_parser::parse_x509_certificate(certificate)
+        .map_err(|error| TlsError::InvalidCertificate(error.to_string()))?;
+
+    let public_key = certificate.public_key();
+
+    if public_key.algorithm.algorithm != OID_SIG_ED25519 {
+        return Err(TlsError::InvalidCertificate(format!(
+            "expected an Ed25519 key, got {}",
+            public_key.algorithm.algorithm
+        )));
+    }
+
+    Ok(public_key.subject_public_key.data.to_vec())
+}
+
+fn provider() -> Arc<rustls::crypto::CryptoProvider> {
+    Arc::new(ring::default_provider())
+}
+
+/// The self-signed certificate and key an axon serves TLS with.
+pub struct NeuronIdentity {
+    certificate: CertificateDer<'static>,
+    private_key: PrivatePkcs8KeyDer<'static>,
+    public_key: Vec<u8>,
+}
+
+impl NeuronIdentity {
+    /// Generates an Ed25519 key and a self-signed certificate for `subject_alt_names`, which can be empty as peers
+    /// pin the key rather than check names.
+    pub fn generate(subject_alt_names: Vec<String>) -> Result<Self, TlsError> {
+        let key_pair = KeyPair::generate_for(&PKCS_ED25519)?;
+        let certificate = CertificateParams::new(subject_alt_names)?.self_signed(&key_pair)?;
+
+        Ok(Self {
+            certificate: certificate.der().clone(),
+            private_key: PrivatePkcs8KeyDer::from(key_pair.serialize_der()),
+            public_key: key_pair.public_key_raw().to_vec(),
+        })
+    }
+
+    /// Loads a PEM certificate and PKCS#8 key, as written by [`NeuronIdentity::save`].
+    pub fn load(
+        certificate_path: impl AsRef<Path>,
+        key_path: impl AsRef<Path>,
+    ) -> Result<Self, TlsError> {
+        let certificate = CertificateDer::from_pem_file(certificate_path)?;
+        let private_key = PrivatePkcs8KeyDer::from_pem_file(key_path)?;
+        let public_key = ed25519_public_key(&certificate)?;
+
+        Ok(Self {
+            certificate,
+            private_key,
+            public_key,
+        })
+    }
+
+    /// Writes the certificate and key as PEM, the key only readable by its owner on unix. Fails if the key exists.
+    pub fn save(
+        &self,
+        certificate_path: impl AsRef<Path>,
+        key_path: impl AsRef<Path>,
+    ) -> Result<(), TlsError> {
+        let key_pem = pem::encode(&pem::Pem::new(
+            "PRIVATE KEY",
+            self.private_key.secret_pkcs8_der(),
+        ));
+        let certificate_pem = pem::encode(&pem::Pem::new("CERTIFICATE", self.certificate.to_vec()));
+
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+        options.open(key_path)?.write_all(key_pem.as_bytes())?;
+        fs::write(certificate_path, certificate_pem)?;
+
+        Ok(())
+    }
+
+    pub fn certificate(&self) -> &CertificateDer<'static> {
+        &self.certificate
+    }
+
+    pub fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    /// The certificate argument of `axon::serve_axon_tls_payload`, the algorithm byte followed by the public key.
+    pub fn neuron_certificate(&self) -> Vec<u8> {
+        let mut certificate = Vec::with_capacity(self.public_key.len() + 1);
+        certificate.push(ED25519_ALGORITHM);
+        certificate.extend_from_slice(&self.public_key);
+
+        certificate
+    }
+
+    /// A server configuration for the axon, without client authentication.
+    pub fn server_config(&self) -> Result<ServerConfig, TlsError> {
+        Ok(ServerConfig::builder_with_provider(provider())
+            .with_safe_default_protocol_versions()?
+            .with_no_client_auth()
+            .with_single_cert(
+                vec![self.certificate.clone()],
+                PrivateKeyDer::Pkcs8(self.private_key.clone_key()),
+            )?)
+    }
+}
+
+/// Accepts a server only if its certificate holds the public key a neuron published on chain, ignoring names, expiry
+/// and issuers, which mean nothing for self-signed neuron certificates.
+#[derive(Debug)]
+pub struct PinnedCertificateVerifier {
+    public_key: Vec<u8>,
+    algorithms: WebPkiSupportedAlgorithms,
+}
+
+impl PinnedCertificateVerifier {
+    pub fn new(certificate: &NeuronCertificate) -> Result<Self, TlsError> {
+        if certificate.algorithm != ED25519_ALGORITHM {
+            return Err(TlsError::UnsupportedAlgorithm(certificate.algorithm));
+        }
+
+        Ok(Self {
+            public_key: certificate.public_key.0.clone(),
+            algorithms: ring::default_provider().signature_verification_algorithms,
+        })
+    }
+
+    /// A client configuration trusting only the pinned certificate.
+    pub fn client_config(self) -> Result<ClientConfig, TlsError> {
+        Ok(ClientConfig::builder_with_provider(provider())
+            .with_safe_default_protocol_versions()?
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(self))
+            .with_no_client_auth())
+    }
+}
+
+impl ServerCertVerifier for PinnedCertificateVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let public_key = ed25519_public_key(end_entity).map_err(|_| {
+            rustls::Error::InvalidCertificate(rustls::CertificateError::BadEncoding)
+        })?;
+
+        if public_key != self.public_key {
+            return Err(rustls::Error::InvalidCertificate(
+                rustls::CertificateError::ApplicationVerificationFailure,
+            ));
+        }
+
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        certificate: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, certificate, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        certificate: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, certificate, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
+
+/// A verifier pinned to the certificate `hotkey` published on `netuid`.
+pub async fn pinned_verifier(
+    client: &Subtensor,
+    at: BlockRef,
+    netuid: u16,
+    hotkey: &AccountId,
+) -> Result<PinnedCertificateVerifier, TlsError> {
+    let certificate = neuron_certificate(client, at, netuid, hotkey)
+        .await?
+        .ok_or_else(|| TlsError::NoCertificate {
+            netuid,
+            hotkey: hotkey.clone(),
+        })?;
+
+    PinnedCertificateVerifier::new(&certificate)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use rcgen::PKCS_ECDSA_P256_SHA256;
+    use rustls::{CertificateError, ClientConnection, Connection, ServerConnection};
+
+    use super::*;
+    use crate::api::runtime_types::bounded_collections::bounded_vec::BoundedVec;
+
+    fn pinned(identity: &NeuronIdentity) -> ClientConfig {
+        PinnedCertificateVerifier::new(&NeuronCertificate {
+            public_key: BoundedVec(identity.public_key().to_vec()),
+            algorithm: ED25519_ALGORITHM,
+        })
+        .unwrap()
+        .client_config()
+        .unwrap()
+    }
+
+    fn transfer(from: &mut Connection, to: &mut Connection) -> Result<(), rustls::Error> {
+        let mut buffer = Vec::new();
+        from.write_tls(&mut buffer).unwrap();
+
+        let mut records = buffer.as_slice();
+        while !records.is_empty() {
+            to.read_tls(&mut records).unwrap();
+        }
+
+        to.process_new_packets().map(|_| ())
+    }
+
+    /// Runs a handshake in memory, returning the client's error if it rejects the server.
+    fn handshake(client: ClientConfig, server: ServerConfig) -> Result<(), rustls::Error> {
+        let name = ServerName::try_from("localhost").unwrap();
+        let mut client = Connection::from(ClientConnection::new(Arc::new(client), name).unwrap());
+        let mut server = Connection::from(ServerConnection::new(Arc::new(server)).unwrap());
+
+        for _ in 0..10 {
+            if !client.is_handshaking() && !server.is_handshaking() {
+                return Ok(());
+            }
+
+            transfer(&mut client, &mut server).unwrap();
+            transfer(&mut server, &mut client)?;
+        }
+
+        panic!("handshake did not complete");
+    }
+
+    #[test]
+    fn connects_to_the_pinned_key() {
+        let identity = NeuronIdentity::generate(vec!["localhost".to_string()]).unwrap();
+
+        handshake(pinned(&identity), identity.server_config().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn rejects_another_key() {
+        let identity = NeuronIdentity::generate(Vec::new()).unwrap();
+        let other = NeuronIdentity::generate(Vec::new()).unwrap();
+
+        assert_eq!(
+            handshake(pinned(&other), identity.server_config().unwrap()),
+            Err(rustls::Error::InvalidCertificate(
+                CertificateError::ApplicationVerificationFailure
+            ))
+        );
+    }
+
+    #[test]
+    fn rejects_non_ed25519_certificates() {
+        let identity = NeuronIdentity::generate(Vec::new()).unwrap();
+        let key_pair = KeyPair::generate_for(&PKCS_ECDSA_P256_SHA256).unwrap();
+        let certificate = CertificateParams::new(vec!["localhost".to_string()])
+            .unwrap()
+            .self_signed(&key_pair)
+            .unwrap();
+
+        let server = ServerConfig::builder_with_provider(provider())
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(
+                vec![certificate.der().clone()],
+                PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key_pair.serialize_der())),
+            )
+            .unwrap();
+
+        assert_eq!(
+            handshake(pinned(&identity), server),
+            Err(rustls::Error::InvalidCertificate(
+                CertificateError::BadEncoding
+            ))
+        );
+    }
+
+    #[test]
+    fn pins_only_ed25519() {
+        let result = PinnedCertificateVerifier::new(&NeuronCertificate {
+            public_key: BoundedVec(vec![0; 32]),
+            algorithm: ED25519_ALGORITHM + 1,
+        });
+
+        assert!(matches!(result, Err(TlsError::UnsupportedAlgorithm(2))));
+    }
+
+    #[test]
+    fn saves_the_key_for_its_owner_only() {
+        let directory = std::env::temp_dir().join(format!("crabtensor-tls-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let certificate_path = directory.join("certificate.pem");
+        let key_path = directory.join("key.pem");
+
+        let identity = NeuronIdentity::generate(Vec::new()).unwrap();
+        identity.save(&certificate_path, &key_path).unwrap();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = fs::metadata(&key_path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let overwritten = NeuronIdentity::generate(Vec::new())
+            .unwrap()
+            .save(&certificate_path, &key_path);
+        assert!(
+            matches!(overwritten, Err(TlsError::Io(error)) if error.kind() == ErrorKind::AlreadyExists)
+        );
+
+        let loaded = NeuronIdentity::load(&certificate_path, &key_path).unwrap();
+        assert_eq!(loaded.public_key(), identity.public_key());
+        assert_eq!(loaded.neuron_certificate(), identity.neuron_certificate());
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,170 @@
+use std::sync::Arc;
+
+use subxt::client::RuntimeVersion;
+use subxt::Metadata;
+use tokio::sync::{broadcast, watch};
+use tokio::task::JoinHandle;
+
+use crate::subtensor::Subtensor;
+use crate::{api, METADATA_CALL_HASHES, METADATA_CONSTANT_HASHES, METADATA_STORAGE_HASHES};
+
+#[derive(Clone, Debug)]
+pub struct PalletCompatibility {
+    pub pallet: String,
+    /// Whether every call, storage entry and constant generated for the pallet is still valid.
+    pub unchanged: bool,
+    /// Generated calls whose hash changed or which were removed, and which would be rejected when submitted.
+    pub changed_calls: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct RuntimeUpgrade {
+    pub previous: RuntimeVersion,
+    pub current: RuntimeVersion,
+    /// Whether the compiled-in codegen as a whole is valid for the new metadata.
+    pub codegen_valid: bool,
+    /// Every compiled-in pallet, compared between the compiled-in codegen and the metadata after the upgrade.
+    pub pallets: Vec<PalletCompatibility>,
+}
+
+impl RuntimeUpgrade {
+    pub fn changed_pallets(&self) -> impl Iterator<Item = &PalletCompatibility> {
+        self.pallets.iter().filter(|pallet| !pallet.unchanged)
+    }
+}
+
+struct Inner {
+    pause_on_upgrade: bool,
+    paused: watch::Sender<bool>,
+    upgrades: broadcast::Sender<RuntimeUpgrade>,
+}
+
+/// Applies runtime upgrades to clients as they are finalized, swapping in the new metadata and reporting how the
+/// compiled-in codegen is affected.
+///
+/// Upgrades are detected from the node's runtime version subscription, and applied once `System::LastRuntimeUpgrade`
+/// shows the new version in a finalized block. When pausing on upgrade, submission code is expected to await
+/// [`UpgradeWatcher::wait_until_acknowledged`] before submitting.
+#[derive(Clone)]
+pub struct UpgradeWatcher {
+    inner: Arc<Inner>,
+}
+
+impl UpgradeWatcher {
+    pub fn new(pause_on_upgrade: bool) -> Self {
+        let (upgrades, _) = broadcast::channel(16);
+
+        Self {
+            inner: Arc::new(Inner {
+                pause_on_upgrade,
+                paused: watch::Sender::new(false),
+                upgrades,
+            }),
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<RuntimeUpgrade> {
+        self.inner.upgrades.subscribe()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.inner.paused.borrow()
+    }
+
+    /// Resumes submission after an upgrade was reviewed.
+    pub fn acknowledge(&self) {
+        self.inner.paused.send_replace(false);
+    }
+
+    /// Waits until no upgrade is pending acknowledgement, returning immediately if none is.
+    pub async fn wait_until_acknowledged(&self) {
+        let mut paused = self.inner.paused.subscribe();
+
+        // The sender lives as long as `self`, so this can not fail
+        let _ = paused.wait_for(|paused| !paused).await;
+    }
+
+    /// Watches `client` for upgrades in the background until its runtime version subscription ends or fails.
+    pub fn spawn(&self, client: &Subtensor) -> JoinHandle<Result<(), subxt::Error>> {
+        let watcher = self.clone();
+        let client = client.clone();
+
+        tokio::spawn(async move {
+            let updater = client.updater();
+            let mut updates = updater.runtime_updates().await?;
+
+            while let Some(update) = updates.next().await {
+                let update = update?;
+
+                let previous = client.runtime_version();
+
+                // The subscription starts with the current version, which is not an upgrade
+                if updater.apply_update(update).is_err() {
+                    continue;
+                }
+
+                let metadata = client.metadata();
+
+                let upgrade = RuntimeUpgrade {
+                    previous,
+                    current: client.runtime_version(),
+                    codegen_valid: api::is_codegen_valid_for(&metadata),
+                    pallets: compare_pallets(&metadata),
+                };
+
+                if watcher.inner.pause_on_upgrade {
+                    watcher.inner.paused.send_replace(true);
+                }
+
+                let _ = watcher.inner.upgrades.send(upgrade);
+            }
+
+            Ok(())
+        })
+    }
+}
+
+/// Compares every compiled-in pallet against `metadata`, using the validation hashes generated for its calls, storage
+/// entries and constants.
+pub fn compare_pallets(metadata: &Metadata) -> Vec<PalletCompatibility> {
+    api::PALLETS
+        .iter()
+        .map(|&name| {
+            let pallet = metadata.pallet_by_name(name);
+
+            let changed_calls = changed_items(METADATA_CALL_HASHES, name, |item| {
+                pallet.as_ref()?.call_hash(item)
+            });
+
+            let unchanged = pallet.is_some()
+                && changed_calls.is_empty()
+                && changed_items(METADATA_STORAGE_HASHES, name, |item| {
+                    pallet.as_ref()?.storage_hash(item)
+                })
+                .is_empty()
+                && changed_items(METADATA_CONSTANT_HASHES, name, |item| {
+                    pallet.as_ref()?.constant_hash(item)
+                })
+                .is_empty();
+
+            PalletCompatibility {
+                pallet: name.to_string(),
+                unchanged,
+                changed_calls,
+            }
+        })
+        .collect()
+}
+
+/// The generated items of `pallet` whose validation hash differs from `hash_of` the item, or which it does not find.
+fn changed_items(
+    hashes: &[(&str, &str, [u8; 32])],
+    pallet: &str,
+    hash_of: impl Fn(&str) -> Option<[u8; 32]>,
+) -> Vec<String> {
+    hashes
+        .iter()
+        .filter(|(item_pallet, item, hash)| *item_pallet == pallet && hash_of(item) != Some(*hash))
+        .map(|(_, item, _)| item.to_string())
+        .collect()
+}
@@ -1,5 +1,5 @@
 use std::fmt::{Display, Formatter};
-use std::fs::File;
+use std::fs::{self, File, OpenOptions};
 use std::io;
 use std::path::{Path, PathBuf};
 
@@ -51,18 +51,31 @@ pub fn hotkey_location(
     wallet_path
 }
 
-pub fn home_hotkey_location(
-    wallet_name: impl AsRef<Path>,
-    hotkey_name: impl AsRef<Path>,
-) -> Option<PathBuf> {
+pub fn coldkeypub_location(mut wallet_path: PathBuf, wallet_name: impl AsRef<Path>) -> PathBuf {
+    wallet_path.push(wallet_name);
+    wallet_path.push("coldkeypub.txt");
+
+    wallet_path
+}
+
+/// The directory btcli keeps wallets in, `~/.bittensor/wallets`.
+pub fn home_wallets_location() -> Option<PathBuf> {
     dirs::home_dir().map(|mut wallet_path| {
         wallet_path.push(".bittensor");
         wallet_path.push("wallets");
 
-        hotkey_location(wallet_path, wallet_name, hotkey_name)
+        wallet_path
     })
 }
 
+pub fn home_hotkey_location(
+    wallet_name: impl AsRef<Path>,
+    hotkey_name: impl AsRef<Path>,
+) -> Option<PathBuf> {
+    home_wallets_location()
+        .map(|wallet_path| hotkey_location(wallet_path, wallet_name, hotkey_name))
+}
+
 pub fn load_key_seed(path: impl AsRef<Path>) -> Result<[u8; 32], AccountLoadingError> {
     let json: Value = serde_json::from_reader(File::open(&path)?)?;
 
@@ -97,6 +110,37 @@ pub fn load_key_account_id(path: impl AsRef<Path>) -> Result<AccountId, AccountL
     Ok(AccountId::from(decoded))
 }
 
+/// Generates a new key and writes it unencrypted to `path` in the btcli key file format, the way btcli stores
+/// hotkeys, returning it along with its recovery phrase. Fails if `path` exists.
+pub fn generate_key_file(path: impl AsRef<Path>) -> Result<(Signer, String), AccountLoadingError> {
+    let (pair, phrase, seed) = sr25519::Pair::generate_with_phrase(None);
+    let public = pair.public();
+    let account_id = AccountId::from(public.0);
+
+    let json = serde_json::json!({
+        "accountId": format!("0x{}", hex::encode(public)),
+        "publicKey": format!("0x{}", hex::encode(public)),
+        "privateKey": format!("0x{}", hex::encode(pair.to_raw_vec())),
+        "secretPhrase": phrase,
+        "secretSeed": format!("0x{}", hex::encode(seed)),
+        "ss58Address": account_id.to_string(),
+    });
+
+    if let Some(parent) = path.as_ref().parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    serde_json::to_writer(options.open(path)?, &json)?;
+
+    Ok((Signer::new(pair), phrase))
+}
+
 pub fn signer_from_seed(seed: &[u8]) -> Result<Signer, SecretStringError> {
     Ok(Signer::new(sr25519::Pair::from_seed_slice(seed)?))
 }
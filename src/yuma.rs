@@ -0,0 +1,834 @@
+//! An offline implementation of the subtensor epoch, computing what Yuma consensus would store for a subnet.
+//!
+//! The pallet computes in `I32F32` fixed-point while this uses `f64`, so results agree with the chain within the
+//! rounding of the stored `u16` values rather than bit for bit.
+
+use subxt::ext::futures::try_join;
+use thiserror::Error;
+
+use crate::api::runtime_types::pallet_subtensor::rpc_info::metagraph::Metagraph;
+use crate::subtensor::Subtensor;
+use crate::{api, dynamic, matrix, AccountId, BlockRef};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum YumaVersion {
+    Yuma2,
+    Yuma3,
+}
+
+/// An [`EpochInput`] vector whose length does not match the number of uids, which is the length of `stake`.
+#[derive(Debug, Error)]
+#[error("EpochInput::{field} has {length} entries, expected one for each of the {uids} uids")]
+pub struct InputLengthError {
+    pub field: &'static str,
+    pub length: usize,
+    pub uids: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct EpochParams {
+    pub version: YumaVersion,
+    pub kappa: u16,
+    pub activity_cutoff: u16,
+    pub max_validators: u16,
+    pub bonds_moving_avg: u64,
+    pub bonds_penalty: u16,
+    pub liquid_alpha_enabled: bool,
+    pub alpha_low: u16,
+    pub alpha_high: u16,
+    /// As stored in `AlphaSigmoidSteepness`, which the pallet scales down by 100.
+    pub alpha_sigmoid_steepness: f64,
+    /// Total stake below which a hotkey is not considered as a validator.
+    pub stake_threshold: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct EpochInput {
+    pub params: EpochParams,
+    /// The block the epoch runs in.
+    pub block: u64,
+    /// The block the previous epoch ran in.
+    pub last_step: u64,
+    pub owner_uid: Option<u16>,
+    /// The stake weight of each uid, alpha stake plus TAO stake scaled by the TAO weight.
+    pub stake: Vec<u64>,
+    pub validator_permit: Vec<bool>,
+    pub last_update: Vec<u64>,
+    pub block_at_registration: Vec<u64>,
+    /// The `Weights` row of each uid, as pairs of target uid and weight.
+    pub weights: Vec<Vec<(u16, u16)>>,
+    /// The `Bonds` row of each uid, as pairs of target uid and bond.
+    pub bonds: Vec<Vec<(u16, u16)>>,
+}
+
+impl EpochInput {
+    /// Builds the input of the epoch following the block `metagraph` was read at.
+    pub fn from_metagraph(
+        metagraph: &Metagraph<AccountId>,
+        params: EpochParams,
+        weights: Vec<Vec<(u16, u16)>>,
+        bonds: Vec<Vec<(u16, u16)>>,
+    ) -> Self {
+        let owner_uid = metagraph
+            .hotkeys
+            .iter()
+            .position(|hotkey| *hotkey == metagraph.owner_hotkey)
+            .map(|uid| uid as u16);
+
+        Self {
+            params,
+            block: metagraph.block + 1,
+            last_step: metagraph.last_step,
+            owner_uid,
+            stake: metagraph.total_stake.iter().map(|stake| stake.0).collect(),
+            validator_permit: metagraph.validator_permit.clone(),
+            last_update: metagraph.last_update.iter().map(|block| block.0).collect(),
+            block_at_registration: metagraph
+                .block_at_registration
+                .iter()
+                .map(|block| block.0)
+                .collect(),
+            weights,
+            bonds,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Epoch {
+    pub active: Vec<bool>,
+    /// The validator permits the epoch grants, which apply from the next epoch on.
+    pub validator_permit: Vec<bool>,
+    pub ranks: Vec<f64>,
+    pub trust: Vec<f64>,
+    pub consensus: Vec<f64>,
+    pub incentive: Vec<f64>,
+    pub dividends: Vec<f64>,
+    pub validator_trust: Vec<f64>,
+    /// The bonds as proportions in the form they are stored, column max-upscaled for Yuma2.
+    pub bonds: Vec<Vec<(u16, f64)>>,
+}
+
+impl Epoch {
+    /// The bonds as the pallet stores them in `Bonds`.
+    pub fn stored_bonds(&self) -> Vec<Vec<(u16, u16)>> {
+        self.bonds
+            .iter()
+            .map(|row| row.iter().map(|&(uid, bond)| (uid, to_u16(bond))).collect())
+            .collect()
+    }
+
+    /// The largest difference between the stored form of the ranks, trust, consensus, incentive and dividends and
+    /// those of `metagraph`, which should be read right after the epoch ran.
+    pub fn max_deviation(&self, metagraph: &Metagraph<AccountId>) -> u16 {
+        [
+            (&self.ranks, &metagraph.rank),
+            (&self.trust, &metagraph.trust),
+            (&self.consensus, &metagraph.consensus),
+            (&self.incentive, &metagraph.incentives),
+            (&self.dividends, &metagraph.dividends),
+        ]
+        .into_iter()
+        .flat_map(|(computed, stored)| {
+            computed
+                .iter()
+                .zip(stored)
+                .map(|(&computed, stored)| to_u16(computed).abs_diff(stored.0))
+        })
+        .max()
+        .unwrap_or(0)
+    }
+}
+
+/// Mirrors `fixed_proportion_to_u16`, which truncates.
+pub fn to_u16(proportion: f64) -> u16 {
+    (proportion * u16::MAX as f64).clamp(0.0, u16::MAX as f64) as u16
+}
+
+/// Reads the input of the epoch that would run in the block after `at`.
+pub async fn load(
+    client: &Subtensor,
+    at: BlockRef,
+    netuid: u16,
+) -> Result<Option<EpochInput>, subxt::Error> {
+    let Some(metagraph) = dynamic::metagraph(client, at.clone(), netuid).await? else {
+        return Ok(None);
+    };
+
+    let storage = client.storage().at(at.clone());
+    let subtensor_module = api::storage().subtensor_module();
+
+    let yuma3 = storage
+        .fetch_or_default(&subtensor_module.yuma3_on(netuid))
+        .await?;
+
+    let params = EpochParams {
+        version: if yuma3 {
+            YumaVersion::Yuma3
+        } else {
+            YumaVersion::Yuma2
+        },
+        kappa: metagraph.kappa,
+        activity_cutoff: metagraph.activity_cutoff,
+        max_validators: metagraph.max_validators,
+        bonds_moving_avg: metagraph.bonds_moving_avg,
+        bonds_penalty: storage
+            .fetch_or_default(&subtensor_module.bonds_penalty(netuid))
+            .await?,
+        liquid_alpha_enabled: metagraph.liquid_alpha_enabled,
+        alpha_low: metagraph.alpha_low,
+        alpha_high: metagraph.alpha_high,
+        alpha_sigmoid_steepness: storage
+            .fetch_or_default(&subtensor_module.alpha_sigmoid_steepness(netuid))
+            .await? as f64,
+        stake_threshold: storage
+            .fetch_or_default(&subtensor_module.stake_threshold())
+            .await?,
+    };
+
+    let (weights, bonds) = try_join!(
+        matrix::weights(client, at.clone(), netuid),
+        matrix::bonds(client, at, netuid),
+    )?;
+
+    Ok(Some(EpochInput::from_metagraph(
+        &metagraph,
+        params,
+        weights.into_rows(),
+        bonds.into_rows(),
+    )))
+}
+
+/// Runs the epoch the way `Pallet::epoch` does.
+pub fn simulate(input: &EpochInput) -> Result<Epoch, InputLengthError> {
+    let params = &input.params;
+    let n = input.stake.len();
+
+    for (field, length) in [
+        ("validator_permit", input.validator_permit.len()),
+        ("last_update", input.last_update.len()),
+        ("block_at_registration", input.block_at_registration.len()),
+        ("weights", input.weights.len()),
+        ("bonds", input.bonds.len()),
+    ] {
+        if length != n {
+            return Err(InputLengthError {
+                field,
+                length,
+                uids: n,
+            });
+        }
+    }
+
+    let active: Vec<bool> = input
+        .last_update
+        .iter()
+        .map(|&updated| updated.saturating_add(params.activity_cutoff as u64) >= input.block)
+        .collect();
+
+    let mut stake: Vec<f64> = input
+        .stake
+        .iter()
+        .map(|&stake| {
+            if stake < params.stake_threshold {
+                0.0
+            } else {
+                stake as f64
+            }
+        })
+        .collect();
+
+    normalize(&mut stake);
+
+    let validator_permit = top_k_nonzero(&stake, params.max_validators as usize);
+    let permitted = |uid: usize| input.validator_permit[uid];
+
+    let mut active_stake: Vec<f64> = (0..n)
+        .map(|uid| {
+            if active[uid] && permitted(uid) {
+                stake[uid]
+            } else {
+                0.0
+            }
+        })
+        .collect();
+
+    normalize(&mut active_stake);
+
+    // Weights from validators without a permit, to themselves (except for the owner) and to uids registered since
+    // the validator last set weights are dropped
+    let mut weights = vec![vec![0.0; n]; n];
+
+    for (i, row) in input.weights.iter().enumerate().take(n) {
+        if !permitted(i) {
+            continue;
+        }
+
+        for &(j, weight) in row {
+            let j = j as usize;
+
+            if j >= n
+                || (i == j && input.owner_uid != Some(j as u16))
+                || input.last_update[i] <= input.block_at_registration[j]
+            {
+                continue;
+            }
+
+            weights[i][j] = weight as f64;
+        }
+
+        normalize(&mut weights[i]);
+    }
+
+    let preranks = weighted_column_sum(&weights, &active_stake);
+
+    let kappa = params.kappa as f64 / u16::MAX as f64;
+    let consensus = weighted_median_columns(&active_stake, &weights, kappa);
+
+    let clipped: Vec<Vec<f64>> = weights
+        .iter()
+        .map(|row| {
+            row.iter()
+                .zip(&consensus)
+                .map(|(&weight, &consensus)| weight.min(consensus))
+                .collect()
+        })
+        .collect();
+
+    let validator_trust: Vec<f64> = clipped.iter().map(|row| row.iter().sum()).collect();
+
+    let mut ranks = weighted_column_sum(&clipped, &active_stake);
+
+    let trust: Vec<f64> = ranks
+        .iter()
+        .zip(&preranks)
+        .map(|(&rank, &prerank)| if prerank == 0.0 { 0.0 } else { rank / prerank })
+        .collect();
+
+    normalize(&mut ranks);
+    let incentive = ranks.clone();
+
+    // The bonds penalty interpolates between the raw weights (no penalty) and the clipped ones (full penalty)
+    let bonds_penalty = params.bonds_penalty as f64 / u16::MAX as f64;
+
+    let weights_for_bonds: Vec<Vec<f64>> = weights
+        .iter()
+        .zip(&clipped)
+        .map(|(row, clipped)| {
+            row.iter()
+                .zip(clipped)
+                .map(|(&weight, &clipped)| weight + bonds_penalty * (clipped - weight))
+                .collect()
+        })
+        .collect();
+
+    let (bonds, dividends) = match params.version {
+        YumaVersion::Yuma2 => yuma2_bonds(
+            input,
+            &weights_for_bonds,
+            &active_stake,
+            &consensus,
+            &incentive,
+        ),
+        YumaVersion::Yuma3 => yuma3_bonds(
+            input,
+            &weights_for_bonds,
+            &active_stake,
+            &consensus,
+            &incentive,
+        ),
+    };
+
+    let bonds = bonds
+        .into_iter()
+        .map(|row| {
+            row.into_iter()
+                .enumerate()
+                .filter(|&(_, bond)| bond > 0.0)
+                .map(|(uid, bond)| (uid as u16, bond))
+                .collect()
+        })
+        .collect();
+
+    Ok(Epoch {
+        active,
+        validator_permit,
+        ranks,
+        trust,
+        consensus,
+        incentive,
+        dividends,
+        validator_trust,
+        bonds,
+    })
+}
+
+fn yuma2_bonds(
+    input: &EpochInput,
+    weights: &[Vec<f64>],
+    active_stake: &[f64],
+    consensus: &[f64],
+    incentive: &[f64],
+) -> (Vec<Vec<f64>>, Vec<f64>) {
+    let n = weights.len();
+
+    // Bonds to uids registered since the validator last set weights are dropped
+    let mut bonds = dense(&input.bonds, n, |i, j, bond| {
+        (input.last_update[i] > input.block_at_registration[j]).then_some(bond as f64)
+    });
+
+    normalize_columns(&mut bonds);
+
+    let mut delta: Vec<Vec<f64>> = weights
+        .iter()
+        .zip(active_stake)
+        .map(|(row, &stake)| row.iter().map(|&weight| weight * stake).collect())
+        .collect();
+
+    normalize_columns(&mut delta);
+
+    let alphas = yuma2_liquid_alpha(&input.params, consensus)
+        .unwrap_or_else(|| vec![disabled_liquid_alpha(&input.params); n]);
+
+    let mut ema: Vec<Vec<f64>> = delta
+        .iter()
+        .zip(&bonds)
+        .map(|(delta, bonds)| {
+            delta
+                .iter()
+                .zip(bonds)
+                .zip(&alphas)
+                .map(|((&new, &old), &alpha)| alpha * new + (1.0 - alpha) * old)
+                .collect()
+        })
+        .collect();
+
+    normalize_columns(&mut ema);
+
+    let mut dividends = weighted_row_sum(&ema, incentive);
+    normalize(&mut dividends);
+
+    // Stored bonds are upscaled so that the largest bond to each uid is 1
+    for j in 0..n {
+        let max = ema.iter().map(|row| row[j]).fold(0.0, f64::max);
+
+        if max > 0.0 {
+            for row in &mut ema {
+                row[j] /= max;
+            }
+        }
+    }
+
+    (ema, dividends)
+}
+
+fn yuma3_bonds(
+    input: &EpochInput,
+    weights: &[Vec<f64>],
+    active_stake: &[f64],
+    consensus: &[f64],
+    incentive: &[f64],
+) -> (Vec<Vec<f64>>, Vec<f64>) {
+    let params = &input.params;
+    let n = weights.len();
+
+    // Bonds to uids registered since the last epoch are dropped
+    let bonds = dense(&input.bonds, n, |_, j, bond| {
+        (input.last_step > input.block_at_registration[j]).then_some(bond as f64 / u16::MAX as f64)
+    });
+
+    let liquid = params.liquid_alpha_enabled && consensus.iter().any(|&c| c != 0.0);
+    let disabled_alpha = disabled_liquid_alpha(params);
+    let (alpha_low, alpha_high) = alpha_bounds(params);
+
+    let ema: Vec<Vec<f64>> = weights
+        .iter()
+        .zip(&bonds)
+        .map(|(weights, bonds)| {
+            weights
+                .iter()
+                .zip(bonds)
+                .zip(consensus)
+                .map(|((&weight, &bond), &consensus)| {
+                    let alpha = if liquid {
+                        alpha_sigmoid(
+                            consensus,
+                            weight,
+                            bond,
+                            alpha_low,
+                            alpha_high,
+                            params.alpha_sigmoid_steepness,
+                        )
+                    } else {
+                        disabled_alpha
+                    };
+
+                    // Bonds decay by alpha, and grow by at most alpha times the weight up to a total of 1
+                    let decayed = (1.0 - alpha) * bond;
+                    let remaining = (1.0 - decayed).max(0.0);
+
+                    decayed + (alpha * weight).max(0.0).min(remaining)
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut ema_normalized = ema.clone();
+    normalize_columns(&mut ema_normalized);
+
+    let mut dividends: Vec<f64> = weighted_row_sum(&ema_normalized, incentive)
+        .into_iter()
+        .zip(active_stake)
+        .map(|(bonds, &stake)| bonds * stake)
+        .collect();
+
+    normalize(&mut dividends);
+
+    (ema, dividends)
+}
+
+fn disabled_liquid_alpha(params: &EpochParams) -> f64 {
+    1.0 - params.bonds_moving_avg as f64 / 1_000_000.0
+}
+
+fn alpha_bounds(params: &EpochParams) -> (f64, f64) {
+    (
+        params.alpha_low as f64 / u16::MAX as f64,
+        params.alpha_high as f64 / u16::MAX as f64,
+    )
+}
+
+/// Per column alphas from a logistic function fitted through the quartiles of the consensus.
+fn yuma2_liquid_alpha(params: &EpochParams, consensus: &[f64]) -> Option<Vec<f64>> {
+    if !params.liquid_alpha_enabled || consensus.iter().all(|&c| c == 0.0) {
+        return None;
+    }
+
+    let consensus_high = quantile(consensus, 0.75);
+    let consensus_low = quantile(consensus, 0.25);
+
+    if !(consensus_high > consensus_low || consensus_high != 0.0 || consensus_low < 0.0) {
+        return None;
+    }
+
+    let (alpha_low, alpha_high) = alpha_bounds(params);
+
+    let (a, b) = if consensus_high <= consensus_low || alpha_low == 0.0 || alpha_high == 0.0 {
+        (0.0, 0.0)
+    } else {
+        let x_top = safe_ln(1.0 / alpha_high - 1.0);
+        let x_bottom = safe_ln(1.0 / alpha_low - 1.0);
+
+        let a = (x_top - x_bottom) / (consensus_low - consensus_high);
+
+        (a, x_bottom + a * consensus_low)
+    };
+
+    Some(
+        consensus
+            .iter()
+            .map(|&c| {
+                let alpha = 1.0 / (1.0 + (b - a * c).exp());
+
+                alpha.max(alpha_low).min(alpha_high)
+            })
+            .collect(),
+    )
+}
+
+/// The alpha of a single bond, higher the further the weight moved away from the consensus or the bond.
+fn alpha_sigmoid(
+    consensus: f64,
+    weight: f64,
+    bond: f64,
+    alpha_low: f64,
+    alpha_high: f64,
+    steepness: f64,
+) -> f64 {
+    let diff = if weight >= bond {
+        (weight - consensus).clamp(0.0, 1.0)
+    } else {
+        (bond - weight).clamp(0.0, 1.0)
+    };
+
+    let sigmoid = 1.0 / (1.0 + (steepness / -100.0 * (diff - 0.5)).exp());
+    let alpha = alpha_low + sigmoid * (alpha_high - alpha_low);
+
+    alpha.max(alpha_low).min(alpha_high)
+}
+
+fn safe_ln(value: f64) -> f64 {
+    if value > 0.0 {
+        value.ln()
+    } else {
+        0.0
+    }
+}
+
+fn quantile(data: &[f64], quantile: f64) -> f64 {
+    let mut sorted = data.to_vec();
+    sorted.sort_by(f64::total_cmp);
+
+    if sorted.is_empty() {
+        return 0.0;
+    }
+
+    let position = quantile * (sorted.len() - 1) as f64;
+    let low = position.floor() as usize;
+    let high = position.ceil() as usize;
+
+    sorted[low] + (sorted[high] - sorted[low]) * (position - low as f64)
+}
+
+fn dense(
+    rows: &[Vec<(u16, u16)>],
+    n: usize,
+    value: impl Fn(usize, usize, u16) -> Option<f64>,
+) -> Vec<Vec<f64>> {
+    let mut matrix = vec![vec![0.0; n]; n];
+
+    for (i, row) in rows.iter().enumerate().take(n) {
+        for &(j, entry) in row {
+            let j = j as usize;
+
+            if j < n {
+                if let Some(entry) = value(i, j, entry) {
+                    matrix[i][j] = entry;
+                }
+            }
+        }
+    }
+
+    matrix
+}
+
+fn normalize(values: &mut [f64]) {
+    let sum: f64 = values.iter().sum();
+
+    if sum != 0.0 {
+        values.iter_mut().for_each(|value| *value /= sum);
+    }
+}
+
+fn normalize_columns(matrix: &mut [Vec<f64>]) {
+    let n = matrix.first().map_or(0, Vec::len);
+
+    for j in 0..n {
+        let sum: f64 = matrix.iter().map(|row| row[j]).sum();
+
+        if sum != 0.0 {
+            matrix.iter_mut().for_each(|row| row[j] /= sum);
+        }
+    }
+}
+
+/// `sum_i matrix[i][j] * vector[i]` for each column `j`.
+fn weighted_column_sum(matrix: &[Vec<f64>], vector: &[f64]) -> Vec<f64> {
+    let mut result = vec![0.0; matrix.first().map_or(0, Vec::len)];
+
+    for (row, &scale) in matrix.iter().zip(vector) {
+        for (result, &value) in result.iter_mut().zip(row) {
+            *result += value * scale;
+        }
+    }
+
+    result
+}
+
+/// `sum_j matrix[i][j] * vector[j]` for each row `i`.
+fn weighted_row_sum(matrix: &[Vec<f64>], vector: &[f64]) -> Vec<f64> {
+    matrix
+        .iter()
+        .map(|row| {
+            row.iter()
+                .zip(vector)
+                .map(|(&value, &scale)| value * scale)
+                .sum()
+        })
+        .collect()
+}
+
+/// Mirrors `is_topk_nonzero`, where ties go to the higher uid.
+fn top_k_nonzero(values: &[f64], k: usize) -> Vec<bool> {
+    let mut result = vec![false; values.len()];
+    let mut indices: Vec<usize> = (0..values.len()).collect();
+
+    indices.sort_by(|&a, &b| values[a].total_cmp(&values[b]));
+
+    for &index in indices.iter().skip(values.len().saturating_sub(k)) {
+        result[index] = values[index] != 0.0;
+    }
+
+    result
+}
+
+/// The stake-weighted `majority` quantile of each column, over the rows with stake.
+fn weighted_median_columns(stake: &[f64], matrix: &[Vec<f64>], majority: f64) -> Vec<f64> {
+    let n = matrix.first().map_or(0, Vec::len);
+
+    let mut rows: Vec<(f64, &Vec<f64>)> = stake
+        .iter()
+        .copied()
+        .zip(matrix)
+        .filter(|&(stake, _)| stake > 0.0)
+        .collect();
+
+    let sum: f64 = rows.iter().map(|&(stake, _)| stake).sum();
+
+    if sum != 0.0 {
+        rows.iter_mut().for_each(|(stake, _)| *stake /= sum);
+    }
+
+    let minority = rows.iter().map(|&(stake, _)| stake).sum::<f64>() - majority;
+
+    (0..n)
+        .map(|j| {
+            let mut column: Vec<(f64, f64)> =
+                rows.iter().map(|&(stake, row)| (row[j], stake)).collect();
+
+            column.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+            // The first score for which the stake at or below it exceeds the minority
+            let mut cumulative = 0.0;
+            let mut index = 0;
+
+            while index < column.len() {
+                let score = column[index].0;
+
+                while index < column.len() && column[index].0 == score {
+                    cumulative += column[index].1;
+                    index += 1;
+                }
+
+                if minority < cumulative {
+                    return score;
+                }
+            }
+
+            column.last().map_or(0.0, |&(score, _)| score)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two validators with 60% and 40% of the stake, and two miners. The first validator splits its weight between
+    /// the miners while the second puts it all on uid 2, so consensus clips the first validator's weight on uid 3.
+    fn fixture(version: YumaVersion) -> EpochInput {
+        EpochInput {
+            params: EpochParams {
+                version,
+                kappa: 32_767,
+                activity_cutoff: 5_000,
+                max_validators: 64,
+                bonds_moving_avg: 900_000,
+                bonds_penalty: u16::MAX,
+                liquid_alpha_enabled: false,
+                alpha_low: 0,
+                alpha_high: 0,
+                alpha_sigmoid_steepness: 0.0,
+                stake_threshold: 0,
+            },
+            block: 1_000,
+            last_step: 900,
+            owner_uid: None,
+            stake: vec![600, 400, 0, 0],
+            validator_permit: vec![true, true, false, false],
+            last_update: vec![1_000; 4],
+            block_at_registration: vec![0; 4],
+            weights: vec![
+                vec![(2, u16::MAX), (3, u16::MAX)],
+                vec![(2, u16::MAX)],
+                Vec::new(),
+                Vec::new(),
+            ],
+            bonds: vec![Vec::new(); 4],
+        }
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+
+        for (actual, expected) in actual.iter().zip(expected) {
+            assert!(
+                (actual - expected).abs() < 1e-9,
+                "{actual:?} != {expected:?}"
+            );
+        }
+    }
+
+    fn assert_shared_results(epoch: &Epoch) {
+        // Consensus is the stake-weighted median of each column, 0.5 for both miners
+        assert_close(&epoch.consensus, &[0.0, 0.0, 0.5, 0.5]);
+        // Clipped ranks are 0.5 and 0.3 against preranks of 0.7 and 0.3, normalized to sum to 1
+        assert_close(&epoch.ranks, &[0.0, 0.0, 0.625, 0.375]);
+        assert_close(&epoch.incentive, &[0.0, 0.0, 0.625, 0.375]);
+        assert_close(&epoch.trust, &[0.0, 0.0, 0.5 / 0.7, 1.0]);
+        assert_close(&epoch.validator_trust, &[1.0, 0.5, 0.0, 0.0]);
+        assert_eq!(epoch.validator_permit, vec![true, true, false, false]);
+    }
+
+    #[test]
+    fn yuma2_epoch_matches_fixture() {
+        let epoch = simulate(&fixture(YumaVersion::Yuma2)).unwrap();
+
+        assert_shared_results(&epoch);
+
+        // Column normalized stake-weighted bonds are 0.6 and 0.4 on uid 2, and 1 on uid 3 for the first validator
+        assert_close(&epoch.dividends, &[0.75, 0.25, 0.0, 0.0]);
+        assert_eq!(
+            epoch.stored_bonds(),
+            vec![
+                vec![(2, u16::MAX), (3, u16::MAX)],
+                vec![(2, to_u16(0.4 / 0.6))],
+                Vec::new(),
+                Vec::new(),
+            ]
+        );
+    }
+
+    #[test]
+    fn yuma3_epoch_matches_fixture() {
+        let epoch = simulate(&fixture(YumaVersion::Yuma3)).unwrap();
+
+        assert_shared_results(&epoch);
+
+        // Bonds grow by alpha times the clipped weights, and are column normalized before computing dividends:
+        // 0.5 * 0.625 + 1 * 0.375 and 0.5 * 0.625, scaled by the validators' stake
+        let dividends = [0.6875 * 0.6, 0.3125 * 0.4];
+        let total: f64 = dividends.iter().sum();
+
+        assert_close(
+            &epoch.dividends,
+            &[dividends[0] / total, dividends[1] / total, 0.0, 0.0],
+        );
+        assert_eq!(
+            epoch.stored_bonds(),
+            vec![
+                vec![(2, to_u16(0.05)), (3, to_u16(0.05))],
+                vec![(2, to_u16(0.05))],
+                Vec::new(),
+                Vec::new(),
+            ]
+        );
+    }
+
+    #[test]
+    fn mismatched_input_lengths_are_rejected() {
+        let mut input = fixture(YumaVersion::Yuma3);
+        input.last_update.pop();
+
+        let error = simulate(&input).unwrap_err();
+
+        assert_eq!(error.field, "last_update");
+        assert_eq!(error.length, 3);
+        assert_eq!(error.uids, 4);
+
+        let mut input = fixture(YumaVersion::Yuma2);
+        input.bonds.push(Vec::new());
+
+        assert_eq!(simulate(&input).unwrap_err().field, "bonds");
+    }
+}
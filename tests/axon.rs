@@ -0,0 +1,122 @@
+mod common;
+
+use std::net::SocketAddr;
+
+use crabtensor::api;
+use crabtensor::api::runtime_types::pallet_subtensor::pallet::AxonInfo;
+use crabtensor::api::subtensor_module::calls::types::{ServeAxon, ServePrometheus};
+use crabtensor::axon::{serve_axon_if_changed, serve_prometheus_payload, AxonProtocol};
+use crabtensor::mock::MockSubtensor;
+use crabtensor::AccountId;
+use subxt::ext::codec::Decode;
+use subxt::tx::Payload;
+
+const NETUID: u16 = 1;
+
+/// The arguments of the call `payload` makes.
+fn call<C: Decode>(mock: &MockSubtensor, payload: &impl Payload) -> C {
+    let data = payload.encode_call_data(&mock.metadata()).unwrap();
+
+    // Skipping the pallet and call indices
+    C::decode(&mut &data[2..]).unwrap()
+}
+
+fn address(address: &str) -> SocketAddr {
+    address.parse().unwrap()
+}
+
+#[tokio::test]
+async fn serves_the_axon_only_when_it_changed() {
+    let mock = MockSubtensor::new(&common::metadata()).unwrap();
+    let client = mock.client();
+    let hotkey = AccountId::from([1; 32]);
+
+    let changed = |served: SocketAddr, protocol| {
+        let client = client.clone();
+        let hotkey = hotkey.clone();
+        let at = mock.latest_hash();
+
+        async move {
+            serve_axon_if_changed(&client, at.into(), NETUID, &hotkey, served, protocol)
+                .await
+                .unwrap()
+        }
+    };
+
+    // Nothing is served yet
+    let payload = changed(address("1.2.3.4:8091"), AxonProtocol::Other)
+        .await
+        .unwrap();
+    let serve: ServeAxon = call(&mock, &payload);
+
+    assert_eq!(serve.netuid, NETUID);
+    assert_eq!(serve.version, 1);
+    assert_eq!(serve.ip, 0x0102_0304);
+    assert_eq!(serve.port, 8091);
+    assert_eq!(serve.ip_type, 4);
+    assert_eq!(serve.protocol, 4);
+
+    mock.set_storage(
+        &api::storage().subtensor_module().axons(NETUID, &hotkey),
+        &AxonInfo {
+            block: 1,
+            version: 1,
+            ip: 0x0102_0304,
+            port: 8091,
+            ip_type: 4,
+            protocol: 4,
+            placeholder1: 0,
+            placeholder2: 0,
+        },
+    )
+    .unwrap();
+
+    assert!(changed(address("1.2.3.4:8091"), AxonProtocol::Other)
+        .await
+        .is_none());
+    assert!(changed(address("1.2.3.4:8092"), AxonProtocol::Other)
+        .await
+        .is_some());
+    assert!(changed(address("1.2.3.4:8091"), AxonProtocol::Tcp)
+        .await
+        .is_some());
+
+    let payload = changed(address("[2606:4700::1]:8091"), AxonProtocol::Other)
+        .await
+        .unwrap();
+    let serve: ServeAxon = call(&mock, &payload);
+
+    assert_eq!(serve.ip, 0x2606_4700_0000_0000_0000_0000_0000_0001);
+    assert_eq!(serve.ip_type, 6);
+}
+
+#[test]
+fn encodes_prometheus_endpoints() {
+    let mock = MockSubtensor::new(&common::metadata()).unwrap();
+
+    let serve: ServePrometheus = call(
+        &mock,
+        &serve_prometheus_payload(NETUID, address("10.0.0.1:9100")),
+    );
+
+    assert_eq!(
+        (
+            serve.netuid,
+            serve.version,
+            serve.ip,
+            serve.port,
+            serve.ip_type
+        ),
+        (NETUID, 1, 0x0a00_0001, 9100, 4)
+    );
+
+    let serve: ServePrometheus = call(
+        &mock,
+        &serve_prometheus_payload(NETUID, address("[2606:4700::1]:9100")),
+    );
+
+    assert_eq!(
+        (serve.ip, serve.port, serve.ip_type),
+        (0x2606_4700_0000_0000_0000_0000_0000_0001, 9100, 6)
+    );
+}
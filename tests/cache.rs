@@ -0,0 +1,185 @@
+mod common;
+
+use common::{MockServer, StakeInfo};
+use crabtensor::api;
+use crabtensor::cache::{CacheOptions, CacheStats, CachedSubtensor};
+use crabtensor::mock::MockSubtensor;
+use crabtensor::subtensor::Subtensor;
+use crabtensor::AccountId;
+use subxt::ext::futures::join;
+use subxt::runtime_api::StaticPayload;
+
+fn mock() -> MockSubtensor {
+    let mock = MockSubtensor::new(&common::metadata()).unwrap();
+
+    for netuid in 0..4 {
+        mock.set_storage(
+            &api::storage().subtensor_module().subnetwork_n(netuid),
+            &(netuid * 10),
+        )
+        .unwrap();
+    }
+
+    mock
+}
+
+async fn subnetwork_n(cache: &CachedSubtensor, at: crabtensor::Hash, netuid: u16) -> u16 {
+    cache
+        .fetch_or_default(
+            at.into(),
+            &api::storage().subtensor_module().subnetwork_n(netuid),
+        )
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn counts_hits_and_misses_per_block() {
+    let mock = mock();
+    let first = mock.latest_hash();
+    let second = mock.produce_block();
+    let cache = CachedSubtensor::new(mock.client());
+
+    assert_eq!(subnetwork_n(&cache, first, 1).await, 10);
+    assert_eq!(subnetwork_n(&cache, first, 1).await, 10);
+    assert_eq!(subnetwork_n(&cache, second, 1).await, 10);
+
+    assert_eq!(
+        cache.stats(),
+        CacheStats {
+            hits: 1,
+            misses: 2,
+            coalesced: 0,
+            evictions: 0,
+            entries: 2,
+        }
+    );
+    assert_eq!(cache.stats().hit_ratio(), 1.0 / 3.0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn coalesces_requests_in_flight() {
+    // Served over a socket, so that the first request is still in flight when the second is made
+    let server = MockServer::start(mock()).await;
+    let client = Subtensor::from_insecure_url(server.url()).await.unwrap();
+    let at = client.blocks().at_latest().await.unwrap().hash();
+    let cache = CachedSubtensor::new(client);
+
+    let (a, b) = join!(subnetwork_n(&cache, at, 2), subnetwork_n(&cache, at, 2));
+
+    assert_eq!((a, b), (20, 20));
+    assert_eq!(
+        (
+            cache.stats().misses,
+            cache.stats().coalesced,
+            cache.stats().hits
+        ),
+        (1, 1, 0)
+    );
+}
+
+#[tokio::test]
+async fn evicts_the_least_recently_used_results() {
+    let mock = mock();
+    let at = mock.latest_hash();
+    let cache = CachedSubtensor::with_options(mock.client(), CacheOptions { capacity: 2 });
+
+    subnetwork_n(&cache, at, 0).await;
+    subnetwork_n(&cache, at, 1).await;
+
+    // Using 0 again makes 1 the least recently used
+    subnetwork_n(&cache, at, 0).await;
+    subnetwork_n(&cache, at, 2).await;
+
+    assert_eq!(cache.stats().evictions, 1);
+    assert_eq!(cache.stats().entries, 2);
+
+    subnetwork_n(&cache, at, 0).await;
+    subnetwork_n(&cache, at, 1).await;
+
+    assert_eq!(
+        cache.stats(),
+        CacheStats {
+            hits: 2,
+            misses: 4,
+            coalesced: 0,
+            evictions: 2,
+            entries: 2,
+        }
+    );
+}
+
+#[tokio::test]
+async fn retries_failed_fetches_instead_of_caching_them() {
+    let mock = mock();
+    let at = mock.latest_hash();
+    let cache = CachedSubtensor::new(mock.client());
+
+    let coldkey = AccountId::from([1; 32]);
+    let generated = api::apis()
+        .stake_info_runtime_api()
+        .get_stake_info_for_coldkey(coldkey.clone());
+    // Built without a validation hash, as the mock's stake info does not match the compiled-in one
+    let payload = StaticPayload::<_, Vec<StakeInfo>>::new(
+        generated.trait_name(),
+        generated.method_name(),
+        generated.args_data().clone(),
+    );
+
+    // The mock rejects runtime API calls it has no response for
+    assert!(cache
+        .runtime_api_call(at.into(), payload.clone())
+        .await
+        .is_err());
+
+    let stake = vec![StakeInfo {
+        hotkey: AccountId::from([2; 32]),
+        coldkey,
+        netuid: 1,
+        stake: 100,
+        locked: 0,
+        emission: 0,
+        tao_emission: 0,
+        drain: 0,
+        is_registered: true,
+        alpha_dividends: 0,
+    }];
+
+    mock.set_runtime_api(&payload, &stake).unwrap();
+
+    assert_eq!(
+        cache
+            .runtime_api_call(at.into(), payload.clone())
+            .await
+            .unwrap(),
+        stake
+    );
+    assert_eq!(
+        cache.runtime_api_call(at.into(), payload).await.unwrap(),
+        stake
+    );
+
+    assert_eq!((cache.stats().misses, cache.stats().hits), (2, 1));
+    assert_eq!(cache.stats().entries, 1);
+}
+
+#[tokio::test]
+async fn invalidates_only_the_given_block() {
+    let mock = mock();
+    let first = mock.latest_hash();
+    let second = mock.produce_block();
+    let cache = CachedSubtensor::new(mock.client());
+
+    subnetwork_n(&cache, first, 3).await;
+    subnetwork_n(&cache, second, 3).await;
+
+    cache.invalidate(first);
+
+    assert_eq!(cache.stats().entries, 1);
+
+    subnetwork_n(&cache, first, 3).await;
+    subnetwork_n(&cache, second, 3).await;
+
+    assert_eq!((cache.stats().misses, cache.stats().hits), (3, 1));
+    assert_eq!(cache.stats().entries, 2);
+}
@@ -0,0 +1,142 @@
+mod common;
+
+use std::time::Duration;
+
+use crabtensor::client::{BlockStreamKind, ConnectionEvent, ReconnectPolicy, SubtensorClient};
+use crabtensor::mock::MockSubtensor;
+use crabtensor::BlockNumber;
+use tokio::time::timeout;
+
+use common::MockServer;
+
+fn policy() -> ReconnectPolicy {
+    ReconnectPolicy {
+        initial_delay: Duration::from_millis(10),
+        max_delay: Duration::from_millis(100),
+        max_attempts: Some(5),
+        connect_timeout: Duration::from_secs(5),
+        health_check_interval: Duration::from_millis(50),
+        health_check_timeout: Duration::from_secs(1),
+    }
+}
+
+async fn next_number(blocks: &mut crabtensor::client::BlockSubscription) -> BlockNumber {
+    timeout(Duration::from_secs(10), blocks.next())
+        .await
+        .expect("Timed out waiting for a block")
+        .expect("Block subscription ended")
+        .number()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn fails_over_to_the_next_endpoint() {
+    let mock = MockSubtensor::new(&common::metadata()).unwrap();
+    let first = MockServer::start(mock.clone()).await;
+    let second = MockServer::start(mock.clone()).await;
+
+    let client = SubtensorClient::connect([first.url(), second.url()], policy())
+        .await
+        .unwrap();
+
+    assert_eq!(client.endpoint(), first.url());
+
+    first.shutdown();
+
+    client.reconnect().await.unwrap();
+
+    assert_eq!(client.endpoint(), second.url());
+    assert!(client.health_check().await.is_ok());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn block_stream_resumes_without_gaps_or_duplicates() {
+    let mock = MockSubtensor::new(&common::metadata()).unwrap();
+    let first = MockServer::start(mock.clone()).await;
+    let second = MockServer::start(mock.clone()).await;
+
+    let client = SubtensorClient::connect([first.url(), second.url()], policy())
+        .await
+        .unwrap();
+
+    let mut events = client.events();
+    let mut blocks = client.subscribe_blocks(BlockStreamKind::Finalized);
+    let mut numbers = vec![next_number(&mut blocks).await];
+
+    mock.produce_blocks_until(5);
+
+    while *numbers.last().unwrap() < 5 {
+        numbers.push(next_number(&mut blocks).await);
+    }
+
+    // Blocks produced while the stream is down must still be delivered once it resumes
+    first.shutdown();
+    mock.produce_blocks_until(10);
+
+    while *numbers.last().unwrap() < 10 {
+        numbers.push(next_number(&mut blocks).await);
+    }
+
+    mock.produce_blocks_until(15);
+
+    while *numbers.last().unwrap() < 15 {
+        numbers.push(next_number(&mut blocks).await);
+    }
+
+    assert_eq!(numbers, (0..=15).collect::<Vec<_>>());
+    assert_eq!(client.endpoint(), second.url());
+
+    let mut resubscribed = false;
+
+    while let Ok(event) = events.try_recv() {
+        resubscribed |= matches!(event, ConnectionEvent::Resubscribed { .. });
+    }
+
+    assert!(resubscribed);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn block_stream_skips_what_it_can_not_backfill() {
+    let mock = MockSubtensor::new(&common::metadata()).unwrap();
+    let server = MockServer::start(mock.clone()).await;
+
+    let client = SubtensorClient::connect([server.url()], policy())
+        .await
+        .unwrap();
+
+    let mut events = client.events();
+    let mut blocks = client.subscribe_blocks(BlockStreamKind::Finalized);
+    let mut numbers = vec![next_number(&mut blocks).await];
+
+    mock.produce_blocks_until(5);
+
+    while numbers.last() != Some(&5) {
+        numbers.push(next_number(&mut blocks).await);
+    }
+
+    // The only endpoint is down while more blocks are finalized than the runtime keeps hashes of
+    let address = server.address();
+    drop(server);
+    mock.produce_blocks_until(30);
+    let _server = MockServer::start_on(mock.clone(), address).await;
+
+    while numbers.last() != Some(&30) {
+        numbers.push(next_number(&mut blocks).await);
+    }
+
+    let resumed = 30 - common::BLOCK_HASH_COUNT;
+
+    assert_eq!(
+        numbers,
+        (0..=5).chain(resumed..=30).collect::<Vec<BlockNumber>>()
+    );
+
+    let mut skipped = None;
+
+    while let Ok(event) = events.try_recv() {
+        if let ConnectionEvent::Skipped { first, last } = event {
+            skipped = Some((first, last));
+        }
+    }
+
+    assert_eq!(skipped, Some((6, resumed - 1)));
+}
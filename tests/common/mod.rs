@@ -0,0 +1,638 @@
+#![allow(dead_code)]
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use frame_metadata::v15::{
+    CustomMetadata, ExtrinsicMetadata, OuterEnums, PalletCallMetadata, PalletConstantMetadata,
+    PalletEventMetadata, PalletMetadata, PalletStorageMetadata, RuntimeApiMetadata,
+    RuntimeApiMethodMetadata, RuntimeApiMethodParamMetadata, RuntimeMetadataV15,
+    StorageEntryMetadata, StorageEntryModifier, StorageEntryType, StorageHasher,
+};
+use frame_metadata::RuntimeMetadataPrefixed;
+use scale_info::{meta_type, TypeInfo};
+use serde_json::value::RawValue;
+use serde_json::{json, Value};
+use soketto::handshake::server::Response;
+use soketto::handshake::Server;
+use sp_core::crypto::AccountId32;
+use sp_core::hashing::twox_128;
+use sp_runtime::{MultiAddress, MultiSignature};
+use subxt::backend::rpc::RpcClientT;
+use subxt::events::Phase;
+use subxt::ext::codec::Encode;
+use subxt::ext::futures::StreamExt;
+use subxt::ext::scale_decode::DecodeAsType;
+use subxt::runtime_api::StaticPayload;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, watch};
+use tokio::time::sleep;
+use tokio_util::compat::TokioAsyncReadCompatExt;
+
+use crabtensor::mock::MockSubtensor;
+use crabtensor::{api, AccountId, Hash};
+
+#[allow(non_camel_case_types, dead_code)]
+#[derive(TypeInfo)]
+enum SubtensorCall {
+    #[codec(index = 0)]
+    set_weights {
+        netuid: u16,
+        dests: Vec<u16>,
+        weights: Vec<u16>,
+        version_key: u64,
+    },
+    #[codec(index = 4)]
+    serve_axon {
+        netuid: u16,
+        version: u32,
+        ip: u128,
+        port: u16,
+        ip_type: u8,
+        protocol: u8,
+        placeholder1: u8,
+        placeholder2: u8,
+    },
+    #[codec(index = 5)]
+    serve_prometheus {
+        netuid: u16,
+        version: u32,
+        ip: u128,
+        port: u16,
+        ip_type: u8,
+    },
+}
+
+#[derive(TypeInfo)]
+struct AxonInfo {
+    block: u64,
+    version: u32,
+    ip: u128,
+    port: u16,
+    ip_type: u8,
+    protocol: u8,
+    placeholder1: u8,
+    placeholder2: u8,
+}
+
+#[derive(TypeInfo)]
+struct AccountInfo {
+    nonce: u32,
+    consumers: u32,
+    providers: u32,
+    sufficients: u32,
+    data: AccountData,
+}
+
+#[derive(TypeInfo)]
+struct AccountData {
+    free: u64,
+    reserved: u64,
+    frozen: u64,
+    flags: ExtraFlags,
+}
+
+#[derive(TypeInfo)]
+struct ExtraFlags(u128);
+
+#[allow(dead_code)]
+#[derive(TypeInfo)]
+enum RuntimeCall {
+    #[codec(index = 7)]
+    SubtensorModule(SubtensorCall),
+}
+
+#[derive(TypeInfo, Encode)]
+#[codec(crate = subxt::ext::codec)]
+pub enum SystemEvent {
+    #[codec(index = 0)]
+    ExtrinsicSuccess,
+    #[codec(index = 1)]
+    ExtrinsicFailed,
+}
+
+#[derive(TypeInfo, Encode)]
+#[codec(crate = subxt::ext::codec)]
+pub enum BalancesEvent {
+    #[codec(index = 2)]
+    Transfer {
+        from: AccountId,
+        to: AccountId,
+        amount: u64,
+    },
+}
+
+/// `SubtensorModule` events, with stake events in their current layout.
+#[derive(TypeInfo, Encode)]
+#[codec(crate = subxt::ext::codec)]
+pub enum SubtensorEvent {
+    #[codec(index = 6)]
+    NeuronRegistered(u16, u16, AccountId),
+    #[codec(index = 9)]
+    StakeAdded(AccountId, AccountId, u64, u64, u16, u64),
+    #[codec(index = 10)]
+    StakeRemoved(AccountId, AccountId, u64, u64, u16, u64),
+    #[codec(index = 12)]
+    WeightsSet(u16, u16),
+    #[codec(index = 13)]
+    AxonServed(u16, AccountId),
+    #[codec(index = 65)]
+    ColdkeySwapped {
+        old_coldkey: AccountId,
+        new_coldkey: AccountId,
+        swap_cost: u64,
+    },
+}
+
+/// `SubtensorModule` events of runtimes from before stake was held per subnet, whose stake events only carried the
+/// hotkey and amount.
+#[derive(TypeInfo, Encode)]
+#[codec(crate = subxt::ext::codec)]
+pub enum LegacySubtensorEvent {
+    #[codec(index = 9)]
+    StakeAdded(AccountId, u64),
+    #[codec(index = 10)]
+    StakeRemoved(AccountId, u64),
+}
+
+#[derive(TypeInfo, Encode)]
+#[codec(crate = subxt::ext::codec)]
+pub enum RuntimeEvent<E = SubtensorEvent> {
+    #[codec(index = 0)]
+    System(SystemEvent),
+    #[codec(index = 5)]
+    Balances(BalancesEvent),
+    #[codec(index = 7)]
+    SubtensorModule(E),
+}
+
+#[derive(Encode)]
+#[codec(crate = subxt::ext::codec)]
+struct EventRecord<E> {
+    phase: Phase,
+    event: RuntimeEvent<E>,
+    topics: Vec<Hash>,
+}
+
+/// Sets the events of the latest block, each emitted by the extrinsic at the given index, or while initializing the
+/// block for `None`. Later blocks inherit them until they are set again.
+pub fn set_events<E: Encode>(mock: &MockSubtensor, events: Vec<(Option<u32>, RuntimeEvent<E>)>) {
+    let records: Vec<_> = events
+        .into_iter()
+        .map(|(extrinsic_index, event)| EventRecord {
+            phase: extrinsic_index.map_or(Phase::Initialization, Phase::ApplyExtrinsic),
+            event,
+            topics: Vec::new(),
+        })
+        .collect();
+
+    let key = [twox_128(b"System"), twox_128(b"Events")].concat();
+
+    mock.set_raw_storage(key, records.encode());
+}
+
+#[derive(TypeInfo)]
+enum RuntimeError {}
+
+#[derive(TypeInfo)]
+struct Runtime;
+
+/// The stake info the mock runtime API returns, with a field the compiled-in metadata does not know about so that
+/// its validation hash never matches.
+#[derive(Clone, Debug, PartialEq, TypeInfo, Encode, DecodeAsType)]
+#[codec(crate = subxt::ext::codec)]
+#[decode_as_type(crate_path = "subxt::ext::scale_decode")]
+pub struct StakeInfo {
+    pub hotkey: AccountId,
+    pub coldkey: AccountId,
+    #[codec(compact)]
+    pub netuid: u16,
+    #[codec(compact)]
+    pub stake: u64,
+    #[codec(compact)]
+    pub locked: u64,
+    #[codec(compact)]
+    pub emission: u64,
+    #[codec(compact)]
+    pub tao_emission: u64,
+    #[codec(compact)]
+    pub drain: u64,
+    pub is_registered: bool,
+    #[codec(compact)]
+    pub alpha_dividends: u64,
+}
+
+/// Answers the stake info runtime API for `coldkey` with `stakes`, through a payload without a validation hash as
+/// [`StakeInfo`] differs from the generated type.
+pub fn set_stake_info(mock: &MockSubtensor, coldkey: &AccountId, stakes: Vec<StakeInfo>) {
+    let generated = api::apis()
+        .stake_info_runtime_api()
+        .get_stake_info_for_coldkey(coldkey.clone());
+    let payload = StaticPayload::<_, Vec<StakeInfo>>::new(
+        generated.trait_name(),
+        generated.method_name(),
+        generated.args_data().clone(),
+    );
+
+    mock.set_runtime_api(&payload, &stakes).unwrap();
+}
+
+fn map_entry<K: TypeInfo + 'static, V: TypeInfo + 'static>(
+    name: &'static str,
+    hashers: Vec<StorageHasher>,
+    default: Vec<u8>,
+) -> StorageEntryMetadata {
+    StorageEntryMetadata {
+        name,
+        modifier: StorageEntryModifier::Default,
+        ty: StorageEntryType::Map {
+            hashers,
+            key: meta_type::<K>(),
+            value: meta_type::<V>(),
+        },
+        default,
+        docs: Vec::new(),
+    }
+}
+
+/// A map entry reading as `None` where it is not set, whose default is the encoded `None`.
+fn optional_map_entry<K: TypeInfo + 'static, V: TypeInfo + 'static>(
+    name: &'static str,
+    hashers: Vec<StorageHasher>,
+) -> StorageEntryMetadata {
+    StorageEntryMetadata {
+        modifier: StorageEntryModifier::Optional,
+        ..map_entry::<K, V>(name, hashers, vec![0])
+    }
+}
+
+fn constant<T: TypeInfo + Encode + 'static>(
+    name: &'static str,
+    value: T,
+) -> PalletConstantMetadata {
+    PalletConstantMetadata {
+        name,
+        ty: meta_type::<T>(),
+        value: value.encode(),
+        docs: Vec::new(),
+    }
+}
+
+/// How many blocks the mock runtime keeps hashes of, kept low so tests can outrun it.
+pub const BLOCK_HASH_COUNT: u32 = 8;
+
+/// The fee in rao of swapping a hotkey on every subnet, and of swapping a coldkey.
+pub const KEY_SWAP_COST: u64 = 100_000_000;
+
+/// The fee in rao of swapping a hotkey on a single subnet.
+pub const KEY_SWAP_ON_SUBNET_COST: u64 = 10_000_000;
+
+/// SCALE encoded metadata holding the parts of the subtensor runtime the tests use, shaped so the static API's
+/// validation hashes match it.
+pub fn metadata() -> Vec<u8> {
+    metadata_with::<SubtensorEvent>()
+}
+
+/// [`metadata`] of a runtime emitting [`LegacySubtensorEvent`]s.
+pub fn legacy_metadata() -> Vec<u8> {
+    metadata_with::<LegacySubtensorEvent>()
+}
+
+fn metadata_with<E: TypeInfo + 'static>() -> Vec<u8> {
+    // A new account's balance flags have the top bit set, marking it as using the current reserve logic
+    let account_default = (0u32, 0u32, 0u32, 0u32, 0u64, 0u64, 0u64, 1u128 << 127).encode();
+
+    let system = PalletMetadata {
+        name: "System",
+        storage: Some(PalletStorageMetadata {
+            prefix: "System",
+            entries: vec![map_entry::<AccountId, AccountInfo>(
+                "Account",
+                vec![StorageHasher::Blake2_128Concat],
+                account_default,
+            )],
+        }),
+        calls: None,
+        event: Some(PalletEventMetadata {
+            ty: meta_type::<SystemEvent>(),
+        }),
+        constants: vec![constant("BlockHashCount", BLOCK_HASH_COUNT)],
+        error: None,
+        index: 0,
+        docs: Vec::new(),
+    };
+
+    let balances = PalletMetadata {
+        name: "Balances",
+        storage: None,
+        calls: None,
+        event: Some(PalletEventMetadata {
+            ty: meta_type::<BalancesEvent>(),
+        }),
+        constants: Vec::new(),
+        error: None,
+        index: 5,
+        docs: Vec::new(),
+    };
+
+    let subtensor = PalletMetadata {
+        name: "SubtensorModule",
+        storage: Some(PalletStorageMetadata {
+            prefix: "SubtensorModule",
+            entries: vec![
+                map_entry::<(u16, u16), AccountId32>(
+                    "Keys",
+                    vec![StorageHasher::Identity, StorageHasher::Identity],
+                    vec![0; 32],
+                ),
+                map_entry::<u16, u16>("SubnetworkN", vec![StorageHasher::Identity], vec![0; 2]),
+                optional_map_entry::<(u16, AccountId), AxonInfo>(
+                    "Axons",
+                    vec![StorageHasher::Identity, StorageHasher::Blake2_128Concat],
+                ),
+                map_entry::<AccountId, AccountId>(
+                    "Owner",
+                    vec![StorageHasher::Blake2_128Concat],
+                    vec![0; 32],
+                ),
+                map_entry::<(AccountId, u16), bool>(
+                    "IsNetworkMember",
+                    vec![StorageHasher::Blake2_128Concat, StorageHasher::Identity],
+                    vec![0],
+                ),
+                optional_map_entry::<(u16, AccountId), u16>(
+                    "Uids",
+                    vec![StorageHasher::Identity, StorageHasher::Blake2_128Concat],
+                ),
+                map_entry::<(AccountId, u16), u64>(
+                    "TotalHotkeyAlpha",
+                    vec![StorageHasher::Blake2_128Concat, StorageHasher::Identity],
+                    vec![0; 8],
+                ),
+                map_entry::<AccountId, Vec<AccountId>>(
+                    "OwnedHotkeys",
+                    vec![StorageHasher::Blake2_128Concat],
+                    vec![0],
+                ),
+                map_entry::<AccountId, Vec<AccountId>>(
+                    "StakingHotkeys",
+                    vec![StorageHasher::Blake2_128Concat],
+                    vec![0],
+                ),
+                map_entry::<AccountId, (u32, AccountId)>(
+                    "ColdkeySwapScheduled",
+                    vec![StorageHasher::Blake2_128Concat],
+                    vec![0; 36],
+                ),
+            ],
+        }),
+        calls: Some(PalletCallMetadata {
+            ty: meta_type::<SubtensorCall>(),
+        }),
+        event: Some(PalletEventMetadata {
+            ty: meta_type::<E>(),
+        }),
+        constants: vec![
+            constant("KeySwapCost", KEY_SWAP_COST),
+            constant("KeySwapOnSubnetCost", KEY_SWAP_ON_SUBNET_COST),
+        ],
+        error: None,
+        index: 7,
+        docs: Vec::new(),
+    };
+
+    let metadata = RuntimeMetadataV15::new(
+        vec![system, balances, subtensor],
+        ExtrinsicMetadata {
+            version: 4,
+            address_ty: meta_type::<MultiAddress<AccountId32, ()>>(),
+            call_ty: meta_type::<RuntimeCall>(),
+            signature_ty: meta_type::<MultiSignature>(),
+            extra_ty: meta_type::<()>(),
+            signed_extensions: Vec::new(),
+        },
+        meta_type::<Runtime>(),
+        vec![RuntimeApiMetadata {
+            name: "StakeInfoRuntimeApi",
+            methods: vec![RuntimeApiMethodMetadata {
+                name: "get_stake_info_for_coldkey",
+                inputs: vec![RuntimeApiMethodParamMetadata {
+                    name: "coldkey_account",
+                    ty: meta_type::<AccountId>(),
+                }],
+                output: meta_type::<Vec<StakeInfo>>(),
+                docs: Vec::new(),
+            }],
+            docs: Vec::new(),
+        }],
+        OuterEnums {
+            call_enum_ty: meta_type::<RuntimeCall>(),
+            event_enum_ty: meta_type::<RuntimeEvent<E>>(),
+            error_enum_ty: meta_type::<RuntimeError>(),
+        },
+        CustomMetadata {
+            map: Default::default(),
+        },
+    );
+
+    RuntimeMetadataPrefixed::from(metadata).encode()
+}
+
+/// Serves a [`MockSubtensor`] over a websocket on a local port, so clients connect to it as they would to a node.
+pub struct MockServer {
+    address: SocketAddr,
+    url: String,
+    shutdown: watch::Sender<bool>,
+}
+
+impl MockServer {
+    pub async fn start(mock: MockSubtensor) -> Self {
+        Self::start_on(mock, SocketAddr::from(([127, 0, 0, 1], 0))).await
+    }
+
+    /// Serves on `address`, such as that of a server shut down earlier to bring its endpoint back up.
+    pub async fn start_on(mock: MockSubtensor, address: SocketAddr) -> Self {
+        // The listener of a server shut down just before may not be closed yet
+        let mut attempts = 0;
+        let listener = loop {
+            match TcpListener::bind(address).await {
+                Ok(listener) => break listener,
+                Err(error) if attempts == 100 => panic!("Failed to bind {address}: {error}"),
+                Err(_) => {
+                    attempts += 1;
+                    sleep(Duration::from_millis(10)).await;
+                }
+            }
+        };
+        let address = listener.local_addr().unwrap();
+        let url = format!("ws://{address}");
+        let (shutdown, stopped) = watch::channel(false);
+
+        tokio::spawn(async move {
+            let mut stopped_accepting = stopped.clone();
+
+            loop {
+                tokio::select! {
+                    accepted = listener.accept() => {
+                        let Ok((socket, _)) = accepted else { return };
+
+                        tokio::spawn(serve_connection(mock.clone(), socket, stopped.clone()));
+                    }
+                    _ = stopped_accepting.changed() => return,
+                }
+            }
+        });
+
+        Self {
+            address,
+            url,
+            shutdown,
+        }
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Stops accepting connections and drops every open one, as a node going down would.
+    pub fn shutdown(&self) {
+        let _ = self.shutdown.send(true);
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+async fn serve_connection(
+    mock: MockSubtensor,
+    socket: TcpStream,
+    mut stopped: watch::Receiver<bool>,
+) {
+    let mut server = Server::new(socket.compat());
+
+    let Ok(request) = server.receive_request().await else {
+        return;
+    };
+
+    let key = request.key();
+
+    if server
+        .send_response(&Response::Accept {
+            key,
+            protocol: None,
+        })
+        .await
+        .is_err()
+    {
+        return;
+    }
+
+    let (mut sender, mut receiver) = server.into_builder().finish();
+    let (outgoing, mut queued) = mpsc::unbounded_channel::<String>();
+
+    let writer = tokio::spawn(async move {
+        while let Some(message) = queued.recv().await {
+            if sender.send_text(message).await.is_err() || sender.flush().await.is_err() {
+                return;
+            }
+        }
+    });
+
+    let mut subscriptions = Vec::new();
+    let mut next_subscription = 0u64;
+
+    loop {
+        let mut message = Vec::new();
+
+        tokio::select! {
+            received = receiver.receive_data(&mut message) => {
+                if received.is_err() {
+                    break;
+                }
+            }
+            _ = stopped.changed() => break,
+        }
+
+        let Ok(request) = serde_json::from_slice::<Value>(&message) else {
+            continue;
+        };
+
+        let id = request["id"].clone();
+        let method = request["method"].as_str().unwrap_or_default().to_string();
+        let params = match &request["params"] {
+            Value::Null => None,
+            params => RawValue::from_string(params.to_string()).ok(),
+        };
+
+        if method.contains("_unsubscribe") || method.contains("_unwatch") {
+            let _ =
+                outgoing.send(json!({ "jsonrpc": "2.0", "id": id, "result": true }).to_string());
+        } else if method.contains("_subscribe") || method == "author_submitAndWatchExtrinsic" {
+            match mock.subscribe_raw(&method, params, "").await {
+                Ok(mut subscription) => {
+                    next_subscription += 1;
+                    let subscription_id = next_subscription.to_string();
+
+                    let _ = outgoing.send(
+                        json!({ "jsonrpc": "2.0", "id": id, "result": subscription_id })
+                            .to_string(),
+                    );
+
+                    let outgoing = outgoing.clone();
+
+                    subscriptions.push(tokio::spawn(async move {
+                        while let Some(Ok(item)) = subscription.stream.next().await {
+                            let notification = json!({
+                                "jsonrpc": "2.0",
+                                "method": method,
+                                "params": {
+                                    "subscription": subscription_id,
+                                    "result": serde_json::from_str::<Value>(item.get()).unwrap(),
+                                },
+                            });
+
+                            if outgoing.send(notification.to_string()).is_err() {
+                                return;
+                            }
+                        }
+                    }));
+                }
+                Err(error) => {
+                    let _ = outgoing.send(error_response(id, error).to_string());
+                }
+            }
+        } else {
+            let response = match mock.request_raw(&method, params).await {
+                Ok(result) => json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "result": serde_json::from_str::<Value>(result.get()).unwrap(),
+                }),
+                Err(error) => error_response(id, error),
+            };
+
+            let _ = outgoing.send(response.to_string());
+        }
+    }
+
+    for subscription in subscriptions {
+        subscription.abort();
+    }
+
+    writer.abort();
+}
+
+fn error_response(id: Value, error: impl std::fmt::Display) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": -32000, "message": error.to_string() },
+    })
+}
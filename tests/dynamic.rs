@@ -0,0 +1,88 @@
+mod common;
+
+use common::StakeInfo;
+use crabtensor::api;
+use crabtensor::dynamic::{self, At};
+use crabtensor::mock::MockSubtensor;
+use crabtensor::AccountId;
+
+fn stake(coldkey: &AccountId) -> StakeInfo {
+    StakeInfo {
+        hotkey: AccountId::from([2; 32]),
+        coldkey: coldkey.clone(),
+        netuid: 3,
+        stake: 1_000,
+        locked: 10,
+        emission: 20,
+        tao_emission: 30,
+        drain: 40,
+        is_registered: true,
+        alpha_dividends: 50,
+    }
+}
+
+/// A mock whose stake info runtime API returns a type the compiled-in metadata does not match.
+fn mock_with_stake(coldkey: &AccountId) -> MockSubtensor {
+    let mock = MockSubtensor::new(&common::metadata()).unwrap();
+    common::set_stake_info(&mock, coldkey, vec![stake(coldkey)]);
+
+    mock
+}
+
+#[tokio::test]
+async fn mismatched_runtime_api_falls_back_to_unvalidated_decoding() {
+    let coldkey = AccountId::from([1; 32]);
+    let mock = mock_with_stake(&coldkey);
+    let client = mock.client();
+    let at = mock.latest_hash().into();
+
+    // The static payload alone is rejected, as its hash does not match the node's metadata
+    let validated = client
+        .runtime_api()
+        .at(mock.latest_hash())
+        .call(
+            api::apis()
+                .stake_info_runtime_api()
+                .get_stake_info_for_coldkey(coldkey.clone()),
+        )
+        .await;
+
+    assert!(validated.is_err());
+
+    let stake = dynamic::stake_info(&client, at, coldkey.clone())
+        .await
+        .unwrap();
+
+    assert_eq!(stake.len(), 1);
+    assert_eq!(stake[0].hotkey, AccountId::from([2; 32]));
+    assert_eq!(stake[0].coldkey, coldkey);
+    assert_eq!(stake[0].netuid, 3);
+    assert_eq!(stake[0].stake, 1_000);
+    assert_eq!(stake[0].drain, 40);
+    assert!(stake[0].is_registered);
+}
+
+#[tokio::test]
+async fn value_calls_expose_fields_the_codegen_does_not_know() {
+    let coldkey = AccountId::from([1; 32]);
+    let mock = mock_with_stake(&coldkey);
+    let client = mock.client();
+
+    let value = dynamic::stake_info_value(&client, mock.latest_hash().into(), coldkey)
+        .await
+        .unwrap();
+
+    let entry = value.at(0).unwrap();
+
+    assert_eq!(
+        entry
+            .at("alpha_dividends")
+            .and_then(|value| value.as_u128()),
+        Some(50)
+    );
+    assert_eq!(
+        entry.at("netuid").and_then(|value| value.as_u128()),
+        Some(3)
+    );
+    assert!(entry.at("missing").is_none());
+}
@@ -0,0 +1,432 @@
+#![cfg(feature = "indexer")]
+
+mod common;
+
+use common::{BalancesEvent, LegacySubtensorEvent, RuntimeEvent, SubtensorEvent, SystemEvent};
+use crabtensor::indexer::{Indexer, IndexerError, IndexerOptions};
+use crabtensor::mock::MockSubtensor;
+use crabtensor::sign::PairSigner;
+use crabtensor::subtensor::Subtensor;
+use crabtensor::weights::{set_weights_payload, NormalizedWeight};
+use crabtensor::{api, AccountId};
+use rusqlite::Connection;
+use sp_core::{sr25519, Pair};
+use subxt::backend::rpc::RpcClient;
+use subxt::tx::Payload;
+
+const NETUID: u16 = 1;
+
+async fn indexer(mock: &MockSubtensor) -> Indexer {
+    Indexer::new(
+        RpcClient::new(mock.clone()),
+        Connection::open_in_memory().unwrap(),
+        IndexerOptions::default(),
+    )
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn indexes_only_missing_blocks_of_overlapping_ranges() {
+    let mock = MockSubtensor::new(&common::metadata()).unwrap();
+    mock.produce_blocks_until(30);
+
+    let mut indexer = indexer(&mock).await;
+
+    assert_eq!(indexer.index(20..=25).await.unwrap(), 6);
+
+    // A range below the last indexed block is still indexed
+    assert_eq!(indexer.index(5..=10).await.unwrap(), 6);
+
+    // Only the blocks between the two earlier ranges and after them are written
+    assert_eq!(indexer.index(5..=30).await.unwrap(), 14);
+    assert_eq!(indexer.index(5..=30).await.unwrap(), 0);
+
+    assert_eq!(indexer.indexed_blocks(&(0..=30)).unwrap().len(), 26);
+    assert_eq!(indexer.last_indexed_block().unwrap(), Some(30));
+}
+
+#[tokio::test]
+async fn rejects_empty_ranges() {
+    let mock = MockSubtensor::new(&common::metadata()).unwrap();
+    let mut indexer = indexer(&mock).await;
+
+    #[allow(clippy::reversed_empty_ranges)]
+    let result = indexer.index(60..=50).await;
+
+    assert!(matches!(
+        result,
+        Err(IndexerError::EmptyRange { start: 60, end: 50 })
+    ));
+}
+
+#[tokio::test]
+async fn writes_rows_decoded_across_runtime_versions() {
+    let signer = PairSigner::new(sr25519::Pair::from_seed(&[1; 32]));
+    let hotkey = signer.account_id().clone();
+    let coldkey = AccountId::from([2; 32]);
+
+    // Block 1, from before stake events carried the coldkey and subnet
+    let mock = MockSubtensor::new(&common::legacy_metadata()).unwrap();
+    mock.set_runtime_version(1, 1);
+    mock.produce_block();
+    common::set_events(
+        &mock,
+        vec![(
+            None,
+            RuntimeEvent::SubtensorModule(LegacySubtensorEvent::StakeAdded(hotkey.clone(), 500)),
+        )],
+    );
+
+    let mut indexer = indexer(&mock).await;
+
+    assert_eq!(indexer.index(1..=1).await.unwrap(), 1);
+
+    mock.set_metadata(&common::metadata()).unwrap();
+    mock.set_runtime_version(2, 1);
+
+    let client = mock.client();
+    // Block 2
+    submit(
+        &client,
+        &signer,
+        set_weights_payload(
+            NETUID,
+            vec![
+                NormalizedWeight {
+                    uid: 1,
+                    weight: 100,
+                },
+                NormalizedWeight {
+                    uid: 2,
+                    weight: 200,
+                },
+            ],
+            0,
+        ),
+    )
+    .await;
+    common::set_events(
+        &mock,
+        vec![
+            (
+                Some(0),
+                RuntimeEvent::SubtensorModule(SubtensorEvent::WeightsSet(NETUID, 3)),
+            ),
+            (Some(0), RuntimeEvent::System(SystemEvent::ExtrinsicSuccess)),
+        ],
+    );
+
+    // Block 3, serving at 10.0.0.1:8091, which fails
+    submit(
+        &client,
+        &signer,
+        api::tx()
+            .subtensor_module()
+            .serve_axon(NETUID, 100, 0x0a00_0001, 8091, 4, 4, 0, 0),
+    )
+    .await;
+    common::set_events(
+        &mock,
+        vec![
+            (
+                Some(0),
+                RuntimeEvent::SubtensorModule(SubtensorEvent::AxonServed(NETUID, hotkey.clone())),
+            ),
+            (Some(0), RuntimeEvent::System(SystemEvent::ExtrinsicFailed)),
+        ],
+    );
+
+    // Block 4, with events emitted outside of extrinsics
+    mock.produce_block();
+    common::set_events(
+        &mock,
+        vec![
+            (
+                None,
+                RuntimeEvent::SubtensorModule(SubtensorEvent::NeuronRegistered(
+                    NETUID,
+                    5,
+                    hotkey.clone(),
+                )),
+            ),
+            (
+                None,
+                RuntimeEvent::SubtensorModule(SubtensorEvent::StakeAdded(
+                    coldkey.clone(),
+                    hotkey.clone(),
+                    1000,
+                    900,
+                    NETUID,
+                    1,
+                )),
+            ),
+            (
+                None,
+                RuntimeEvent::SubtensorModule(SubtensorEvent::StakeRemoved(
+                    coldkey.clone(),
+                    hotkey.clone(),
+                    20,
+                    10,
+                    2,
+                    1,
+                )),
+            ),
+            (
+                None,
+                RuntimeEvent::Balances(BalancesEvent::Transfer {
+                    from: coldkey.clone(),
+                    to: hotkey.clone(),
+                    amount: 42,
+                }),
+            ),
+            // Above what SQLite stores as an integer
+            (
+                None,
+                RuntimeEvent::Balances(BalancesEvent::Transfer {
+                    from: coldkey.clone(),
+                    to: hotkey.clone(),
+                    amount: u64::MAX,
+                }),
+            ),
+        ],
+    );
+
+    mock.produce_block();
+    common::set_events::<SubtensorEvent>(&mock, Vec::new());
+
+    assert_eq!(indexer.index(1..=5).await.unwrap(), 4);
+
+    let hotkey = hotkey.to_string();
+    let coldkey = coldkey.to_string();
+    let connection = indexer.connection();
+
+    let spec_versions: Vec<(u32, u32)> = rows(
+        connection,
+        "SELECT number, spec_version FROM blocks ORDER BY number",
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    );
+
+    assert_eq!(spec_versions, vec![(1, 1), (2, 2), (3, 2), (4, 2), (5, 2)]);
+
+    let extrinsics: Vec<(u32, u32, String, String, Option<String>, bool)> = rows(
+        connection,
+        "SELECT block, idx, pallet, call, signer, success FROM extrinsics ORDER BY block",
+        |row| {
+            Ok((
+                row.get(0)?,
+                row.get(1)?,
+                row.get(2)?,
+                row.get(3)?,
+                row.get(4)?,
+                row.get(5)?,
+            ))
+        },
+    );
+
+    assert_eq!(
+        extrinsics,
+        vec![
+            (
+                2,
+                0,
+                "SubtensorModule".into(),
+                "set_weights".into(),
+                Some(hotkey.clone()),
+                true
+            ),
+            (
+                3,
+                0,
+                "SubtensorModule".into(),
+                "serve_axon".into(),
+                Some(hotkey.clone()),
+                false
+            ),
+        ]
+    );
+
+    let weight_sets: Vec<WeightSetRow> = rows(
+        connection,
+        "SELECT block, extrinsic_index, netuid, uid, hotkey, weights FROM weight_sets",
+        |row| {
+            Ok((
+                row.get(0)?,
+                row.get(1)?,
+                row.get(2)?,
+                row.get(3)?,
+                row.get(4)?,
+                row.get(5)?,
+            ))
+        },
+    );
+
+    assert_eq!(
+        weight_sets,
+        vec![(
+            2,
+            Some(0),
+            NETUID,
+            3,
+            Some(hotkey.clone()),
+            Some("[[1,100],[2,200]]".into())
+        )]
+    );
+
+    let axon_updates: Vec<AxonUpdateRow> =
+        rows(
+            connection,
+            "SELECT block, extrinsic_index, netuid, hotkey, address, protocol, version FROM axon_updates",
+            |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                    row.get(6)?,
+                ))
+            },
+        );
+
+    assert_eq!(
+        axon_updates,
+        vec![(
+            3,
+            Some(0),
+            NETUID,
+            hotkey.clone(),
+            Some("10.0.0.1:8091".into()),
+            Some(4),
+            Some(100)
+        )]
+    );
+
+    let registrations: Vec<(u32, Option<u32>, u16, u16, String)> = rows(
+        connection,
+        "SELECT block, extrinsic_index, netuid, uid, hotkey FROM registrations",
+        |row| {
+            Ok((
+                row.get(0)?,
+                row.get(1)?,
+                row.get(2)?,
+                row.get(3)?,
+                row.get(4)?,
+            ))
+        },
+    );
+
+    assert_eq!(registrations, vec![(4, None, NETUID, 5, hotkey.clone())]);
+
+    let stake_changes: Vec<StakeChangeRow> =
+        rows(
+            connection,
+            "SELECT block, kind, coldkey, hotkey, netuid, tao, alpha FROM stake_changes ORDER BY block, rowid",
+            |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                    row.get(6)?,
+                ))
+            },
+        );
+
+    assert_eq!(
+        stake_changes,
+        vec![
+            (1, "add".into(), None, hotkey.clone(), None, Some(500), None),
+            (
+                4,
+                "add".into(),
+                Some(coldkey.clone()),
+                hotkey.clone(),
+                Some(NETUID),
+                Some(1000),
+                Some(900)
+            ),
+            (
+                4,
+                "remove".into(),
+                Some(coldkey.clone()),
+                hotkey.clone(),
+                Some(2),
+                Some(20),
+                Some(10)
+            ),
+        ]
+    );
+
+    let transfers: Vec<TransferRow> = rows(
+        connection,
+        "SELECT block, extrinsic_index, sender, recipient, amount FROM transfers ORDER BY rowid",
+        |row| {
+            Ok((
+                row.get(0)?,
+                row.get(1)?,
+                row.get(2)?,
+                row.get(3)?,
+                row.get(4)?,
+            ))
+        },
+    );
+
+    assert_eq!(
+        transfers,
+        vec![
+            (4, None, coldkey.clone(), hotkey.clone(), Some(42)),
+            (4, None, coldkey, hotkey, None),
+        ]
+    );
+}
+
+type WeightSetRow = (u32, Option<u32>, u16, u16, Option<String>, Option<String>);
+type AxonUpdateRow = (
+    u32,
+    Option<u32>,
+    u16,
+    String,
+    Option<String>,
+    Option<u8>,
+    Option<u32>,
+);
+type StakeChangeRow = (
+    u32,
+    String,
+    Option<String>,
+    String,
+    Option<u16>,
+    Option<i64>,
+    Option<i64>,
+);
+type TransferRow = (u32, Option<u32>, String, String, Option<i64>);
+
+/// Signs and submits `payload`, which seals it into a new block.
+async fn submit(client: &Subtensor, signer: &PairSigner, payload: impl Payload) {
+    client
+        .tx()
+        .create_signed(&payload, signer, Default::default())
+        .await
+        .unwrap()
+        .submit()
+        .await
+        .unwrap();
+}
+
+fn rows<T>(
+    connection: &Connection,
+    query: &str,
+    row: impl FnMut(&rusqlite::Row) -> rusqlite::Result<T>,
+) -> Vec<T> {
+    connection
+        .prepare(query)
+        .unwrap()
+        .query_map([], row)
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
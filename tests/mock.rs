@@ -0,0 +1,120 @@
+mod common;
+
+use crabtensor::api;
+use crabtensor::api::subtensor_module::calls::types::SetWeights;
+use crabtensor::mock::MockSubtensor;
+use crabtensor::sign::PairSigner;
+use crabtensor::subtensor::Subtensor;
+use crabtensor::weights::{set_weights_payload, NormalizedWeight};
+use crabtensor::AccountId;
+use sp_core::{sr25519, Pair};
+
+const NETUID: u16 = 1;
+
+/// A validator scoring every registered miner by its uid, reading the metagraph the way one would from a node.
+async fn run_validator(client: &Subtensor, signer: &PairSigner) -> Result<(), subxt::Error> {
+    let storage = client.storage().at_latest().await?;
+
+    let n = storage
+        .fetch_or_default(&api::storage().subtensor_module().subnetwork_n(NETUID))
+        .await?;
+
+    let mut uids = Vec::new();
+
+    for uid in 0..n {
+        let hotkey = storage
+            .fetch_or_default(&api::storage().subtensor_module().keys(NETUID, uid))
+            .await?;
+
+        if hotkey != *signer.account_id() {
+            uids.push(uid);
+        }
+    }
+
+    // Later uids score higher, scaled so the best miner gets `u16::MAX`
+    let best = uids.iter().copied().max().unwrap_or_default() as u32 + 1;
+    let weights = uids
+        .iter()
+        .map(|uid| NormalizedWeight {
+            uid: *uid,
+            weight: ((*uid as u32 + 1) * u16::MAX as u32 / best) as u16,
+        })
+        .collect();
+
+    client
+        .tx()
+        .sign_and_submit_then_watch_default(&set_weights_payload(NETUID, weights, 7), signer)
+        .await?
+        .wait_for_finalized()
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn validator_sets_weights_from_a_scripted_metagraph() {
+    let mock = MockSubtensor::new(&common::metadata()).unwrap();
+    let signer = PairSigner::new(sr25519::Pair::from_seed(&[1; 32]));
+
+    let hotkeys = [
+        signer.account_id().clone(),
+        AccountId::from([2; 32]),
+        AccountId::from([3; 32]),
+        AccountId::from([4; 32]),
+    ];
+
+    mock.set_storage(
+        &api::storage().subtensor_module().subnetwork_n(NETUID),
+        &(hotkeys.len() as u16),
+    )
+    .unwrap();
+
+    for (uid, hotkey) in hotkeys.iter().enumerate() {
+        mock.set_storage(
+            &api::storage().subtensor_module().keys(NETUID, uid as u16),
+            hotkey,
+        )
+        .unwrap();
+    }
+
+    mock.produce_block();
+
+    run_validator(&mock.client(), &signer).await.unwrap();
+
+    let calls = mock.submitted_calls::<SetWeights>().unwrap();
+
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].netuid, NETUID);
+    assert_eq!(calls[0].dests, vec![1, 2, 3]);
+    assert_eq!(calls[0].weights, vec![32767, 49151, 65535]);
+    assert_eq!(calls[0].version_key, 7);
+
+    // The submission was sealed into a block of its own on top of the scripted one
+    assert_eq!(mock.block_number(), 2);
+}
+
+#[tokio::test]
+async fn storage_set_on_a_block_is_inherited_but_not_retroactive() {
+    let mock = MockSubtensor::new(&common::metadata()).unwrap();
+    let address = api::storage().subtensor_module().subnetwork_n(NETUID);
+
+    let genesis = mock.latest_hash();
+    mock.produce_block();
+    mock.set_storage(&address, &5u16).unwrap();
+    mock.produce_block();
+
+    let client = mock.client();
+
+    let before = client.storage().at(genesis).fetch(&address).await.unwrap();
+    let after = client
+        .storage()
+        .at_latest()
+        .await
+        .unwrap()
+        .fetch(&address)
+        .await
+        .unwrap();
+
+    assert_eq!(before, None);
+    assert_eq!(after, Some(5));
+}
@@ -0,0 +1,454 @@
+mod common;
+
+use common::{RuntimeEvent, StakeInfo, SubtensorEvent, KEY_SWAP_COST, KEY_SWAP_ON_SUBNET_COST};
+use crabtensor::api;
+use crabtensor::api::runtime_types::frame_system::AccountInfo;
+use crabtensor::api::runtime_types::pallet_balances::types::{AccountData, ExtraFlags};
+use crabtensor::mock::MockSubtensor;
+use crabtensor::swap::{
+    plan_coldkey_swap, plan_hotkey_swap, wait_for_coldkey_swap, ColdkeySwapExecution,
+    ColdkeySwapPlan, HotkeySwapPlan, SubnetHolding, SwapError, COLDKEY_SWAP_GRACE_BLOCKS,
+};
+use crabtensor::AccountId;
+use tokio::task::{yield_now, JoinHandle};
+
+fn account(byte: u8) -> AccountId {
+    AccountId::from([byte; 32])
+}
+
+fn set_free_balance(mock: &MockSubtensor, coldkey: &AccountId, free: u64) {
+    let account = AccountInfo {
+        nonce: 0,
+        consumers: 0,
+        providers: 1,
+        sufficients: 0,
+        data: AccountData {
+            free,
+            reserved: 0,
+            frozen: 0,
+            flags: ExtraFlags(0),
+        },
+    };
+
+    mock.set_storage(&api::storage().system().account(coldkey), &account)
+        .unwrap();
+}
+
+/// Registers `hotkey` on `netuid` with `uid` and the total alpha staked to it.
+fn register(mock: &MockSubtensor, hotkey: &AccountId, netuid: u16, uid: u16, alpha: u64) {
+    let subtensor_module = api::storage().subtensor_module();
+
+    mock.set_storage(&subtensor_module.is_network_member(hotkey, netuid), &true)
+        .unwrap();
+    mock.set_storage(&subtensor_module.uids(netuid, hotkey), &uid)
+        .unwrap();
+    mock.set_storage(&subtensor_module.total_hotkey_alpha(hotkey, netuid), &alpha)
+        .unwrap();
+}
+
+fn set_owned_hotkeys(mock: &MockSubtensor, coldkey: &AccountId, hotkeys: Vec<AccountId>) {
+    mock.set_storage(
+        &api::storage().subtensor_module().owned_hotkeys(coldkey),
+        &hotkeys,
+    )
+    .unwrap();
+}
+
+fn schedule_swap(mock: &MockSubtensor, coldkey: &AccountId, execution_block: u32, new: &AccountId) {
+    mock.set_storage(
+        &api::storage()
+            .subtensor_module()
+            .coldkey_swap_scheduled(coldkey),
+        &(execution_block, new.clone()),
+    )
+    .unwrap();
+}
+
+/// A coldkey owning a hotkey registered on subnets 1 and 2, able to pay for any swap.
+fn hotkey_owner() -> (MockSubtensor, AccountId, AccountId) {
+    let mock = MockSubtensor::new(&common::metadata()).unwrap();
+    let coldkey = account(1);
+    let hotkey = account(2);
+
+    mock.set_storage(&api::storage().subtensor_module().owner(&hotkey), &coldkey)
+        .unwrap();
+    register(&mock, &hotkey, 1, 10, 1_000);
+    register(&mock, &hotkey, 2, 20, 2_000);
+    set_free_balance(&mock, &coldkey, KEY_SWAP_COST);
+
+    (mock, coldkey, hotkey)
+}
+
+async fn plan_hotkey(
+    mock: &MockSubtensor,
+    coldkey: &AccountId,
+    old_hotkey: &AccountId,
+    new_hotkey: &AccountId,
+    netuid: Option<u16>,
+) -> Result<HotkeySwapPlan, SwapError> {
+    plan_hotkey_swap(
+        &mock.client(),
+        mock.latest_hash().into(),
+        coldkey,
+        old_hotkey,
+        new_hotkey,
+        netuid,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn plans_a_hotkey_swap_on_the_subnets_in_scope() {
+    let (mock, coldkey, hotkey) = hotkey_owner();
+    let new_hotkey = account(3);
+
+    let plan = plan_hotkey(&mock, &coldkey, &hotkey, &new_hotkey, None)
+        .await
+        .unwrap();
+
+    assert_eq!(plan.cost, KEY_SWAP_COST);
+    assert_eq!(plan.free_balance, KEY_SWAP_COST);
+    assert_eq!(plan.netuids(), vec![1, 2]);
+    assert_eq!(
+        plan.holdings[1],
+        SubnetHolding {
+            netuid: 2,
+            uid: 20,
+            alpha: 2_000,
+        }
+    );
+
+    let plan = plan_hotkey(&mock, &coldkey, &hotkey, &new_hotkey, Some(2))
+        .await
+        .unwrap();
+
+    assert_eq!(plan.cost, KEY_SWAP_ON_SUBNET_COST);
+    assert_eq!(plan.netuids(), vec![2]);
+}
+
+#[tokio::test]
+async fn rejects_swapping_a_hotkey_for_itself() {
+    let (mock, coldkey, hotkey) = hotkey_owner();
+
+    let result = plan_hotkey(&mock, &coldkey, &hotkey, &hotkey, None).await;
+
+    assert!(matches!(result, Err(SwapError::SameKey)));
+}
+
+#[tokio::test]
+async fn rejects_hotkeys_the_coldkey_does_not_own() {
+    let (mock, _, hotkey) = hotkey_owner();
+    let other = account(4);
+
+    let result = plan_hotkey(&mock, &other, &hotkey, &account(3), None).await;
+
+    assert!(matches!(
+        result,
+        Err(SwapError::NotOwner { owner: Some(owner), .. }) if owner == account(1)
+    ));
+
+    // A hotkey without an owner is not registered at all
+    let result = plan_hotkey(&mock, &other, &account(5), &account(3), None).await;
+
+    assert!(matches!(
+        result,
+        Err(SwapError::NotOwner { owner: None, .. })
+    ));
+}
+
+#[tokio::test]
+async fn rejects_new_hotkeys_registered_on_the_subnets_in_scope() {
+    let (mock, coldkey, hotkey) = hotkey_owner();
+    let new_hotkey = account(3);
+
+    register(&mock, &new_hotkey, 2, 30, 0);
+    register(&mock, &new_hotkey, 3, 30, 0);
+
+    let result = plan_hotkey(&mock, &coldkey, &hotkey, &new_hotkey, None).await;
+
+    assert!(matches!(
+        result,
+        Err(SwapError::NewHotkeyRegistered { netuids, .. }) if netuids == vec![2, 3]
+    ));
+
+    let result = plan_hotkey(&mock, &coldkey, &hotkey, &new_hotkey, Some(2)).await;
+
+    assert!(matches!(
+        result,
+        Err(SwapError::NewHotkeyRegistered { netuids, .. }) if netuids == vec![2]
+    ));
+
+    // Registrations on other subnets do not stand in the way of a single subnet swap
+    let plan = plan_hotkey(&mock, &coldkey, &hotkey, &new_hotkey, Some(1))
+        .await
+        .unwrap();
+
+    assert_eq!(plan.netuids(), vec![1]);
+}
+
+#[tokio::test]
+async fn rejects_hotkey_swaps_the_coldkey_can_not_pay_for() {
+    let (mock, coldkey, hotkey) = hotkey_owner();
+    set_free_balance(&mock, &coldkey, KEY_SWAP_ON_SUBNET_COST);
+
+    let result = plan_hotkey(&mock, &coldkey, &hotkey, &account(3), None).await;
+
+    assert!(matches!(
+        result,
+        Err(SwapError::InsufficientBalance {
+            cost: KEY_SWAP_COST,
+            free: KEY_SWAP_ON_SUBNET_COST,
+            ..
+        })
+    ));
+
+    // Swapping on a single subnet costs less
+    assert!(plan_hotkey(&mock, &coldkey, &hotkey, &account(3), Some(1))
+        .await
+        .is_ok());
+}
+
+/// A coldkey owning and staking to a hotkey, able to pay for a swap, at block 5.
+fn coldkey_owner() -> (MockSubtensor, AccountId) {
+    let mock = MockSubtensor::new(&common::metadata()).unwrap();
+    let coldkey = account(1);
+    let hotkey = account(2);
+
+    mock.produce_blocks_until(5);
+    set_owned_hotkeys(&mock, &coldkey, vec![hotkey.clone()]);
+    set_free_balance(&mock, &coldkey, KEY_SWAP_COST);
+    common::set_stake_info(
+        &mock,
+        &coldkey,
+        vec![StakeInfo {
+            hotkey,
+            coldkey: coldkey.clone(),
+            netuid: 1,
+            stake: 1_000,
+            locked: 0,
+            emission: 0,
+            tao_emission: 0,
+            drain: 0,
+            is_registered: true,
+            alpha_dividends: 0,
+        }],
+    );
+
+    (mock, coldkey)
+}
+
+async fn plan_coldkey(
+    mock: &MockSubtensor,
+    old_coldkey: &AccountId,
+    new_coldkey: &AccountId,
+) -> Result<ColdkeySwapPlan, SwapError> {
+    plan_coldkey_swap(
+        &mock.client(),
+        mock.latest_hash().into(),
+        old_coldkey,
+        new_coldkey,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn plans_a_coldkey_swap() {
+    let (mock, coldkey) = coldkey_owner();
+
+    let plan = plan_coldkey(&mock, &coldkey, &account(3)).await.unwrap();
+
+    assert_eq!(plan.cost, KEY_SWAP_COST);
+    assert_eq!(plan.hotkeys, vec![account(2)]);
+    assert_eq!(plan.netuids(), vec![1]);
+}
+
+#[tokio::test]
+async fn rejects_swapping_a_coldkey_for_itself() {
+    let (mock, coldkey) = coldkey_owner();
+
+    let result = plan_coldkey(&mock, &coldkey, &coldkey).await;
+
+    assert!(matches!(result, Err(SwapError::SameKey)));
+}
+
+#[tokio::test]
+async fn rejects_coldkey_swaps_already_scheduled() {
+    let (mock, coldkey) = coldkey_owner();
+    let scheduled = account(4);
+
+    schedule_swap(&mock, &coldkey, 5, &scheduled);
+
+    let result = plan_coldkey(&mock, &coldkey, &account(3)).await;
+
+    assert!(matches!(
+        result,
+        Err(SwapError::AlreadyScheduled { new_coldkey, execution_block: 5, .. }) if new_coldkey == scheduled
+    ));
+
+    // A swap whose block passed no longer stands in the way
+    mock.produce_block();
+
+    assert!(plan_coldkey(&mock, &coldkey, &account(3)).await.is_ok());
+}
+
+#[tokio::test]
+async fn rejects_coldkey_swaps_the_coldkey_can_not_pay_for() {
+    let (mock, coldkey) = coldkey_owner();
+    set_free_balance(&mock, &coldkey, KEY_SWAP_COST - 1);
+
+    let result = plan_coldkey(&mock, &coldkey, &account(3)).await;
+
+    assert!(matches!(
+        result,
+        Err(SwapError::InsufficientBalance { cost: KEY_SWAP_COST, free, .. }) if free == KEY_SWAP_COST - 1
+    ));
+}
+
+/// Starts waiting for the swap of `coldkey`, returning once the waiter follows new blocks. Tests run on a single
+/// thread, so the waiter only sees blocks produced and filled in before the test awaits again.
+async fn start_waiting(
+    mock: &MockSubtensor,
+    coldkey: &AccountId,
+) -> JoinHandle<Result<ColdkeySwapExecution, SwapError>> {
+    let client = mock.client();
+    let coldkey = coldkey.clone();
+    let waiting = tokio::spawn(async move { wait_for_coldkey_swap(&client, &coldkey).await });
+
+    while mock.head_subscribers() == 0 {
+        yield_now().await;
+    }
+
+    waiting
+}
+
+fn swap_executed(mock: &MockSubtensor, coldkey: &AccountId, new_coldkey: &AccountId) {
+    mock.remove_storage(
+        &api::storage()
+            .subtensor_module()
+            .coldkey_swap_scheduled(coldkey),
+    )
+    .unwrap();
+    set_owned_hotkeys(mock, coldkey, Vec::new());
+    set_owned_hotkeys(mock, new_coldkey, vec![account(2)]);
+    common::set_events(
+        mock,
+        vec![(
+            None,
+            RuntimeEvent::SubtensorModule(SubtensorEvent::ColdkeySwapped {
+                old_coldkey: coldkey.clone(),
+                new_coldkey: new_coldkey.clone(),
+                swap_cost: KEY_SWAP_COST,
+            }),
+        )],
+    );
+}
+
+#[tokio::test]
+async fn keeps_waiting_for_a_deferred_coldkey_swap() {
+    let (mock, coldkey) = coldkey_owner();
+    let new_coldkey = account(3);
+    schedule_swap(&mock, &coldkey, 5, &new_coldkey);
+
+    let waiting = start_waiting(&mock, &coldkey).await;
+
+    // Still scheduled and the hotkeys have not moved, so the scheduler deferred it
+    mock.produce_block();
+    mock.produce_block();
+    swap_executed(&mock, &coldkey, &new_coldkey);
+
+    let execution = waiting.await.unwrap().unwrap();
+
+    assert_eq!(execution.block_number, 7);
+    assert_eq!(execution.new_coldkey, new_coldkey);
+    assert_eq!(execution.swap_cost, KEY_SWAP_COST);
+    assert_eq!(execution.events.len(), 1);
+}
+
+#[tokio::test]
+async fn follows_a_rescheduled_coldkey_swap() {
+    let (mock, coldkey) = coldkey_owner();
+    let new_coldkey = account(3);
+    schedule_swap(&mock, &coldkey, 5, &new_coldkey);
+
+    let waiting = start_waiting(&mock, &coldkey).await;
+
+    mock.produce_block();
+    schedule_swap(&mock, &coldkey, 10, &new_coldkey);
+    mock.produce_blocks_until(10);
+    swap_executed(&mock, &coldkey, &new_coldkey);
+
+    let execution = waiting.await.unwrap().unwrap();
+
+    assert_eq!(execution.block_number, 10);
+}
+
+#[tokio::test]
+async fn gives_up_on_a_dropped_coldkey_swap() {
+    let (mock, coldkey) = coldkey_owner();
+    schedule_swap(&mock, &coldkey, 5, &account(3));
+
+    let waiting = start_waiting(&mock, &coldkey).await;
+
+    mock.produce_block();
+    mock.remove_storage(
+        &api::storage()
+            .subtensor_module()
+            .coldkey_swap_scheduled(&coldkey),
+    )
+    .unwrap();
+
+    assert!(matches!(
+        waiting.await.unwrap(),
+        Err(SwapError::NotExecuted {
+            execution_block: 5,
+            ..
+        })
+    ));
+}
+
+#[tokio::test]
+async fn gives_up_once_the_hotkeys_moved_without_a_swap() {
+    let (mock, coldkey) = coldkey_owner();
+    schedule_swap(&mock, &coldkey, 5, &account(3));
+
+    let waiting = start_waiting(&mock, &coldkey).await;
+
+    // Still scheduled, but the coldkey no longer holds what the swap would move
+    mock.produce_block();
+    set_owned_hotkeys(&mock, &coldkey, Vec::new());
+
+    assert!(matches!(
+        waiting.await.unwrap(),
+        Err(SwapError::NotExecuted {
+            execution_block: 5,
+            ..
+        })
+    ));
+}
+
+#[tokio::test]
+async fn gives_up_on_a_deferred_coldkey_swap_after_the_grace_blocks() {
+    let (mock, coldkey) = coldkey_owner();
+    schedule_swap(&mock, &coldkey, 5, &account(3));
+
+    let waiting = start_waiting(&mock, &coldkey).await;
+
+    mock.produce_blocks_until(5 + COLDKEY_SWAP_GRACE_BLOCKS);
+
+    assert!(matches!(
+        waiting.await.unwrap(),
+        Err(SwapError::NotExecuted {
+            execution_block: 5,
+            ..
+        })
+    ));
+}
+
+#[tokio::test]
+async fn fails_without_a_scheduled_coldkey_swap() {
+    let (mock, coldkey) = coldkey_owner();
+
+    let result = wait_for_coldkey_swap(&mock.client(), &coldkey).await;
+
+    assert!(matches!(result, Err(SwapError::NotScheduled(unscheduled)) if unscheduled == coldkey));
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use crabtensor::upgrade::compare_pallets;
+use crabtensor::{METADATA_CALL_HASHES, METADATA_STORAGE_HASHES};
+use subxt::ext::codec::Decode;
+use subxt::Metadata;
+
+#[test]
+fn snapshot_validation_hashes_are_extracted() {
+    assert!(METADATA_CALL_HASHES
+        .iter()
+        .any(|(pallet, call, _)| *pallet == "SubtensorModule" && *call == "set_weights"));
+    assert!(METADATA_STORAGE_HASHES
+        .iter()
+        .any(|(pallet, entry, _)| *pallet == "SubtensorModule" && *entry == "Keys"));
+}
+
+#[test]
+fn compares_metadata_against_the_compiled_in_codegen() {
+    let metadata = Metadata::decode(&mut &common::metadata()[..]).unwrap();
+    let pallets = compare_pallets(&metadata);
+
+    let subtensor = pallets
+        .iter()
+        .find(|pallet| pallet.pallet == "SubtensorModule")
+        .unwrap();
+
+    // Only the calls the tests make are kept with the shape the codegen was generated for
+    assert!(!subtensor.unchanged);
+    assert!(!subtensor.changed_calls.contains(&"set_weights".to_string()));
+    assert!(!subtensor.changed_calls.contains(&"serve_axon".to_string()));
+    assert!(!subtensor
+        .changed_calls
+        .contains(&"serve_prometheus".to_string()));
+    assert!(subtensor.changed_calls.contains(&"add_stake".to_string()));
+
+    let system = pallets
+        .iter()
+        .find(|pallet| pallet.pallet == "System")
+        .unwrap();
+
+    assert!(!system.unchanged);
+    assert!(system.changed_calls.contains(&"remark".to_string()));
+}